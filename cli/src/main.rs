@@ -54,14 +54,29 @@ use substrate_api_client::{
 use itc_rpc_client::direct_client::{DirectApi, DirectClient as DirectWorkerApi};
 use itp_api_client_extensions::{PalletNftsApi, PalletTeerexApi};
 use itp_types::{
-	RetrieveNftSecretRequest, RpcRequest, RpcResponse, SignableRequest, StoreNftSecretRequest,
+	BlockNumber, DecryptWithNftKeyRequest, EncryptedNftSecret, GenerateNftKeyRequest,
+	GetNftSecretCommitmentRequest, GrantNftAccessRequest, Header, ListMyNftSecretsRequest,
+	ListNftSecretSharesRequest, NftCiphertext, NftPublicKey, NftSecretBatchResult,
+	NftSecretCommitmentProof, NftSecretHandover, NftSecretInfo, NftSecretWriteMode, NftSignature,
+	RegisterRecipientKeyRequest, RemoveNftSecretRequest, RetrieveCapsuleKeyRequest,
+	RetrieveNftSecretHandoverRequest, RetrieveNftSecretRequest, RetrieveNftSecretsRequest,
+	RetrieveNftSeriesSecretRequest, RevokeNftAccessRequest, RpcRequest, RpcResponse,
+	SignWithNftKeyRequest, SignableRequest, SignedRequest, StoreCapsuleKeyRequest,
+	StoreNftSecretRequest, StoreNftSeriesSecretRequest, StoreShieldedNftSecretRequest,
+	UnlockCondition,
 };
 use serde::{Deserialize, Serialize};
+use sgx_crypto_helper::{rsa3072::Rsa3072KeyPair, RsaKeyPair};
+use sgx_types::sgx_ec256_public_t;
+use sgx_ucrypto::{rsgx_rijndael128GCM_decrypt, rsgx_rijndael128GCM_encrypt, SgxEccHandle};
+use std::{convert::TryInto, fs};
 use substrate_client_keystore::{KeystoreExt, LocalKeystore};
 
 type AccountPublic = <Signature as Verify>::Signer;
 const KEYSTORE_PATH: &str = "my_keystore";
 const PREFUNDING_AMOUNT: u128 = 1_000_000_000;
+/// Number of parentchain blocks a signed request stays valid for.
+const REQUEST_VALIDITY_BLOCKS: BlockNumber = 10;
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
@@ -387,65 +402,1245 @@ fn main() {
 							.value_name("string")
 							.help("Secret share to be stored"),
 					)
+					.arg(
+						Arg::with_name("shielded")
+							.long("shielded")
+							.help("Encrypt the secret share with the shielding key of the enclave"),
+					)
+					.arg(
+						Arg::with_name("share-index")
+							.long("share-index")
+							.takes_value(true)
+							.default_value("0")
+							.value_name("U8")
+							.help("Index of the secret share"),
+					)
+					.arg(
+						Arg::with_name("unlock-block")
+							.long("unlock-block")
+							.takes_value(true)
+							.conflicts_with("unlock-timestamp")
+							.value_name("U32")
+							.help("Block number before which the secret is not released"),
+					)
+					.arg(
+						Arg::with_name("unlock-timestamp")
+							.long("unlock-timestamp")
+							.takes_value(true)
+							.value_name("U64")
+							.help("Unix timestamp in ms before which the secret is not released"),
+					)
+					.arg(
+						Arg::with_name("as-creator")
+							.long("as-creator")
+							.help("Write the secret as the creator of the NFT"),
+					)
+					.arg(
+						Arg::with_name("lock-in")
+							.long("lock-in")
+							.requires("as-creator")
+							.help("Prevent owners from overwriting or removing the secret"),
+					)
+				})
+				.description("Store a NFT secret share")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let arg_secret = matches.value_of("secret").unwrap();
+					let arg_share_index: u8 = matches
+						.value_of("share-index")
+						.unwrap()
+						.parse()
+						.expect("share-index cannot be converted to u8");
+					let unlock = match (
+						matches.value_of("unlock-block"),
+						matches.value_of("unlock-timestamp"),
+					) {
+						(Some(block_number), _) => Some(UnlockCondition::BlockNumber(
+							block_number.parse().expect("unlock-block cannot be converted to u32"),
+						)),
+						(None, Some(timestamp)) => Some(UnlockCondition::Timestamp(
+							timestamp.parse().expect("unlock-timestamp cannot be converted to u64"),
+						)),
+						(None, None) => None,
+					};
+					let mode = if matches.is_present("as-creator") {
+						NftSecretWriteMode::Creator { locked_in: matches.is_present("lock-in") }
+					} else {
+						NftSecretWriteMode::Owner
+					};
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let direct_api = get_worker_api_direct(matches);
+
+					// compose jsonrpc call
+					let jsonrpc_call: String = if matches.is_present("shielded") {
+						let shielding_key =
+							direct_api.get_rsa_pubkey().expect("failed to get shielding key");
+						let mut encrypted_secret = Vec::new();
+						shielding_key
+							.encrypt_buffer(arg_secret.as_bytes(), &mut encrypted_secret)
+							.expect("failed to encrypt secret");
+
+						let rpc_method = StoreShieldedNftSecretRequest::METHOD.to_owned();
+						let data = sign_request(
+							matches,
+							&direct_api,
+							StoreShieldedNftSecretRequest {
+								nft_id: arg_nft_id,
+								share_index: arg_share_index,
+								encrypted_secret,
+								unlock,
+								mode,
+							},
+							&account,
+						);
+						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode())
+					} else {
+						let rpc_method = StoreNftSecretRequest::METHOD.to_owned();
+						let data = sign_request(
+							matches,
+							&direct_api,
+							StoreNftSecretRequest {
+								nft_id: arg_nft_id,
+								share_index: arg_share_index,
+								secret: arg_secret.into(),
+								unlock,
+								mode,
+							},
+							&account,
+						);
+						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode())
+					};
+
+					// call the api
+					let response_str = match direct_api.get(&jsonrpc_call) {
+						Ok(resp) => resp,
+						Err(_) => panic!("Error when sending direct invocation call"),
+					};
+
+					// Decode the response
+					let response: RpcResponse<Option<String>> =
+						match serde_json::from_str(&response_str) {
+							Ok(resp) => resp,
+							Err(err_msg) => panic!(
+								"Error while deserialisation of the RpcResponse: {:?}",
+								err_msg
+							),
+						};
+
+					if let Some(error) = &response.error {
+						print!("Failed to store NFT secret");
+						let cli_response = CliResponseFormat::<String> {
+							status: false,
+							result: String::from_utf8(
+								error.message.clone().unwrap_or("".to_string()).into(),
+							)
+							.unwrap(),
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					} else {
+						let cli_response =
+							CliResponseFormat { status: true, result: "".to_string() };
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap())
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("retrieve-nft-secret")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+					.arg(
+						Arg::with_name("share-index")
+							.long("share-index")
+							.takes_value(true)
+							.default_value("0")
+							.value_name("U8")
+							.help("Index of the secret share"),
+					)
+				})
+				.description("Retrieve the secret share associated with a NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let arg_share_index: u8 = matches
+						.value_of("share-index")
+						.unwrap()
+						.parse()
+						.expect("share-index cannot be converted to u8");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let direct_api = get_worker_api_direct(matches);
+
+					// the secret is returned encrypted to an ephemeral key
+					let recipient_pair =
+						Rsa3072KeyPair::new().expect("failed to generate ephemeral rsa key");
+					let recipient_key = serde_json::to_vec(
+						&recipient_pair.export_pubkey().expect("failed to export rsa pubkey"),
+					)
+					.unwrap();
+
+					// compose jsonrpc call
+					let rpc_method = RetrieveNftSecretRequest::METHOD.to_owned();
+					let data = sign_request(
+						matches,
+						&direct_api,
+						RetrieveNftSecretRequest {
+							nft_id: arg_nft_id,
+							share_index: arg_share_index,
+							recipient_key: Some(recipient_key),
+						},
+						&account,
+					);
+					let jsonrpc_call: String =
+						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+					// call the api
+					let response_str = match direct_api.get(&jsonrpc_call) {
+						Ok(resp) => resp,
+						Err(_) => panic!("Error when sending direct invocation call"),
+					};
+
+					// Decode the response
+					let response: RpcResponse<Option<Vec<u8>>> =
+						match serde_json::from_str(&response_str) {
+							Ok(resp) => resp,
+							Err(err_msg) => panic!(
+								"Error while deserialisation of the RpcResponse: {:?}",
+								err_msg
+							),
+						};
+
+					if let Some(error) = &response.error {
+						//print!("Failed to retrieve NFT secret");
+						let cli_response = CliResponseFormat::<String> {
+							status: false,
+							result: String::from_utf8(
+								error.message.clone().unwrap_or("".to_string()).into(),
+							)
+							.unwrap(),
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					} else {
+						let encrypted_secret =
+							EncryptedNftSecret::decode(&mut response.result.unwrap().as_slice())
+								.expect("failed to decode encrypted secret");
+						let secret = decrypt_nft_secret(
+							&(arg_nft_id, arg_share_index).encode(),
+							encrypted_secret,
+							&recipient_pair,
+						);
+						let cli_response = CliResponseFormat {
+							status: true,
+							result: String::from_utf8(secret).unwrap(),
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("retrieve-nft-secrets")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-ids")
+							.takes_value(true)
+							.required(true)
+							.multiple(true)
+							.value_name("U32")
+							.help("Ids of the NFTs"),
+					)
+					.arg(
+						Arg::with_name("share-index")
+							.long("share-index")
+							.takes_value(true)
+							.default_value("0")
+							.value_name("U8")
+							.help("Index of the secret share"),
+					)
+				})
+				.description("Retrieve the secret shares associated with multiple NFTs at once")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_ids: Vec<u32> = matches
+						.values_of("nft-ids")
+						.unwrap()
+						.map(|nft_id| nft_id.parse().expect("nft-id cannot be converted to u32"))
+						.collect();
+					let arg_share_index: u8 = matches
+						.value_of("share-index")
+						.unwrap()
+						.parse()
+						.expect("share-index cannot be converted to u8");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let direct_api = get_worker_api_direct(matches);
+
+					// the secrets are returned encrypted to an ephemeral key
+					let recipient_pair =
+						Rsa3072KeyPair::new().expect("failed to generate ephemeral rsa key");
+					let recipient_key = serde_json::to_vec(
+						&recipient_pair.export_pubkey().expect("failed to export rsa pubkey"),
+					)
+					.unwrap();
+
+					// compose jsonrpc call
+					let rpc_method = RetrieveNftSecretsRequest::METHOD.to_owned();
+					let secrets = arg_nft_ids
+						.iter()
+						.map(|nft_id| RetrieveNftSecretRequest {
+							nft_id: *nft_id,
+							share_index: arg_share_index,
+							recipient_key: Some(recipient_key.clone()),
+						})
+						.collect();
+					let data = sign_request(
+						matches,
+						&direct_api,
+						RetrieveNftSecretsRequest { secrets },
+						&account,
+					);
+					let jsonrpc_call: String =
+						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+					// call the api
+					let response_str = match direct_api.get(&jsonrpc_call) {
+						Ok(resp) => resp,
+						Err(_) => panic!("Error when sending direct invocation call"),
+					};
+
+					// Decode the response
+					let response: RpcResponse<Option<Vec<u8>>> =
+						match serde_json::from_str(&response_str) {
+							Ok(resp) => resp,
+							Err(err_msg) => panic!(
+								"Error while deserialisation of the RpcResponse: {:?}",
+								err_msg
+							),
+						};
+
+					if let Some(error) = response.error {
+						let cli_response = CliResponseFormat::<String> {
+							status: false,
+							result: error.message.unwrap_or_default(),
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					} else {
+						let results: Vec<NftSecretBatchResult<Vec<u8>>> =
+							Decode::decode(&mut response.result.unwrap_or_default().as_slice())
+								.expect("failed to decode batch results");
+						let result = arg_nft_ids
+							.iter()
+							.zip(results.into_iter())
+							.map(|(nft_id, result)| {
+								let secret = result.map(|encrypted| {
+									let encrypted_secret =
+										EncryptedNftSecret::decode(&mut encrypted.as_slice())
+											.expect("failed to decode encrypted secret");
+									let secret = decrypt_nft_secret(
+										&(*nft_id, arg_share_index).encode(),
+										encrypted_secret,
+										&recipient_pair,
+									);
+									String::from_utf8(secret).unwrap()
+								});
+								NftSecretBatchItemFormat::new(*nft_id, secret)
+							})
+							.collect();
+						let cli_response = CliResponseFormat::<Vec<NftSecretBatchItemFormat>> {
+							status: true,
+							result,
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("list-nft-secret-shares")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+				})
+				.description("List the indices of the secret shares stored for a NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let direct_api = get_worker_api_direct(matches);
+
+					// compose jsonrpc call
+					let rpc_method = ListNftSecretSharesRequest::METHOD.to_owned();
+					let data = sign_request(
+						matches,
+						&direct_api,
+						ListNftSecretSharesRequest { nft_id: arg_nft_id },
+						&account,
+					);
+					let jsonrpc_call: String =
+						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+					// call the api
+					let response_str = match direct_api.get(&jsonrpc_call) {
+						Ok(resp) => resp,
+						Err(_) => panic!("Error when sending direct invocation call"),
+					};
+
+					// Decode the response
+					let response: RpcResponse<Option<Vec<u8>>> =
+						match serde_json::from_str(&response_str) {
+							Ok(resp) => resp,
+							Err(err_msg) => panic!(
+								"Error while deserialisation of the RpcResponse: {:?}",
+								err_msg
+							),
+						};
+
+					match &response.error {
+						Some(error) => {
+							let cli_response = CliResponseFormat::<String> {
+								status: false,
+								result: error.message.clone().unwrap_or_default(),
+							};
+							println!(
+								"{}",
+								CliResponseFormat::pretty_format(&cli_response).unwrap()
+							);
+						},
+						None => {
+							let cli_response = CliResponseFormat {
+								status: true,
+								result: response.result.unwrap_or_default(),
+							};
+							println!(
+								"{}",
+								CliResponseFormat::pretty_format(&cli_response).unwrap()
+							);
+						},
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("nft-secret-commitment")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+					.arg(
+						Arg::with_name("share-index")
+							.takes_value(true)
+							.default_value("0")
+							.value_name("U8")
+							.help("Index of the secret share"),
+					)
+				})
+				.description(
+					"Get the commitment to a stored NFT secret published on-chain, along with its salt",
+				)
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let arg_share_index: u8 = matches
+						.value_of("share-index")
+						.unwrap()
+						.parse()
+						.expect("share-index cannot be converted to u8");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let direct_api = get_worker_api_direct(matches);
+
+					// compose jsonrpc call
+					let rpc_method = GetNftSecretCommitmentRequest::METHOD.to_owned();
+					let data = sign_request(
+						matches,
+						&direct_api,
+						GetNftSecretCommitmentRequest {
+							nft_id: arg_nft_id,
+							share_index: arg_share_index,
+						},
+						&account,
+					);
+					let jsonrpc_call: String =
+						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+					// call the api
+					let response_str = match direct_api.get(&jsonrpc_call) {
+						Ok(resp) => resp,
+						Err(_) => panic!("Error when sending direct invocation call"),
+					};
+
+					// Decode the response
+					let response: RpcResponse<Option<Vec<u8>>> =
+						match serde_json::from_str(&response_str) {
+							Ok(resp) => resp,
+							Err(err_msg) => panic!(
+								"Error while deserialisation of the RpcResponse: {:?}",
+								err_msg
+							),
+						};
+
+					if let Some(error) = response.error {
+						let cli_response = CliResponseFormat::<String> {
+							status: false,
+							result: error.message.unwrap_or_default(),
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					} else {
+						let proof = NftSecretCommitmentProof::decode(
+							&mut response.result.unwrap_or_default().as_slice(),
+						)
+						.expect("Error while decoding the NftSecretCommitmentProof");
+						let cli_response = CliResponseFormat {
+							status: true,
+							result: NftSecretCommitmentFormat {
+								commitment: hex::encode(proof.commitment.commitment),
+								salt: hex::encode(proof.salt),
+							},
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("generate-nft-key")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+				})
+				.description("Get the public key of the key pair the enclave derives for a NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let request = GenerateNftKeyRequest { nft_id: arg_nft_id };
+					match query_nft_request::<_, NftPublicKey>(matches, request, &account) {
+						Ok(key) => {
+							let cli_response = CliResponseFormat {
+								status: true,
+								result: NftPublicKeyFormat {
+									gx: hex::encode(key.gx),
+									gy: hex::encode(key.gy),
+								},
+							};
+							println!(
+								"{}",
+								CliResponseFormat::pretty_format(&cli_response).unwrap()
+							);
+						},
+						Err(message) => print_nft_request_error(message),
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("nft-sign")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+					.arg(
+						Arg::with_name("message")
+							.takes_value(true)
+							.required(true)
+							.value_name("STRING")
+							.help("Message to sign"),
+					)
+				})
+				.description("Sign a message with the key pair of a NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let arg_message = matches.value_of("message").unwrap();
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let request = SignWithNftKeyRequest {
+						nft_id: arg_nft_id,
+						message: arg_message.as_bytes().to_vec(),
+					};
+					match query_nft_request::<_, NftSignature>(matches, request, &account) {
+						Ok(signature) => {
+							let cli_response = CliResponseFormat {
+								status: true,
+								result: NftSignatureFormat {
+									x: hex::encode(signature.x),
+									y: hex::encode(signature.y),
+								},
+							};
+							println!(
+								"{}",
+								CliResponseFormat::pretty_format(&cli_response).unwrap()
+							);
+						},
+						Err(message) => print_nft_request_error(message),
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("encrypt-to-nft")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+					.arg(
+						Arg::with_name("gx")
+							.long("gx")
+							.takes_value(true)
+							.required(true)
+							.value_name("HEX")
+							.help("x-coordinate of the public key of the NFT"),
+					)
+					.arg(
+						Arg::with_name("gy")
+							.long("gy")
+							.takes_value(true)
+							.required(true)
+							.value_name("HEX")
+							.help("y-coordinate of the public key of the NFT"),
+					)
+					.arg(
+						Arg::with_name("message")
+							.takes_value(true)
+							.required(true)
+							.value_name("STRING")
+							.help("Message to encrypt"),
+					)
+				})
+				.description("Encrypt a message to the public key of a NFT, offline")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let nft_key = NftPublicKey {
+						gx: decode_coordinate(matches.value_of("gx").unwrap()),
+						gy: decode_coordinate(matches.value_of("gy").unwrap()),
+					};
+					let arg_message = matches.value_of("message").unwrap();
+
+					let ciphertext = encrypt_to_nft(arg_nft_id, &nft_key, arg_message.as_bytes());
+					let cli_response =
+						CliResponseFormat { status: true, result: hex::encode(ciphertext.encode()) };
+					println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("nft-decrypt")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+					.arg(
+						Arg::with_name("ciphertext")
+							.takes_value(true)
+							.required(true)
+							.value_name("HEX")
+							.help("Ciphertext as printed by encrypt-to-nft"),
+					)
+				})
+				.description("Decrypt a message encrypted to a NFT with the key pair of the NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let ciphertext = hex::decode(matches.value_of("ciphertext").unwrap())
+						.expect("ciphertext is not hex encoded");
+					let ciphertext = NftCiphertext::decode(&mut ciphertext.as_slice())
+						.expect("failed to decode the ciphertext");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let cli_response = retrieve_encrypted_secret(
+						matches,
+						|recipient_key| DecryptWithNftKeyRequest {
+							nft_id: arg_nft_id,
+							ciphertext,
+							recipient_key: Some(recipient_key),
+						},
+						&DecryptWithNftKeyRequest::aad(arg_nft_id),
+						&account,
+					);
+					println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("register-recipient-key")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("key-file")
+							.takes_value(true)
+							.required(true)
+							.value_name("PATH")
+							.help("File to write the generated RSA-3072 key pair to"),
+					)
+				})
+				.description("Register a recipient key that NFT secrets are handed over to")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_key_file = matches.value_of("key-file").unwrap();
+
+					let recipient_pair =
+						Rsa3072KeyPair::new().expect("failed to generate rsa key pair");
+					fs::write(arg_key_file, serde_json::to_vec(&recipient_pair).unwrap())
+						.expect("failed to write the key file");
+					let key = serde_json::to_vec(
+						&recipient_pair.export_pubkey().expect("failed to export rsa pubkey"),
+					)
+					.unwrap();
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					send_nft_request(matches, RegisterRecipientKeyRequest { key }, &account);
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("retrieve-nft-secret-handover")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+					.arg(
+						Arg::with_name("key-file")
+							.takes_value(true)
+							.required(true)
+							.value_name("PATH")
+							.help("File holding the key pair written by register-recipient-key"),
+					)
+				})
+				.description("Retrieve the secrets handed over to the new owner of a NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let arg_key_file = matches.value_of("key-file").unwrap();
+
+					let recipient_pair: Rsa3072KeyPair = serde_json::from_slice(
+						&fs::read(arg_key_file).expect("failed to read the key file"),
+					)
+					.expect("failed to decode the key file");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let request = RetrieveNftSecretHandoverRequest { nft_id: arg_nft_id };
+					match query_nft_request::<_, NftSecretHandover>(matches, request, &account) {
+						Ok(handover) => {
+							let shares = handover
+								.shares
+								.into_iter()
+								.map(|(share_index, encrypted_secret)| {
+									let aad = (arg_nft_id, share_index).encode();
+									let secret =
+										decrypt_nft_secret(&aad, encrypted_secret, &recipient_pair);
+									(share_index, String::from_utf8(secret).unwrap())
+								})
+								.collect();
+							let cli_response = CliResponseFormat {
+								status: true,
+								result: NftSecretHandoverFormat {
+									block_number: handover.block_number,
+									shares,
+								},
+							};
+							println!(
+								"{}",
+								CliResponseFormat::pretty_format(&cli_response).unwrap()
+							);
+						},
+						Err(message) => print_nft_request_error(message),
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("list-nft-secrets")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-ids")
+							.takes_value(true)
+							.required(true)
+							.multiple(true)
+							.value_name("U32")
+							.help("Ids of the NFTs to look up"),
+					)
+				})
+				.description("List those of the given NFTs of the sender that have secrets stored")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_ids: Vec<u32> = matches
+						.values_of("nft-ids")
+						.unwrap()
+						.map(|nft_id| nft_id.parse().expect("nft-id cannot be converted to u32"))
+						.collect();
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let direct_api = get_worker_api_direct(matches);
+
+					// compose jsonrpc call
+					let rpc_method = ListMyNftSecretsRequest::METHOD.to_owned();
+					let request = ListMyNftSecretsRequest { nft_ids: arg_nft_ids };
+					let data = sign_request(matches, &direct_api, request, &account);
+					let jsonrpc_call: String =
+						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+					// call the api
+					let response_str = match direct_api.get(&jsonrpc_call) {
+						Ok(resp) => resp,
+						Err(_) => panic!("Error when sending direct invocation call"),
+					};
+
+					// Decode the response
+					let response: RpcResponse<Option<Vec<u8>>> =
+						match serde_json::from_str(&response_str) {
+							Ok(resp) => resp,
+							Err(err_msg) => panic!(
+								"Error while deserialisation of the RpcResponse: {:?}",
+								err_msg
+							),
+						};
+
+					if let Some(error) = response.error {
+						let cli_response = CliResponseFormat::<String> {
+							status: false,
+							result: error.message.unwrap_or_default(),
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					} else {
+						let secrets: Vec<(u32, NftSecretInfo)> =
+							Decode::decode(&mut response.result.unwrap_or_default().as_slice())
+								.expect("failed to decode NFT secret list");
+						let cli_response = CliResponseFormat::<Vec<NftSecretInfoFormat>> {
+							status: true,
+							result: secrets
+								.into_iter()
+								.map(|(nft_id, info)| NftSecretInfoFormat::new(nft_id, info))
+								.collect(),
+						};
+						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("nft-secret-info")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+				})
+				.description("Show whether a secret is stored for a NFT, without revealing it")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+
+					let direct_api = get_worker_api_direct(matches);
+					match direct_api.get_nft_secret_info(arg_nft_id) {
+						Ok(info) => {
+							let cli_response = CliResponseFormat {
+								status: true,
+								result: NftSecretInfoFormat::new(arg_nft_id, info),
+							};
+							println!(
+								"{}",
+								CliResponseFormat::pretty_format(&cli_response).unwrap()
+							);
+						},
+						Err(e) => {
+							let cli_response = CliResponseFormat::<String> {
+								status: false,
+								result: format!("{:?}", e),
+							};
+							println!(
+								"{}",
+								CliResponseFormat::pretty_format(&cli_response).unwrap()
+							);
+						},
+					}
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("remove-nft-secret")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+				})
+				.description("Remove the secret share associated with a NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					send_nft_request(
+						matches,
+						RemoveNftSecretRequest { nft_id: arg_nft_id },
+						&account,
+					);
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("store-series-secret")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("series-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("STRING")
+							.help("Id of the NFT series"),
+					)
+					.arg(
+						Arg::with_name("secret")
+							.takes_value(true)
+							.required(true)
+							.value_name("STRING")
+							.help("Secret shared by all NFTs of the series"),
+					)
+				})
+				.description("Store a secret for all NFTs of a series, as its creator")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_series_id = matches.value_of("series-id").unwrap();
+					let arg_secret = matches.value_of("secret").unwrap();
+
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					send_nft_request(
+						matches,
+						StoreNftSeriesSecretRequest {
+							series_id: arg_series_id.into(),
+							secret: arg_secret.into(),
+						},
+						&account,
+					);
+
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("retrieve-series-secret")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("series-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("STRING")
+							.help("Id of the NFT series"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of an owned NFT of the series"),
+					)
 				})
-				.description("Store a NFT secret share")
+				.description("Retrieve the secret of a NFT series, as the owner of a NFT in it")
 				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
 					let arg_account = matches.value_of("account").unwrap();
+					let arg_series_id = matches.value_of("series-id").unwrap();
 					let arg_nft_id: u32 = matches
 						.value_of("nft-id")
 						.unwrap()
 						.parse()
 						.expect("nft-id cannot be converted to u32");
-					let arg_secret = matches.value_of("secret").unwrap();
 
-					let account = get_pair_from_str(arg_account);
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let cli_response = retrieve_encrypted_secret(
+						matches,
+						|recipient_key| RetrieveNftSeriesSecretRequest {
+							series_id: arg_series_id.into(),
+							nft_id: arg_nft_id,
+							recipient_key: Some(recipient_key),
+						},
+						&arg_series_id.to_string().encode(),
+						&account,
+					);
+					println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
 
-					// compose jsonrpc call
-					let rpc_method = "nft_storeSecret".to_owned();
-					let data =
-						StoreNftSecretRequest { nft_id: arg_nft_id, secret: arg_secret.into() }
-							.sign(&sr25519_core::Pair::from(account));
-					let jsonrpc_call: String =
-						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("store-capsule-key")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT converted to the capsule"),
+					)
+					.arg(
+						Arg::with_name("key")
+							.takes_value(true)
+							.required(true)
+							.value_name("STRING")
+							.help("Key of the capsule"),
+					)
+				})
+				.description("Store the key of a capsule")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let arg_key = matches.value_of("key").unwrap();
 
-					// call the api
-					let direct_api = get_worker_api_direct(matches);
-					let response_str = match direct_api.get(&jsonrpc_call) {
-						Ok(resp) => resp,
-						Err(_) => panic!("Error when sending direct invocation call"),
-					};
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					send_nft_request(
+						matches,
+						StoreCapsuleKeyRequest { nft_id: arg_nft_id, key: arg_key.into() },
+						&account,
+					);
 
-					// Decode the response
-					let response: RpcResponse<Option<String>> =
-						match serde_json::from_str(&response_str) {
-							Ok(resp) => resp,
-							Err(err_msg) => panic!(
-								"Error while deserialisation of the RpcResponse: {:?}",
-								err_msg
-							),
-						};
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("retrieve-capsule-key")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT converted to the capsule"),
+					)
+				})
+				.description("Retrieve the key of a capsule")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
 
-					if let Some(error) = &response.error {
-						print!("Failed to store NFT secret");
-						let cli_response = CliResponseFormat::<String> {
-							status: false,
-							result: String::from_utf8(
-								error.message.clone().unwrap_or("".to_string()).into(),
-							)
-							.unwrap(),
-						};
-						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
-					} else {
-						let cli_response =
-							CliResponseFormat { status: true, result: "".to_string() };
-						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap())
-					}
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					let cli_response = retrieve_encrypted_secret(
+						matches,
+						|recipient_key| RetrieveCapsuleKeyRequest {
+							nft_id: arg_nft_id,
+							recipient_key: Some(recipient_key),
+						},
+						&RetrieveCapsuleKeyRequest::aad(arg_nft_id),
+						&account,
+					);
+					println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
 
 					Ok(())
 				}),
 		)
 		.add_cmd(
-			Command::new("retrieve-nft-secret")
+			Command::new("grant-nft-access")
 				.options(|app| {
 					app.arg(
 						Arg::with_name("account")
@@ -461,8 +1656,15 @@ fn main() {
 							.value_name("U32")
 							.help("Id of the NFT"),
 					)
+					.arg(
+						Arg::with_name("grantee")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("AccountId to grant read access to, in ss58check format"),
+					)
 				})
-				.description("Retrieve the secret share associated with a NFT")
+				.description("Grant an account read access to the secret share of a NFT")
 				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
 					let arg_account = matches.value_of("account").unwrap();
 					let arg_nft_id: u32 = matches
@@ -470,50 +1672,59 @@ fn main() {
 						.unwrap()
 						.parse()
 						.expect("nft-id cannot be converted to u32");
+					let grantee = get_accountid_from_str(matches.value_of("grantee").unwrap());
 
-					let account = get_pair_from_str(arg_account);
-
-					// compose jsonrpc call
-					let rpc_method = "nft_retrieveSecret".to_owned();
-					let data = RetrieveNftSecretRequest { nft_id: arg_nft_id }
-						.sign(&sr25519_core::Pair::from(account));
-					let jsonrpc_call: String =
-						RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
-
-					// call the api
-					let direct_api = get_worker_api_direct(matches);
-					let response_str = match direct_api.get(&jsonrpc_call) {
-						Ok(resp) => resp,
-						Err(_) => panic!("Error when sending direct invocation call"),
-					};
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					send_nft_request(
+						matches,
+						GrantNftAccessRequest { nft_id: arg_nft_id, grantee },
+						&account,
+					);
 
-					// Decode the response
-					let response: RpcResponse<Option<Vec<u8>>> =
-						match serde_json::from_str(&response_str) {
-							Ok(resp) => resp,
-							Err(err_msg) => panic!(
-								"Error while deserialisation of the RpcResponse: {:?}",
-								err_msg
-							),
-						};
+					Ok(())
+				}),
+		)
+		.add_cmd(
+			Command::new("revoke-nft-access")
+				.options(|app| {
+					app.arg(
+						Arg::with_name("account")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("Sender's incognito AccountId in ss58check format"),
+					)
+					.arg(
+						Arg::with_name("nft-id")
+							.takes_value(true)
+							.required(true)
+							.value_name("U32")
+							.help("Id of the NFT"),
+					)
+					.arg(
+						Arg::with_name("grantee")
+							.takes_value(true)
+							.required(true)
+							.value_name("SS58")
+							.help("AccountId to revoke the read access of, in ss58check format"),
+					)
+				})
+				.description("Revoke the read access of an account to the secret share of a NFT")
+				.runner(move |_args: &str, matches: &ArgMatches<'_>| {
+					let arg_account = matches.value_of("account").unwrap();
+					let arg_nft_id: u32 = matches
+						.value_of("nft-id")
+						.unwrap()
+						.parse()
+						.expect("nft-id cannot be converted to u32");
+					let grantee = get_accountid_from_str(matches.value_of("grantee").unwrap());
 
-					if let Some(error) = &response.error {
-						//print!("Failed to retrieve NFT secret");
-						let cli_response = CliResponseFormat::<String> {
-							status: false,
-							result: String::from_utf8(
-								error.message.clone().unwrap_or("".to_string()).into(),
-							)
-							.unwrap(),
-						};
-						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
-					} else {
-						let cli_response = CliResponseFormat {
-							status: true,
-							result: String::from_utf8(response.result.unwrap()).unwrap(),
-						};
-						println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
-					}
+					let account = sr25519_core::Pair::from(get_pair_from_str(arg_account));
+					send_nft_request(
+						matches,
+						RevokeNftAccessRequest { nft_id: arg_nft_id, grantee },
+						&account,
+					);
 
 					Ok(())
 				}),
@@ -548,6 +1759,214 @@ fn get_worker_api_direct(matches: &ArgMatches<'_>) -> DirectWorkerApi {
 	DirectWorkerApi::new(url)
 }
 
+/// Signs `request` for the worker's request context with the next request nonce of `signer`.
+///
+/// The nonce is fetched anew for every request, as a request that the enclave has authorized
+/// consumes its nonce even if it fails afterwards. The request expires `REQUEST_VALIDITY_BLOCKS`
+/// after the latest parentchain block.
+fn sign_request<T: SignableRequest>(
+	matches: &ArgMatches<'_>,
+	direct_api: &DirectWorkerApi,
+	request: T,
+	signer: &sr25519_core::Pair,
+) -> SignedRequest<T> {
+	let context = direct_api
+		.get_request_context()
+		.expect("failed to get request context from worker");
+	let nonce = direct_api
+		.get_request_nonce(&signer.public())
+		.expect("failed to get request nonce from worker");
+	let latest_header: Header = get_chain_api(matches)
+		.get_header(None)
+		.unwrap()
+		.expect("failed to get latest parentchain header");
+	request.sign(signer, &context, nonce, latest_header.number + REQUEST_VALIDITY_BLOCKS)
+}
+
+/// Decrypts a NFT secret that the enclave encrypted to `recipient_pair`, with the encoded NFT id
+/// and share index, or the encoded series id, as `aad`.
+fn decrypt_nft_secret(
+	aad: &[u8],
+	encrypted_secret: EncryptedNftSecret,
+	recipient_pair: &Rsa3072KeyPair,
+) -> Vec<u8> {
+	let mut key = Vec::new();
+	recipient_pair
+		.decrypt_buffer(&encrypted_secret.encrypted_key, &mut key)
+		.expect("failed to decrypt secret key");
+	let key: [u8; 16] = key.as_slice().try_into().expect("invalid secret key length");
+
+	let mut secret = vec![0u8; encrypted_secret.ciphertext.len()];
+	rsgx_rijndael128GCM_decrypt(
+		&key,
+		&encrypted_secret.ciphertext,
+		&encrypted_secret.nonce,
+		aad,
+		&encrypted_secret.mac,
+		&mut secret,
+	)
+	.expect("failed to decrypt secret, it may have been tampered with");
+	secret
+}
+
+/// Sends a signed request for a secret, that the enclave returns encrypted to an ephemeral key
+/// with the given `aad`, and returns the decrypted secret.
+fn retrieve_encrypted_secret<T: SignableRequest>(
+	matches: &ArgMatches<'_>,
+	request: impl FnOnce(Vec<u8>) -> T,
+	aad: &[u8],
+	signer: &sr25519_core::Pair,
+) -> CliResponseFormat<String> {
+	let direct_api = get_worker_api_direct(matches);
+
+	let recipient_pair = Rsa3072KeyPair::new().expect("failed to generate ephemeral rsa key");
+	let recipient_key =
+		serde_json::to_vec(&recipient_pair.export_pubkey().expect("failed to export rsa pubkey"))
+			.unwrap();
+
+	// compose jsonrpc call
+	let rpc_method = T::METHOD.to_owned();
+	let data = sign_request(matches, &direct_api, request(recipient_key), signer);
+	let jsonrpc_call: String = RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+	// call the api
+	let response_str = match direct_api.get(&jsonrpc_call) {
+		Ok(resp) => resp,
+		Err(_) => panic!("Error when sending direct invocation call"),
+	};
+
+	// Decode the response
+	let response: RpcResponse<Option<Vec<u8>>> = match serde_json::from_str(&response_str) {
+		Ok(resp) => resp,
+		Err(err_msg) => panic!("Error while deserialisation of the RpcResponse: {:?}", err_msg),
+	};
+
+	match &response.error {
+		Some(error) => CliResponseFormat::<String> {
+			status: false,
+			result: error.message.clone().unwrap_or_default(),
+		},
+		None => {
+			let encrypted_secret =
+				EncryptedNftSecret::decode(&mut response.result.unwrap().as_slice())
+					.expect("failed to decode encrypted secret");
+			let secret = decrypt_nft_secret(aad, encrypted_secret, &recipient_pair);
+			CliResponseFormat { status: true, result: String::from_utf8(secret).unwrap() }
+		},
+	}
+}
+
+/// Sends a signed NFT request whose rpc returns no value and prints the outcome.
+fn send_nft_request<T: SignableRequest>(
+	matches: &ArgMatches<'_>,
+	request: T,
+	signer: &sr25519_core::Pair,
+) {
+	let direct_api = get_worker_api_direct(matches);
+
+	// compose jsonrpc call
+	let rpc_method = T::METHOD.to_owned();
+	let data = sign_request(matches, &direct_api, request, signer);
+	let jsonrpc_call: String = RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+	// call the api
+	let response_str = match direct_api.get(&jsonrpc_call) {
+		Ok(resp) => resp,
+		Err(_) => panic!("Error when sending direct invocation call"),
+	};
+
+	// Decode the response
+	let response: RpcResponse<Option<String>> = match serde_json::from_str(&response_str) {
+		Ok(resp) => resp,
+		Err(err_msg) => panic!("Error while deserialisation of the RpcResponse: {:?}", err_msg),
+	};
+
+	let cli_response = match &response.error {
+		Some(error) => CliResponseFormat::<String> {
+			status: false,
+			result: error.message.clone().unwrap_or_default(),
+		},
+		None => CliResponseFormat { status: true, result: "".to_string() },
+	};
+	println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+}
+
+/// Sends a signed NFT request and decodes the value its rpc returns. Returns the error message
+/// of the enclave if the request failed.
+fn query_nft_request<T: SignableRequest, R: Decode>(
+	matches: &ArgMatches<'_>,
+	request: T,
+	signer: &sr25519_core::Pair,
+) -> Result<R, String> {
+	let direct_api = get_worker_api_direct(matches);
+
+	// compose jsonrpc call
+	let rpc_method = T::METHOD.to_owned();
+	let data = sign_request(matches, &direct_api, request, signer);
+	let jsonrpc_call: String = RpcRequest::compose_jsonrpc_call(rpc_method, data.encode());
+
+	// call the api
+	let response_str = match direct_api.get(&jsonrpc_call) {
+		Ok(resp) => resp,
+		Err(_) => panic!("Error when sending direct invocation call"),
+	};
+
+	// Decode the response
+	let response: RpcResponse<Option<Vec<u8>>> = match serde_json::from_str(&response_str) {
+		Ok(resp) => resp,
+		Err(err_msg) => panic!("Error while deserialisation of the RpcResponse: {:?}", err_msg),
+	};
+
+	match response.error {
+		Some(error) => Err(error.message.unwrap_or_default()),
+		None => Ok(R::decode(&mut response.result.unwrap_or_default().as_slice())
+			.expect("Error while decoding the rpc result")),
+	}
+}
+
+fn print_nft_request_error(message: String) {
+	let cli_response = CliResponseFormat::<String> { status: false, result: message };
+	println!("{}", CliResponseFormat::pretty_format(&cli_response).unwrap());
+}
+
+fn decode_coordinate(hex_str: &str) -> [u8; 32] {
+	hex::decode(hex_str)
+		.expect("coordinate is not hex encoded")
+		.as_slice()
+		.try_into()
+		.expect("coordinate has to be 32 bytes")
+}
+
+/// Encrypts the message to the key of the NFT with a fresh ephemeral key pair, see
+/// `NftCiphertext`.
+fn encrypt_to_nft(nft_id: u32, nft_key: &NftPublicKey, message: &[u8]) -> NftCiphertext {
+	let ecc_handle = SgxEccHandle::new();
+	ecc_handle.open().expect("failed to open the ecc context");
+	let (ephemeral_private, ephemeral_public) =
+		ecc_handle.create_key_pair().expect("failed to generate ephemeral key pair");
+	let shared_secret = ecc_handle
+		.compute_shared_dhkey(
+			&ephemeral_private,
+			&sgx_ec256_public_t { gx: nft_key.gx, gy: nft_key.gy },
+		)
+		.expect("invalid public key of the NFT");
+
+	let ephemeral_key = NftPublicKey { gx: ephemeral_public.gx, gy: ephemeral_public.gy };
+	let key = NftCiphertext::key(&shared_secret.s, &ephemeral_key);
+	let mut ciphertext = vec![0u8; message.len()];
+	let mut mac = [0u8; 16];
+	rsgx_rijndael128GCM_encrypt(
+		&key,
+		message,
+		&NftCiphertext::NONCE,
+		&NftCiphertext::aad(nft_id),
+		&mut ciphertext,
+		&mut mac,
+	)
+	.expect("failed to encrypt the message");
+	NftCiphertext { ephemeral_key, ciphertext, mac }
+}
+
 #[allow(dead_code)]
 #[derive(Decode)]
 struct ProcessedParentchainBlockArgs {
@@ -755,4 +2174,73 @@ where
 		metadata.serialize(&mut ser).unwrap();
 		String::from_utf8(ser.into_inner()).ok()
 	}
-}
\ No newline at end of file
+}
+
+/// Commitment to a stored NFT secret and the salt to open it, hex encoded.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftSecretCommitmentFormat {
+	pub commitment: String,
+	pub salt: String,
+}
+
+/// Public key of the key pair of a NFT, with hex encoded little-endian coordinates.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftPublicKeyFormat {
+	pub gx: String,
+	pub gy: String,
+}
+
+/// ECDSA signature made with the key pair of a NFT, with hex encoded little-endian coordinates.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftSignatureFormat {
+	pub x: String,
+	pub y: String,
+}
+
+/// Secrets handed over to the new owner of a NFT, along with their share indices.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftSecretHandoverFormat {
+	pub block_number: u32,
+	pub shares: Vec<(u8, String)>,
+}
+
+/// Public metadata of the secrets of a NFT.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftSecretInfoFormat {
+	pub nft_id: u32,
+	pub exists: bool,
+	pub byte_length: u32,
+	pub share_count: u8,
+	pub updated_at: Option<BlockNumber>,
+	pub writer: Option<String>,
+}
+
+impl NftSecretInfoFormat {
+	pub fn new(nft_id: u32, info: NftSecretInfo) -> Self {
+		Self {
+			nft_id,
+			exists: info.exists,
+			byte_length: info.byte_length,
+			share_count: info.share_count,
+			updated_at: info.updated_at,
+			writer: info.writer.map(|writer| writer.to_ss58check()),
+		}
+	}
+}
+
+/// Result of a single NFT of a batch request.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftSecretBatchItemFormat {
+	pub nft_id: u32,
+	pub status: bool,
+	pub result: String,
+}
+
+impl NftSecretBatchItemFormat {
+	pub fn new(nft_id: u32, result: NftSecretBatchResult<String>) -> Self {
+		match result {
+			Ok(result) => Self { nft_id, status: true, result },
+			Err(error) => Self { nft_id, status: false, result: error },
+		}
+	}
+}
@@ -11,6 +11,7 @@ extern "C" {
 		mu_ra_addr_size: u32,
 		untrusted_worker_addr: *const u8,
 		untrusted_worker_addr_size: u32,
+		block_listed_nft_secrets: c_int,
 	) -> sgx_status_t;
 
 	pub fn init_direct_invocation_server(
@@ -47,6 +48,29 @@ extern "C" {
 		nonce: *const u32,
 	) -> sgx_status_t;
 
+	pub fn init_nft_secret_sharing(
+		eid: sgx_enclave_id_t,
+		retval: *mut sgx_status_t,
+		threshold: u8,
+		skip_ra: c_int,
+	) -> sgx_status_t;
+
+	pub fn backup_nft_db(
+		eid: sgx_enclave_id_t,
+		retval: *mut sgx_status_t,
+		cid: *mut u8,
+		cid_size: u32,
+	) -> sgx_status_t;
+
+	pub fn restore_nft_db(
+		eid: sgx_enclave_id_t,
+		retval: *mut sgx_status_t,
+		cid: *const u8,
+		cid_size: u32,
+		backup: *const u8,
+		backup_size: u32,
+	) -> sgx_status_t;
+
 	pub fn get_rsa_encryption_pubkey(
 		eid: sgx_enclave_id_t,
 		retval: *mut sgx_status_t,
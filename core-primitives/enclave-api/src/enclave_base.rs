@@ -21,7 +21,7 @@ use codec::{Decode, Encode};
 use frame_support::ensure;
 use itp_enclave_api_ffi as ffi;
 use itp_settings::worker::{
-	HEADER_MAX_SIZE, MR_ENCLAVE_SIZE, SHIELDING_KEY_SIZE, SIGNING_KEY_SIZE,
+	HEADER_MAX_SIZE, IPFS_CID_SIZE, MR_ENCLAVE_SIZE, SHIELDING_KEY_SIZE, SIGNING_KEY_SIZE,
 };
 use log::*;
 use sgx_crypto_helper::rsa3072::Rsa3072PubKey;
@@ -33,7 +33,14 @@ use sp_runtime::traits::Header;
 /// Trait for base/common Enclave API functions
 pub trait EnclaveBase: Send + Sync + 'static {
 	/// Initialize the enclave (needs to be called once at application startup).
-	fn init(&self, mu_ra_addr: &str, untrusted_worker_addr: &str) -> EnclaveResult<()>;
+	///
+	/// `block_listed_nft_secrets` denies access to the secrets of NFTs that are listed for sale.
+	fn init(
+		&self,
+		mu_ra_addr: &str,
+		untrusted_worker_addr: &str,
+		block_listed_nft_secrets: bool,
+	) -> EnclaveResult<()>;
 
 	/// Initialize the direct invocation RPC server.
 	fn init_direct_invocation_server(&self, rpc_server_addr: String) -> EnclaveResult<()>;
@@ -48,6 +55,20 @@ pub trait EnclaveBase: Send + Sync + 'static {
 
 	fn set_nonce(&self, nonce: u32) -> EnclaveResult<()>;
 
+	/// Distribute NFT secrets as Shamir shares across all registered workers, such that
+	/// `threshold` of them are needed to reconstruct a secret. A `threshold` of 0 stores
+	/// the secrets in full on every worker.
+	///
+	/// `skip_ra` has to be set if the workers have been registered without remote attestation.
+	fn init_nft_secret_sharing(&self, threshold: u8, skip_ra: bool) -> EnclaveResult<()>;
+
+	/// Write an encrypted backup of the NFT secret database to IPFS and return its CID.
+	fn backup_nft_db(&self) -> EnclaveResult<String>;
+
+	/// Restore the NFT secret database from the backup with the given CID, `backup` being the
+	/// content fetched from IPFS.
+	fn restore_nft_db(&self, cid: &str, backup: &[u8]) -> EnclaveResult<()>;
+
 	fn get_rsa_shielding_pubkey(&self) -> EnclaveResult<Rsa3072PubKey>;
 
 	fn get_ecc_signing_pubkey(&self) -> EnclaveResult<ed25519::Public>;
@@ -57,7 +78,12 @@ pub trait EnclaveBase: Send + Sync + 'static {
 
 /// EnclaveApi implementation for Enclave struct
 impl EnclaveBase for Enclave {
-	fn init(&self, mu_ra_addr: &str, untrusted_worker_addr: &str) -> EnclaveResult<()> {
+	fn init(
+		&self,
+		mu_ra_addr: &str,
+		untrusted_worker_addr: &str,
+		block_listed_nft_secrets: bool,
+	) -> EnclaveResult<()> {
 		let mut retval = sgx_status_t::SGX_SUCCESS;
 
 		let encoded_mu_ra_addr = mu_ra_addr.encode();
@@ -71,6 +97,7 @@ impl EnclaveBase for Enclave {
 				encoded_mu_ra_addr.len() as u32,
 				encoded_untrusted_worker_addr.as_ptr(),
 				encoded_untrusted_worker_addr.len() as u32,
+				block_listed_nft_secrets.into(),
 			)
 		};
 
@@ -136,6 +163,53 @@ impl EnclaveBase for Enclave {
 		Ok(())
 	}
 
+	fn init_nft_secret_sharing(&self, threshold: u8, skip_ra: bool) -> EnclaveResult<()> {
+		let mut retval = sgx_status_t::SGX_SUCCESS;
+
+		let result = unsafe {
+			ffi::init_nft_secret_sharing(self.eid, &mut retval, threshold, skip_ra.into())
+		};
+
+		ensure!(result == sgx_status_t::SGX_SUCCESS, Error::Sgx(result));
+		ensure!(retval == sgx_status_t::SGX_SUCCESS, Error::Sgx(retval));
+
+		Ok(())
+	}
+
+	fn backup_nft_db(&self) -> EnclaveResult<String> {
+		let mut retval = sgx_status_t::SGX_SUCCESS;
+		let mut cid = [0u8; IPFS_CID_SIZE];
+
+		let result = unsafe {
+			ffi::backup_nft_db(self.eid, &mut retval, cid.as_mut_ptr(), cid.len() as u32)
+		};
+
+		ensure!(result == sgx_status_t::SGX_SUCCESS, Error::Sgx(result));
+		ensure!(retval == sgx_status_t::SGX_SUCCESS, Error::Sgx(retval));
+
+		String::from_utf8(cid.to_vec()).map_err(|e| Error::Other(e.into()))
+	}
+
+	fn restore_nft_db(&self, cid: &str, backup: &[u8]) -> EnclaveResult<()> {
+		let mut retval = sgx_status_t::SGX_SUCCESS;
+
+		let result = unsafe {
+			ffi::restore_nft_db(
+				self.eid,
+				&mut retval,
+				cid.as_ptr(),
+				cid.len() as u32,
+				backup.as_ptr(),
+				backup.len() as u32,
+			)
+		};
+
+		ensure!(result == sgx_status_t::SGX_SUCCESS, Error::Sgx(result));
+		ensure!(retval == sgx_status_t::SGX_SUCCESS, Error::Sgx(retval));
+
+		Ok(())
+	}
+
 	fn get_rsa_shielding_pubkey(&self) -> EnclaveResult<Rsa3072PubKey> {
 		let mut retval = sgx_status_t::SGX_SUCCESS;
 
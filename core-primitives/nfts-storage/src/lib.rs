@@ -5,6 +5,9 @@ use sp_std::prelude::Vec;
 
 pub struct NFTsStorage;
 
+/// Storage of the capsules pallet, that NFTs can be converted into.
+pub struct CapsulesStorage;
+
 // Separate the prefix from the rest because in our case we changed the storage prefix due to
 // the rebranding. With the below implementation of the `NFTsStorageKeys`, we could simply
 // define another struct `OtherStorage`, implement `StoragePrefix` for it, and get the
@@ -21,10 +24,25 @@ impl StoragePrefix for NFTsStorage {
 
 pub trait NFTsStorageKeys {
 	fn data(id: u32) -> Vec<u8>;
+	fn series(series_id: &[u8]) -> Vec<u8>;
 }
 
 impl<S: StoragePrefix> NFTsStorageKeys for S {
 	fn data(id: u32) -> Vec<u8> {
 		storage_map_key(Self::prefix(), "Data", &id, &StorageHasher::Blake2_128Concat)
 	}
+
+	fn series(series_id: &[u8]) -> Vec<u8> {
+		storage_map_key(Self::prefix(), "Series", &series_id, &StorageHasher::Blake2_128Concat)
+	}
+}
+
+pub trait CapsulesStorageKeys {
+	fn capsule(nft_id: u32) -> Vec<u8>;
+}
+
+impl CapsulesStorageKeys for CapsulesStorage {
+	fn capsule(nft_id: u32) -> Vec<u8> {
+		storage_map_key("Capsules", "Capsules", &nft_id, &StorageHasher::Blake2_128Concat)
+	}
 }
@@ -31,6 +31,9 @@ pub trait EnclaveAttestationOCallApi: Clone + Send + Sync {
 
 	fn get_ias_socket(&self) -> SgxResult<i32>;
 
+	/// Connects to the mutual remote attestation server of the worker registered with `worker_url`.
+	fn get_mu_ra_socket(&self, worker_url: &str) -> SgxResult<i32>;
+
 	fn get_quote(
 		&self,
 		sig_rl: Vec<u8>,
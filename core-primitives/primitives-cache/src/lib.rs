@@ -18,7 +18,8 @@
 //! Stores all primitives of the enclave that do need to be accessed often, but are
 //! not be frequently mutated, such as keys and server urls.
 //!
-//! TODO: For now only the mu-ra server and untrusted wokrer url is stored here. Keys and such could also be stored here.
+//! TODO: For now only the mu-ra server and untrusted wokrer url, as well as the NFT secret policy
+//! of the deployment are stored here. Keys and such could also be stored here.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![feature(assert_matches)]
@@ -68,13 +69,19 @@ pub mod primitives_cache;
 pub struct Primitives {
 	mu_ra_url: String,
 	untrusted_worker_url: String,
+	block_listed_nft_secrets: bool,
 }
 
 impl Primitives {
-	pub fn new(mu_ra_url: &str, untrusted_worker_url: &str) -> Primitives {
+	pub fn new(
+		mu_ra_url: &str,
+		untrusted_worker_url: &str,
+		block_listed_nft_secrets: bool,
+	) -> Primitives {
 		Primitives {
 			mu_ra_url: mu_ra_url.to_string(),
 			untrusted_worker_url: untrusted_worker_url.to_string(),
+			block_listed_nft_secrets,
 		}
 	}
 
@@ -85,6 +92,11 @@ impl Primitives {
 	pub fn untrusted_worker_url(&self) -> &str {
 		&self.untrusted_worker_url
 	}
+
+	/// Whether access to the secrets of NFTs that are listed for sale is denied.
+	pub fn block_listed_nft_secrets(&self) -> bool {
+		self.block_listed_nft_secrets
+	}
 }
 
 /// Trait to mutate the primitives.
@@ -103,6 +115,8 @@ pub trait GetPrimitives {
 	fn get_mu_ra_url(&self) -> Result<String>;
 
 	fn get_untrusted_worker_url(&self) -> Result<String>;
+
+	fn get_block_listed_nft_secrets(&self) -> Result<bool>;
 }
 
 // Helper function to set primitives of a given cache.
@@ -110,8 +124,9 @@ pub fn set_primitives<E: MutatePrimitives>(
 	cache: &E,
 	mu_ra_url: &str,
 	untrusted_worker_url: &str,
+	block_listed_nft_secrets: bool,
 ) -> Result<()> {
-	let primitives = Primitives::new(mu_ra_url, untrusted_worker_url);
+	let primitives = Primitives::new(mu_ra_url, untrusted_worker_url, block_listed_nft_secrets);
 	let mut rw_lock = cache.load_for_mutation()?;
 
 	*rw_lock = primitives;
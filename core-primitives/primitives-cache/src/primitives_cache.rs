@@ -70,6 +70,11 @@ impl GetPrimitives for PrimitivesCache {
 		let primitives_lock = self.primitives_lock.read().map_err(|_| Error::LockPoisoning)?;
 		Ok(primitives_lock.untrusted_worker_url().to_string())
 	}
+
+	fn get_block_listed_nft_secrets(&self) -> Result<bool> {
+		let primitives_lock = self.primitives_lock.read().map_err(|_| Error::LockPoisoning)?;
+		Ok(primitives_lock.block_listed_nft_secrets())
+	}
 }
 
 #[cfg(test)]
@@ -83,7 +88,7 @@ pub mod tests {
 		let mut lock = cache.load_for_mutation().unwrap();
 		let mu_ra_url = "hello";
 		let untrusted_url = "world";
-		let primitives = Primitives::new(mu_ra_url, untrusted_url);
+		let primitives = Primitives::new(mu_ra_url, untrusted_url, true);
 		*lock = primitives.clone();
 		std::mem::drop(lock);
 		assert_eq!(primitives, *cache.get_primitives().unwrap());
@@ -94,7 +99,7 @@ pub mod tests {
 		let cache = Arc::new(PrimitivesCache::default());
 		let mu_ra_url = "hello";
 		let untrusted_url = "world";
-		let primitives = Primitives::new(mu_ra_url, untrusted_url);
+		let primitives = Primitives::new(mu_ra_url, untrusted_url, true);
 
 		let mut write_lock = cache.load_for_mutation().unwrap();
 
@@ -30,8 +30,22 @@ pub mod files {
 	pub const RSA3072_SEALED_KEY_FILE: &str = "rsa3072_key_sealed.bin";
 	pub const SEALED_SIGNER_SEED_FILE: &str = "ed25519_key_sealed.bin";
 	pub const AES_KEY_FILE_AND_INIT_V: &str = "aes_key_sealed.bin";
+	// master seed of the per-NFT key pairs
+	pub const NFT_KEY_SEED_FILE: &str = "nft_key_seed_sealed.bin";
 	pub const LIGHT_CLIENT_DB: &str = "light_client_db.bin";
 	pub const NFT_DB: &str = "nft_db.bin";
+	pub const NFT_DB_PATH: &str = "./nft_db";
+	pub const NFT_DB_SHARES_INDEX_FILE: &str = "shares_index.bin";
+	pub const NFT_DB_SERIES_INDEX_FILE: &str = "series_index.bin";
+	pub const NFT_DB_CAPSULE_INDEX_FILE: &str = "capsule_index.bin";
+	pub const NFT_DB_RECIPIENT_KEY_INDEX_FILE: &str = "recipient_key_index.bin";
+	// versions of the replicated writes to the NFT secret storage
+	pub const NFT_DB_VERSIONS_FILE: &str = "versions.bin";
+	pub const REQUEST_NONCE_DB: &str = "request_nonce_db.bin";
+	// latest versions of the sealed version buckets, to detect rollbacks
+	pub const VERSION_ANCHOR_FILE: &str = "version_anchor.bin";
+	// latest versions of the sealed files, spread over buckets
+	pub const VERSION_BUCKETS_PATH: &str = "./version_buckets";
 
 	pub const RA_DUMP_CERT_DER_FILE: &str = "ra_dump_cert.der";
 
@@ -64,6 +78,8 @@ pub mod worker {
 	pub const SIGNING_KEY_SIZE: usize = 32;
 	// size of the MR enclave
 	pub const MR_ENCLAVE_SIZE: usize = 32;
+	// size of a base58 encoded IPFS CIDv0
+	pub const IPFS_CID_SIZE: usize = 46;
 	// Factors to tune the initial amount of enclave funding:
 	// Should be set to a value that ensures that the enclave can register itself
 	// and the worker can run for a certain time. Only for development.
@@ -87,6 +103,14 @@ pub mod enclave {
 
 	pub static MAX_TRUSTED_GETTERS_EXEC_DURATION: Duration = Duration::from_millis(150);
 	pub static TRUSTED_GETTERS_SLOT_DURATION: Duration = Duration::from_millis(400);
+
+	// maximum number of NFT secrets in a single batch request
+	pub const MAX_NFT_SECRET_BATCH_SIZE: usize = 256;
+	// number of stored NFTs whose owner is checked on every block import, besides the NFTs
+	// that the imported blocks transfer or burn
+	pub const NFT_OWNERSHIP_SWEEP_SIZE: usize = 16;
+	// time a NFT secret write waits for the other workers to apply it
+	pub static NFT_SECRET_REPLICATION_TIMEOUT: Duration = Duration::from_secs(5);
 }
 
 /// Settings concerning the node
@@ -104,4 +128,13 @@ pub mod node {
 	pub static RUNTIME_SPEC_VERSION: u32 = 43;
 	pub static RUNTIME_TRANSACTION_VERSION: u32 = 6;
 	pub static UNSHIELD: u8 = 6u8;
+	// commitments to stored NFT secrets are published as remarks of the enclave account
+	pub static SYSTEM_MODULE: u8 = 0u8;
+	pub static REMARK: u8 = 1u8;
+	// calls that change the owner of an NFT, taking the NFT id as their first argument
+	pub static NFTS_MODULE: u8 = 41u8;
+	pub static NFT_TRANSFER: u8 = 2u8;
+	pub static NFT_BURN: u8 = 3u8;
+	pub static MARKETPLACE_MODULE: u8 = 42u8;
+	pub static MARKETPLACE_BUY: u8 = 2u8;
 }
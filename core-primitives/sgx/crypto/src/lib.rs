@@ -19,6 +19,7 @@ pub mod sgx_reexport_prelude {
 pub mod aes;
 pub mod ed25519;
 pub mod error;
+pub mod nft_key_seed;
 pub mod traits;
 
 #[cfg(feature = "sgx")]
@@ -29,6 +30,8 @@ pub use self::aes::*;
 #[cfg(feature = "sgx")]
 pub use self::ed25519::*;
 #[cfg(feature = "sgx")]
+pub use self::nft_key_seed::NftKeySeedSeal;
+#[cfg(feature = "sgx")]
 pub use self::rsa3072::*;
 pub use error::*;
 pub use traits::*;
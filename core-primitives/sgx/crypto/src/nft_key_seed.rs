@@ -0,0 +1,72 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Master seed of the per-NFT key pairs. It is provisioned along with the other keys, such that
+//! all workers derive the same key pair for a NFT.
+
+use derive_more::Display;
+
+/// Sealed master seed, from which the key pair of each NFT is derived.
+#[derive(Copy, Clone, Debug, Display)]
+pub struct NftKeySeedSeal;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+#[cfg(feature = "sgx")]
+pub mod sgx {
+
+	use super::*;
+	use crate::error::{Error, Result};
+	use itp_settings::files::NFT_KEY_SEED_FILE;
+	use itp_sgx_io::{seal, unseal, SealedIO};
+	use log::info;
+	use sgx_rand::{Rng, StdRng};
+	use std::{convert::TryInto, sgxfs::SgxFile};
+
+	impl SealedIO for NftKeySeedSeal {
+		type Error = Error;
+		type Unsealed = [u8; 32];
+
+		fn unseal() -> Result<Self::Unsealed> {
+			unseal(NFT_KEY_SEED_FILE)?
+				.as_slice()
+				.try_into()
+				.map_err(|_| Error::Other("invalid NFT key seed length".into()))
+		}
+
+		fn seal(unsealed: Self::Unsealed) -> Result<()> {
+			Ok(seal(&unsealed, NFT_KEY_SEED_FILE)?)
+		}
+	}
+
+	pub fn create_sealed_if_absent() -> Result<()> {
+		if SgxFile::open(NFT_KEY_SEED_FILE).is_err() {
+			info!("[Enclave] Keyfile not found, creating new! {}", NFT_KEY_SEED_FILE);
+			return create_sealed_seed()
+		}
+		Ok(())
+	}
+
+	pub fn create_sealed_seed() -> Result<()> {
+		let mut seed = [0u8; 32];
+		let mut rand = StdRng::new()?;
+		rand.fill_bytes(&mut seed);
+
+		NftKeySeedSeal::seal(seed)
+	}
+}
@@ -18,6 +18,8 @@ use std::{
 #[cfg(feature = "sgx")]
 pub use sgx::*;
 
+pub mod rollback;
+
 /// Abstraction around IO that is supposed to use the `std::io::File`
 pub trait IO: Sized {
 	type Error: From<std::io::Error> + std::fmt::Debug + 'static;
@@ -56,12 +58,41 @@ pub fn read_to_string(filepath: &str) -> IOResult<String> {
 
 #[cfg(feature = "sgx")]
 mod sgx {
+	use crate::rollback::{
+		bucket_of, check_bucket, check_missing_bucket, encode_versioned, PublishedAnchor,
+		RootAnchor, VersionBucket, VERSION_BUCKETS,
+	};
+	use codec::{Decode, Encode};
+	use core::sync::atomic::{AtomicBool, Ordering};
+	use itp_settings::files::{VERSION_ANCHOR_FILE, VERSION_BUCKETS_PATH};
+	use lazy_static::lazy_static;
 	use std::{
-		io::{Read, Result, Write},
+		format, fs,
+		io::{Error, ErrorKind, Read, Result, Write},
+		path::Path,
 		sgxfs::SgxFile,
+		string::String,
+		sync::{SgxMutex, SgxMutexGuard},
 		vec::Vec,
 	};
 
+	lazy_static! {
+		/// Serializes the versioned IO of the files anchored in the same bucket.
+		static ref BUCKET_LOCKS: Vec<SgxMutex<()>> =
+			(0..VERSION_BUCKETS).map(|_| SgxMutex::new(())).collect();
+		/// Root anchor, unsealed once and kept in memory.
+		static ref ROOT_ANCHOR: SgxMutex<Option<Root>> = Default::default();
+	}
+
+	/// Set once a rollback of all sealed files has been detected, see `check_version_anchor`.
+	static ROLLED_BACK: AtomicBool = AtomicBool::new(false);
+
+	struct Root {
+		anchor: RootAnchor,
+		/// Whether the root anchor has been created by this process, as none has been sealed.
+		created: bool,
+	}
+
 	pub fn unseal(path: &str) -> Result<Vec<u8>> {
 		let mut buf = Vec::new();
 		SgxFile::open(path).map(|mut f| f.read_to_end(&mut buf))??;
@@ -71,4 +102,186 @@ mod sgx {
 	pub fn seal(bytes: &[u8], path: &str) -> Result<()> {
 		SgxFile::create(path).map(|mut f| f.write_all(bytes))?
 	}
+
+	/// Seals the bytes with the next version of the file, see `rollback`.
+	pub fn seal_versioned(bytes: &[u8], path: &str) -> Result<()> {
+		check_not_rolled_back()?;
+		let bucket = bucket_of(path);
+		let _lock = lock_bucket(bucket)?;
+		let mut files = unseal_bucket(bucket)?;
+		let version = files.advance(path);
+		// Seal the file before anchoring its version in the bucket, and the bucket before the
+		// root anchor. If this is interrupted, the anchors catch up on the next unseal.
+		seal(&encode_versioned(version, bytes), path)?;
+		seal_bucket(bucket, &files)
+	}
+
+	/// Unseals a file sealed with `seal_versioned`, failing if it has been rolled back to an
+	/// older version. Returns `None` if the file has never been sealed.
+	pub fn unseal_versioned(path: &str) -> Result<Option<Vec<u8>>> {
+		check_not_rolled_back()?;
+		let bucket = bucket_of(path);
+		let _lock = lock_bucket(bucket)?;
+		let mut files = unseal_bucket(bucket)?;
+		if !Path::new(path).exists() {
+			files.check_missing(path)?;
+			return Ok(None)
+		}
+		let blob = unseal(path)?;
+		let (payload, caught_up) = files.check(path, &blob)?;
+		let payload = payload.to_vec();
+		if caught_up {
+			seal_bucket(bucket, &files)?;
+		}
+		Ok(Some(payload))
+	}
+
+	/// Removes a file sealed with `seal_versioned` along with its anchored version.
+	pub fn remove_versioned(path: &str) -> Result<()> {
+		check_not_rolled_back()?;
+		let bucket = bucket_of(path);
+		let _lock = lock_bucket(bucket)?;
+		let mut files = unseal_bucket(bucket)?;
+		if Path::new(path).exists() {
+			fs::remove_file(path)?;
+		}
+		if files.remove(path) {
+			seal_bucket(bucket, &files)?;
+		}
+		Ok(())
+	}
+
+	/// Whether any file has been sealed with `seal_versioned`, see `RootAnchor::is_empty`.
+	pub fn has_versioned_files() -> Result<bool> {
+		check_not_rolled_back()?;
+		with_root(|root| Ok(!root.anchor.is_empty()))
+	}
+
+	/// Publishes the root anchor, see `RootAnchor::publish`. It is sealed before it is returned,
+	/// such that the sealed root anchor is never older than a published one.
+	pub fn publish_version_anchor(
+		digest: impl FnOnce(&[u8]) -> [u8; 32],
+	) -> Result<Option<PublishedAnchor>> {
+		check_not_rolled_back()?;
+		with_root(|root| {
+			let mut anchor = root.anchor.clone();
+			let published = match anchor.publish(digest) {
+				Some(published) => published,
+				None => return Ok(None),
+			};
+			anchor.using_encoded(|bytes| seal(bytes, VERSION_ANCHOR_FILE))?;
+			root.anchor = anchor;
+			Ok(Some(published))
+		})
+	}
+
+	/// Checks an anchor that has been published from this enclave, see
+	/// `RootAnchor::check_published`, and confirms it if it is the latest publication. Once a
+	/// rollback has been detected, all versioned IO fails, such that the enclave does not
+	/// continue on the rolled back files.
+	pub fn check_version_anchor(anchor: &PublishedAnchor) -> Result<()> {
+		check_not_rolled_back()?;
+		with_root(|root| {
+			if let Err(e) = root.anchor.check_published(anchor) {
+				ROLLED_BACK.store(true, Ordering::SeqCst);
+				return Err(e)
+			}
+			let mut confirmed = root.anchor.clone();
+			if confirmed.confirm(anchor) {
+				confirmed.using_encoded(|bytes| seal(bytes, VERSION_ANCHOR_FILE))?;
+				root.anchor = confirmed;
+			}
+			Ok(())
+		})
+	}
+
+	/// Whether the latest published anchor has been seen on the parentchain, see
+	/// `RootAnchor::confirm`.
+	pub fn is_version_anchor_confirmed() -> Result<bool> {
+		check_not_rolled_back()?;
+		with_root(|root| Ok(root.anchor.is_confirmed()))
+	}
+
+	fn check_not_rolled_back() -> Result<()> {
+		if ROLLED_BACK.load(Ordering::SeqCst) {
+			return Err(Error::new(
+				ErrorKind::InvalidData,
+				"Rollback of sealed file detected: a newer anchor has been published",
+			))
+		}
+		Ok(())
+	}
+
+	fn lock_bucket(bucket: usize) -> Result<SgxMutexGuard<'static, ()>> {
+		BUCKET_LOCKS[bucket]
+			.lock()
+			.map_err(|_| Error::new(ErrorKind::Other, "version bucket lock is poisoned"))
+	}
+
+	fn bucket_path(bucket: usize) -> String {
+		format!("{}/{:02x}.bin", VERSION_BUCKETS_PATH, bucket)
+	}
+
+	/// The bucket has to be locked by the caller.
+	fn unseal_bucket(bucket: usize) -> Result<VersionBucket> {
+		let (anchored, root_created) =
+			with_root(|root| Ok((root.anchor.version(bucket), root.created)))?;
+		let path = bucket_path(bucket);
+		if !Path::new(&path).exists() {
+			check_missing_bucket(bucket, anchored)?;
+			return Ok(VersionBucket::default())
+		}
+		let blob = unseal(&path)?;
+		let (version, payload) = check_bucket(bucket, anchored, root_created, &blob)?;
+		let files = VersionBucket::decode(&mut &payload[..])
+			.map_err(|e| Error::new(ErrorKind::InvalidData, e.what()))?;
+		if version > anchored {
+			anchor_bucket(bucket, version)?;
+		}
+		Ok(files)
+	}
+
+	/// Seals the bucket with its next version and anchors it. The bucket has to be locked by the
+	/// caller, such that its version does not change in the meantime.
+	fn seal_bucket(bucket: usize, files: &VersionBucket) -> Result<()> {
+		let version = with_root(|root| Ok(root.anchor.version(bucket)))? + 1;
+		fs::create_dir_all(VERSION_BUCKETS_PATH)?;
+		files
+			.using_encoded(|bytes| seal(&encode_versioned(version, bytes), &bucket_path(bucket)))?;
+		anchor_bucket(bucket, version)
+	}
+
+	fn anchor_bucket(bucket: usize, version: u64) -> Result<()> {
+		with_root(|root| {
+			let mut anchor = root.anchor.clone();
+			anchor.anchor(bucket, version);
+			anchor.using_encoded(|bytes| seal(bytes, VERSION_ANCHOR_FILE))?;
+			root.anchor = anchor;
+			Ok(())
+		})
+	}
+
+	/// Runs `f` on the root anchor, which is unsealed on first use. If none has been sealed yet,
+	/// a new one is sealed right away, such that buckets are never sealed before it.
+	fn with_root<T>(f: impl FnOnce(&mut Root) -> Result<T>) -> Result<T> {
+		let mut root = ROOT_ANCHOR
+			.lock()
+			.map_err(|_| Error::new(ErrorKind::Other, "root anchor lock is poisoned"))?;
+		if root.is_none() {
+			*root = Some(unseal_root()?);
+		}
+		f(root.as_mut().expect("root anchor has just been unsealed; qed"))
+	}
+
+	fn unseal_root() -> Result<Root> {
+		if !Path::new(VERSION_ANCHOR_FILE).exists() {
+			let anchor = RootAnchor::default();
+			anchor.using_encoded(|bytes| seal(bytes, VERSION_ANCHOR_FILE))?;
+			return Ok(Root { anchor, created: true })
+		}
+		let anchor = RootAnchor::decode(&mut unseal(VERSION_ANCHOR_FILE)?.as_slice())
+			.map_err(|e| Error::new(ErrorKind::InvalidData, e.what()))?;
+		anchor.check_size()?;
+		Ok(Root { anchor, created: false })
+	}
 }
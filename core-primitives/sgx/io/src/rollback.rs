@@ -0,0 +1,420 @@
+//! Rollback protection of sealed files.
+//!
+//! Sealing protects the confidentiality and integrity of a file, but the host can still replace
+//! it with an older sealed copy. Therefore every versioned sealed file embeds a version, and the
+//! latest version of every file is kept in a `VersionBucket`. Unsealing a file that is older
+//! than its anchored version fails with a rollback error.
+//!
+//! The files are spread over `VERSION_BUCKETS` buckets by the hash of their path, such that a
+//! seal only has to update the small bucket of the file. The buckets are sealed files versioned
+//! alike, whose latest versions are kept in the `RootAnchor`.
+//!
+//! The root anchor itself is a sealed file. This detects the replacement of single files, e.g.
+//! by a stale backup copy, but not a consistent rollback of all files together with the root
+//! anchor. Hardware monotonic counters are not available to enclaves on Linux, hence the root
+//! anchor is published on the parentchain instead, as a `PublishedAnchor`. Importing a published
+//! anchor that is newer than the sealed root anchor reveals such a rollback.
+//!
+//! Until the latest publication has been seen on the parentchain, the files may still be rolled
+//! back to before it undetected. Hence it is republished until it has been confirmed.
+
+use codec::{Decode, Encode};
+use std::{
+	format,
+	io::{Error, ErrorKind, Result},
+	string::{String, ToString},
+	vec,
+	vec::Vec,
+};
+
+/// Number of buckets that the versions of the sealed files are spread over. Changing it
+/// invalidates all anchored versions.
+pub const VERSION_BUCKETS: usize = 256;
+
+/// Prefix of a versioned sealed file, distinguishing it from files sealed without version.
+const VERSIONED_FILE_MAGIC: [u8; 8] = *b"sgxvrsn1";
+
+/// Embeds the version into the bytes to be sealed.
+pub fn encode_versioned(version: u64, payload: &[u8]) -> Vec<u8> {
+	let mut blob = Vec::with_capacity(VERSIONED_FILE_MAGIC.len() + 8 + payload.len());
+	blob.extend_from_slice(&VERSIONED_FILE_MAGIC);
+	blob.extend_from_slice(&version.to_le_bytes());
+	blob.extend_from_slice(payload);
+	blob
+}
+
+/// Splits unsealed bytes into version and payload. Returns `None` for a file that has been
+/// sealed without version.
+pub fn decode_versioned(blob: &[u8]) -> Option<(u64, &[u8])> {
+	let rest = blob.strip_prefix(&VERSIONED_FILE_MAGIC[..])?;
+	if rest.len() < 8 {
+		return None
+	}
+	let (version, payload) = rest.split_at(8);
+	let mut version_bytes = [0u8; 8];
+	version_bytes.copy_from_slice(version);
+	Some((u64::from_le_bytes(version_bytes), payload))
+}
+
+/// Bucket that anchors the version of the file, by the FNV-1a hash of its path.
+pub fn bucket_of(path: &str) -> usize {
+	let hash = path.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+		(hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+	});
+	(hash % VERSION_BUCKETS as u64) as usize
+}
+
+fn rollback_error(reason: String) -> Error {
+	Error::new(ErrorKind::InvalidData, format!("Rollback of sealed file detected: {}", reason))
+}
+
+/// Latest sealed version of every versioned file of a bucket, sorted by path.
+#[derive(Debug, Default, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct VersionBucket(Vec<(String, u64)>);
+
+impl VersionBucket {
+	pub fn version(&self, path: &str) -> Option<u64> {
+		self.position(path).ok().map(|p| self.0[p].1)
+	}
+
+	/// Anchors and returns the version of the next seal of the file.
+	pub fn advance(&mut self, path: &str) -> u64 {
+		match self.position(path) {
+			Ok(p) => {
+				self.0[p].1 += 1;
+				self.0[p].1
+			},
+			Err(p) => {
+				self.0.insert(p, (path.to_string(), 1));
+				1
+			},
+		}
+	}
+
+	/// Forgets the version of a file that has been removed on purpose.
+	pub fn remove(&mut self, path: &str) -> bool {
+		match self.position(path) {
+			Ok(p) => {
+				self.0.remove(p);
+				true
+			},
+			Err(_) => false,
+		}
+	}
+
+	/// Checks the unsealed bytes of the file against its anchored version and returns the
+	/// payload. Fails if the file is older than its anchored version, or has been replaced by a
+	/// file without version.
+	///
+	/// A file that is newer than its anchored version has been sealed right before the bucket
+	/// could be updated, the bucket then catches up. Returns whether it did.
+	pub fn check<'a>(&mut self, path: &str, blob: &'a [u8]) -> Result<(&'a [u8], bool)> {
+		match (decode_versioned(blob), self.position(path)) {
+			(Some((version, _)), Ok(p)) if version < self.0[p].1 => Err(rollback_error(format!(
+				"{} has version {}, expected {}",
+				path, version, self.0[p].1
+			))),
+			(Some((version, payload)), Ok(p)) => {
+				let caught_up = version > self.0[p].1;
+				self.0[p].1 = version;
+				Ok((payload, caught_up))
+			},
+			(Some(_), Err(_)) =>
+				Err(rollback_error(format!("{} has a version, but none is anchored", path))),
+			(None, Ok(_)) =>
+				Err(rollback_error(format!("{} has been sealed without version", path))),
+			// Sealed before versioning was introduced, the next seal anchors it.
+			(None, Err(_)) => Ok((blob, false)),
+		}
+	}
+
+	/// Fails if a file with an anchored version is missing.
+	pub fn check_missing(&self, path: &str) -> Result<()> {
+		match self.version(path) {
+			Some(version) =>
+				Err(rollback_error(format!("{} with version {} is missing", path, version))),
+			None => Ok(()),
+		}
+	}
+
+	fn position(&self, path: &str) -> core::result::Result<usize, usize> {
+		self.0.binary_search_by(|(anchored_path, _)| anchored_path.as_str().cmp(path))
+	}
+}
+
+/// Root anchor as published on the parentchain.
+#[derive(Debug, Default, Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct PublishedAnchor {
+	/// Number of the publication, counting from 1.
+	pub sequence: u64,
+	/// Digest of the bucket versions at the time of the publication.
+	pub digest: [u8; 32],
+}
+
+/// Latest sealed version of every bucket, 0 for a bucket that has never been sealed, along with
+/// the latest publication of the root anchor.
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct RootAnchor {
+	buckets: Vec<u64>,
+	/// Number of bucket updates.
+	epoch: u64,
+	published_epoch: u64,
+	published: PublishedAnchor,
+	/// Sequence of the latest publication that has been seen on the parentchain.
+	confirmed: u64,
+}
+
+impl Default for RootAnchor {
+	fn default() -> Self {
+		RootAnchor {
+			buckets: vec![0; VERSION_BUCKETS],
+			epoch: 0,
+			published_epoch: 0,
+			published: PublishedAnchor::default(),
+			confirmed: 0,
+		}
+	}
+}
+
+impl RootAnchor {
+	pub fn version(&self, bucket: usize) -> u64 {
+		self.buckets[bucket]
+	}
+
+	/// Whether no bucket has ever been anchored, hence no file has ever been sealed versioned.
+	pub fn is_empty(&self) -> bool {
+		self.epoch == 0
+	}
+
+	/// Anchors the version of the bucket, which has been sealed with it.
+	pub fn anchor(&mut self, bucket: usize, version: u64) {
+		self.buckets[bucket] = version;
+		self.epoch += 1;
+	}
+
+	/// Publishes the root anchor, if any bucket has been updated since its last publication.
+	/// `digest` hashes the encoded bucket versions. Otherwise, the latest publication is
+	/// published again until it has been confirmed.
+	pub fn publish(&mut self, digest: impl FnOnce(&[u8]) -> [u8; 32]) -> Option<PublishedAnchor> {
+		if self.epoch == self.published_epoch {
+			return (!self.is_confirmed()).then(|| self.published)
+		}
+		self.published = PublishedAnchor {
+			sequence: self.published.sequence + 1,
+			digest: digest(&(self.epoch, &self.buckets).encode()),
+		};
+		self.published_epoch = self.epoch;
+		Some(self.published)
+	}
+
+	/// Checks an anchor that has been published from this enclave. Fails if it has been published
+	/// after the latest publication of this root anchor, hence the root anchor has been rolled
+	/// back, or if it has been published from other bucket versions under the same number.
+	pub fn check_published(&self, anchor: &PublishedAnchor) -> Result<()> {
+		let rolled_back = anchor.sequence > self.published.sequence
+			|| (anchor.sequence == self.published.sequence
+				&& anchor.digest != self.published.digest);
+		if rolled_back {
+			return Err(rollback_error(format!(
+				"anchor {} has been published, but the latest sealed one is {}",
+				anchor.sequence, self.published.sequence
+			)))
+		}
+		Ok(())
+	}
+
+	/// Confirms the latest publication, once it has been seen on the parentchain. Returns
+	/// whether it has been confirmed by this anchor.
+	pub fn confirm(&mut self, anchor: &PublishedAnchor) -> bool {
+		if anchor != &self.published || self.is_confirmed() {
+			return false
+		}
+		self.confirmed = anchor.sequence;
+		true
+	}
+
+	/// Whether the latest publication has been seen on the parentchain, or none has been made.
+	pub fn is_confirmed(&self) -> bool {
+		self.confirmed == self.published.sequence
+	}
+
+	/// Fails unless the anchor has a version for every bucket.
+	pub fn check_size(&self) -> Result<()> {
+		if self.buckets.len() != VERSION_BUCKETS {
+			return Err(Error::new(ErrorKind::InvalidData, "unexpected number of version buckets"))
+		}
+		Ok(())
+	}
+}
+
+/// Checks the unsealed bytes of the bucket against its `anchored` version, and returns the
+/// version and payload of the bucket. Fails if the bucket is older than its anchored version, or
+/// if it has been sealed although the root anchor has been created anew.
+///
+/// A bucket that is newer than its anchored version has been sealed right before the root anchor
+/// could be updated, the root anchor then has to catch up.
+pub fn check_bucket(
+	bucket: usize,
+	anchored: u64,
+	root_created: bool,
+	blob: &[u8],
+) -> Result<(u64, &[u8])> {
+	match decode_versioned(blob) {
+		Some((version, _)) if version < anchored => Err(rollback_error(format!(
+			"version bucket {} has version {}, expected {}",
+			bucket, version, anchored
+		))),
+		Some((_, _)) if anchored == 0 && root_created =>
+			Err(rollback_error(format!("version bucket {} outlived the root anchor", bucket))),
+		Some((version, payload)) => Ok((version, payload)),
+		None => Err(rollback_error(format!(
+			"version bucket {} has been sealed without version",
+			bucket
+		))),
+	}
+}
+
+/// Fails if a bucket with an anchored version is missing.
+pub fn check_missing_bucket(bucket: usize, anchored: u64) -> Result<()> {
+	if anchored > 0 {
+		return Err(rollback_error(format!(
+			"version bucket {} with version {} is missing",
+			bucket, anchored
+		)))
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const PATH: &str = "./file.bin";
+
+	#[test]
+	fn versioned_blob_roundtrips() {
+		let blob = encode_versioned(7, b"payload");
+
+		assert_eq!(decode_versioned(&blob), Some((7, &b"payload"[..])));
+		assert_eq!(decode_versioned(b"payload"), None);
+	}
+
+	#[test]
+	fn paths_are_spread_over_the_buckets() {
+		let buckets: Vec<usize> =
+			(0..64).map(|id| bucket_of(&format!("./nft_db/{}.bin", id))).collect();
+
+		assert!(buckets.iter().all(|bucket| *bucket < VERSION_BUCKETS));
+		assert!(buckets.iter().any(|bucket| *bucket != buckets[0]));
+		assert_eq!(bucket_of(PATH), bucket_of(PATH));
+	}
+
+	#[test]
+	fn stale_file_is_rejected() {
+		let mut files = VersionBucket::default();
+		let stale = encode_versioned(files.advance(PATH), b"old");
+		let fresh = encode_versioned(files.advance(PATH), b"new");
+
+		assert_eq!(files.check(PATH, &fresh).unwrap(), (&b"new"[..], false));
+		assert_eq!(files.check(PATH, &stale).unwrap_err().kind(), ErrorKind::InvalidData);
+	}
+
+	#[test]
+	fn bucket_catches_up_with_newer_file() {
+		let mut files = VersionBucket::default();
+		files.advance(PATH);
+		let newer = encode_versioned(2, b"new");
+
+		assert_eq!(files.check(PATH, &newer).unwrap(), (&b"new"[..], true));
+		assert_eq!(files.version(PATH), Some(2));
+	}
+
+	#[test]
+	fn unversioned_file_is_only_accepted_if_never_anchored() {
+		let mut files = VersionBucket::default();
+
+		assert_eq!(files.check(PATH, b"legacy").unwrap(), (&b"legacy"[..], false));
+		files.advance(PATH);
+		assert!(files.check(PATH, b"legacy").is_err());
+	}
+
+	#[test]
+	fn missing_file_is_rejected_unless_removed() {
+		let mut files = VersionBucket::default();
+		files.advance(PATH);
+
+		assert!(files.check_missing(PATH).is_err());
+		assert!(files.remove(PATH));
+		assert!(files.check_missing(PATH).is_ok());
+		assert!(files.check(PATH, &encode_versioned(1, b"removed")).is_err());
+	}
+
+	#[test]
+	fn stale_bucket_is_rejected() {
+		let mut root = RootAnchor::default();
+		root.anchor(3, 2);
+		let stale = encode_versioned(1, b"old");
+		let newer = encode_versioned(3, b"new");
+
+		assert!(check_bucket(3, root.version(3), false, &stale).is_err());
+		assert_eq!(check_bucket(3, root.version(3), false, &newer).unwrap(), (3, &b"new"[..]));
+		assert!(check_bucket(3, root.version(3), false, b"unversioned").is_err());
+	}
+
+	#[test]
+	fn root_anchor_is_only_published_after_updates() {
+		let mut root = RootAnchor::default();
+
+		assert!(root.is_empty());
+		assert_eq!(root.publish(|_| [1; 32]), None);
+		root.anchor(0, 1);
+		assert!(!root.is_empty());
+		let published = root.publish(|_| [1; 32]);
+		assert_eq!(published, Some(PublishedAnchor { sequence: 1, digest: [1; 32] }));
+		assert!(root.confirm(&published.unwrap()));
+		assert_eq!(root.publish(|_| [2; 32]), None);
+	}
+
+	#[test]
+	fn unconfirmed_anchor_is_published_again() {
+		let mut root = RootAnchor::default();
+		root.anchor(0, 1);
+		let published = root.publish(|_| [1; 32]).unwrap();
+
+		assert!(!root.is_confirmed());
+		assert_eq!(root.publish(|_| [2; 32]), Some(published));
+		assert!(!root.confirm(&PublishedAnchor { sequence: 1, digest: [2; 32] }));
+		assert!(root.confirm(&published));
+		assert!(root.is_confirmed());
+		assert!(!root.confirm(&published));
+		assert_eq!(root.publish(|_| [2; 32]), None);
+	}
+
+	#[test]
+	fn anchor_published_after_the_sealed_one_reveals_rollback() {
+		let mut root = RootAnchor::default();
+		root.anchor(0, 1);
+		let first = root.publish(|_| [1; 32]).unwrap();
+		let mut rolled_back = root.clone();
+		root.anchor(0, 2);
+		let second = root.publish(|_| [2; 32]).unwrap();
+
+		assert!(root.check_published(&first).is_ok());
+		assert!(root.check_published(&second).is_ok());
+		assert!(rolled_back.check_published(&first).is_ok());
+		assert!(rolled_back.check_published(&second).is_err());
+
+		// Publishing anew from the rolled back root anchor must not hide the rollback.
+		rolled_back.anchor(1, 1);
+		rolled_back.publish(|_| [3; 32]).unwrap();
+		assert!(rolled_back.check_published(&second).is_err());
+	}
+
+	#[test]
+	fn bucket_is_rejected_if_root_anchor_has_been_created_anew() {
+		let bucket = encode_versioned(1, b"files");
+
+		assert!(check_bucket(0, 0, true, &bucket).is_err());
+		assert_eq!(check_bucket(0, 0, false, &bucket).unwrap(), (1, &b"files"[..]));
+	}
+}
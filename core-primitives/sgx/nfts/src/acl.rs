@@ -0,0 +1,177 @@
+//! Sealed per-NFT access control lists, listing the accounts the owner granted read access to.
+
+use codec::{Decode, Encode};
+use sp_core::{crypto::AccountId32 as AccountId, hashing::blake2_256};
+use std::vec::Vec;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+/// Read access grants of a NFT secret.
+///
+/// The grants are bound to the owner that made them. Once the NFT is transferred, they must
+/// no longer be honoured.
+///
+/// Every change increases the revision, such that an outdated ACL, e.g. of an older backup or
+/// replicated from another worker, is not imported over a newer one.
+#[derive(Debug, Encode, Decode, Clone, PartialEq)]
+pub struct NftAcl {
+	nft_id: u32,
+	owner: AccountId,
+	grantees: Vec<AccountId>,
+	revision: u32,
+}
+
+impl NftAcl {
+	pub fn new(nft_id: u32, owner: AccountId) -> Self {
+		Self { nft_id, owner, grantees: Vec::new(), revision: 0 }
+	}
+
+	/// The grants of `owner`, discarding the grants of a previous owner.
+	pub fn for_owner(self, owner: AccountId) -> Self {
+		if self.owner == owner {
+			return self
+		}
+		Self { nft_id: self.nft_id, owner, grantees: Vec::new(), revision: self.revision + 1 }
+	}
+
+	pub fn nft_id(&self) -> u32 {
+		self.nft_id
+	}
+
+	/// Owner that made the grants.
+	pub fn owner(&self) -> &AccountId {
+		&self.owner
+	}
+
+	pub fn grantees(&self) -> &[AccountId] {
+		&self.grantees
+	}
+
+	pub fn revision(&self) -> u32 {
+		self.revision
+	}
+
+	/// Returns `false` if `account` already had access.
+	pub fn grant(&mut self, account: AccountId) -> bool {
+		if self.grantees.contains(&account) {
+			return false
+		}
+		self.grantees.push(account);
+		self.revision += 1;
+		true
+	}
+
+	/// Returns `false` if `account` had no access.
+	pub fn revoke(&mut self, account: &AccountId) -> bool {
+		let len = self.grantees.len();
+		self.grantees.retain(|grantee| grantee != account);
+		if self.grantees.len() == len {
+			return false
+		}
+		self.revision += 1;
+		true
+	}
+
+	/// Whether this ACL is newer than `other`. ACLs of the same revision, that have been changed
+	/// on different workers, are ordered by their digest, such that all replicas agree on one.
+	pub fn supersedes(&self, other: &NftAcl) -> bool {
+		(self.revision, blake2_256(&self.encode())) > (other.revision, blake2_256(&other.encode()))
+	}
+
+	/// Whether `account` may read the secret while `current_owner` owns the NFT.
+	pub fn has_access(&self, current_owner: &AccountId, account: &AccountId) -> bool {
+		&self.owner == current_owner && self.grantees.contains(account)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn every_change_increases_the_revision() {
+		let owner = AccountId::new([1u8; 32]);
+		let grantee = AccountId::new([2u8; 32]);
+		let mut acl = NftAcl::new(1, owner.clone());
+
+		assert!(acl.grant(grantee.clone()));
+		assert!(!acl.grant(grantee.clone()));
+		assert_eq!(acl.revision(), 1);
+		assert!(acl.revoke(&grantee));
+		assert_eq!(acl.revision(), 2);
+
+		acl.grant(grantee.clone());
+		let acl = acl.clone().for_owner(owner).for_owner(AccountId::new([3u8; 32]));
+		assert!(acl.grantees().is_empty());
+		assert_eq!(acl.revision(), 4);
+	}
+
+	#[test]
+	fn conflicting_changes_of_same_revision_are_ordered_alike() {
+		let owner = AccountId::new([1u8; 32]);
+		let mut granted = NftAcl::new(1, owner.clone());
+		granted.grant(AccountId::new([2u8; 32]));
+		let mut other = NftAcl::new(1, owner);
+		other.grant(AccountId::new([3u8; 32]));
+
+		assert_ne!(granted.supersedes(&other), other.supersedes(&granted));
+		assert!(!granted.supersedes(&granted.clone()));
+
+		let mut revoked = granted.clone();
+		revoked.revoke(&AccountId::new([2u8; 32]));
+		assert!(revoked.supersedes(&granted));
+		assert!(revoked.supersedes(&other));
+	}
+}
+
+#[cfg(feature = "sgx")]
+mod sgx {
+	use super::*;
+	use crate::error::{Error, Result};
+	use derive_more::Display;
+	use itp_settings::files::NFT_DB_PATH;
+	use itp_sgx_io::{remove_versioned, seal_versioned, unseal_versioned};
+	use std::{format, string::String};
+
+	fn acl_path(id: u32) -> String {
+		format!("{}/{}.acl.bin", NFT_DB_PATH, id)
+	}
+
+	/// Sealed ACL storage, one file per NFT next to its sealed secret.
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct NftAclSeal;
+
+	impl NftAclSeal {
+		/// Returns `None` if no grants have been made for the NFT.
+		pub fn get(id: u32) -> Result<Option<NftAcl>> {
+			let acl: NftAcl = match unseal_versioned(&acl_path(id))? {
+				Some(b) => Decode::decode(&mut b.as_slice())?,
+				None => return Ok(None),
+			};
+			// The host could swap the files of two records, hence we check the sealed id.
+			if acl.nft_id() != id {
+				return Err(Error::RecordIdMismatch)
+			}
+			Ok(Some(acl))
+		}
+
+		pub fn seal(acl: NftAcl) -> Result<()> {
+			Ok(acl.using_encoded(|bytes| seal_versioned(bytes, &acl_path(acl.nft_id())))?)
+		}
+
+		/// Seals the imported ACL, unless the present one is as recent. Returns whether the ACL
+		/// has been sealed.
+		pub fn import(acl: NftAcl) -> Result<bool> {
+			match Self::get(acl.nft_id())? {
+				Some(present) if !acl.supersedes(&present) => Ok(false),
+				_ => Self::seal(acl).map(|_| true),
+			}
+		}
+
+		/// Removes all grants of the NFT. Succeeds if there are none.
+		pub fn remove(id: u32) -> Result<()> {
+			Ok(remove_versioned(&acl_path(id))?)
+		}
+	}
+}
@@ -0,0 +1,149 @@
+//! Sealed key material of Ternoa capsules, kept apart from the secrets of the NFTs the capsules
+//! have been converted from.
+
+use crate::versions::WriteVersion;
+use codec::{Decode, Encode};
+use std::vec::Vec;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+/// Key of a capsule, bound to the id of its NFT.
+#[derive(Debug, Encode, Decode, Clone, PartialEq)]
+pub struct CapsuleKey {
+	nft_id: u32,
+	/// Version of the write, replicated writes can arrive out of order.
+	version: WriteVersion,
+	key: Vec<u8>,
+}
+
+impl CapsuleKey {
+	pub fn new(nft_id: u32, block_number: u32, key: Vec<u8>) -> Self {
+		let version = WriteVersion::upsert(block_number, &key);
+		Self { nft_id, version, key }
+	}
+
+	pub fn nft_id(&self) -> u32 {
+		self.nft_id
+	}
+
+	pub fn version(&self) -> WriteVersion {
+		self.version
+	}
+
+	pub fn into_key(self) -> Vec<u8> {
+		self.key
+	}
+}
+
+/// Sorted ids of all NFTs with a stored capsule key.
+#[derive(Debug, Default, Encode, Decode)]
+pub struct CapsuleIndex(Vec<u32>);
+
+impl CapsuleIndex {
+	/// Returns `false` if the NFT was already present.
+	pub fn insert(&mut self, nft_id: u32) -> bool {
+		match self.0.binary_search(&nft_id) {
+			Ok(_) => false,
+			Err(p) => {
+				self.0.insert(p, nft_id);
+				true
+			},
+		}
+	}
+
+	pub fn nft_ids(&self) -> &[u32] {
+		&self.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn newer_capsule_key_has_newer_version() {
+		let key = CapsuleKey::new(1, 5, b"key".to_vec());
+		let rotated = CapsuleKey::new(1, 7, b"rotated key".to_vec());
+
+		assert!(rotated.version() > key.version());
+	}
+
+	#[test]
+	fn capsule_index_is_sorted_and_deduplicated() {
+		let mut index = CapsuleIndex::default();
+		assert!(index.insert(2));
+		assert!(index.insert(1));
+		assert!(!index.insert(2));
+
+		assert_eq!(index.nft_ids(), &[1, 2]);
+	}
+}
+
+#[cfg(feature = "sgx")]
+mod sgx {
+	use super::*;
+	use crate::error::{Error, Result};
+	use derive_more::Display;
+	use itp_settings::files::{NFT_DB_CAPSULE_INDEX_FILE, NFT_DB_PATH};
+	use itp_sgx_io::{seal_versioned, unseal_versioned};
+	use std::{format, string::String};
+
+	fn index_path() -> String {
+		format!("{}/{}", NFT_DB_PATH, NFT_DB_CAPSULE_INDEX_FILE)
+	}
+
+	fn capsule_path(nft_id: u32) -> String {
+		format!("{}/{}.capsule.bin", NFT_DB_PATH, nft_id)
+	}
+
+	fn unseal_index() -> Result<CapsuleIndex> {
+		Ok(unseal_versioned(&index_path())?
+			.map_or(Ok(CapsuleIndex::default()), |b| Decode::decode(&mut b.as_slice()))?)
+	}
+
+	/// Sealed capsule key storage, one file per capsule next to a sealed index of all capsules.
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct CapsuleKeySeal;
+
+	impl CapsuleKeySeal {
+		/// Returns `None` if no key has been stored for the capsule.
+		pub fn get(nft_id: u32) -> Result<Option<CapsuleKey>> {
+			let key: CapsuleKey = match unseal_versioned(&capsule_path(nft_id))? {
+				Some(b) => Decode::decode(&mut b.as_slice())?,
+				None => return Ok(None),
+			};
+			// The host could swap the files of two records, hence we check the sealed id.
+			if key.nft_id() != nft_id {
+				return Err(Error::RecordIdMismatch)
+			}
+			Ok(Some(key))
+		}
+
+		/// Seals the key, unless a newer write of the capsule is present. Returns whether the
+		/// key has been sealed.
+		pub fn upsert(key: CapsuleKey) -> Result<bool> {
+			if let Some(present) = Self::get(key.nft_id())? {
+				if present.version() >= key.version() {
+					return Ok(false)
+				}
+			}
+			key.using_encoded(|bytes| seal_versioned(bytes, &capsule_path(key.nft_id())))?;
+
+			let mut index = unseal_index()?;
+			if index.insert(key.nft_id()) {
+				index.using_encoded(|bytes| seal_versioned(bytes, &index_path()))?;
+			}
+			Ok(true)
+		}
+
+		/// All sealed capsule keys.
+		pub fn keys() -> Result<Vec<CapsuleKey>> {
+			let mut keys = Vec::new();
+			for nft_id in unseal_index()?.nft_ids() {
+				keys.extend(Self::get(*nft_id)?);
+			}
+			Ok(keys)
+		}
+	}
+}
@@ -8,6 +8,13 @@ pub enum Error {
 	Codec(codec::Error),
 	NftNotFound,
 	NftAlreadyExist,
+	RecordIdMismatch,
+	InvalidNonce,
+	InvalidThreshold,
+	InconsistentShares,
+	NotEnoughShares,
+	WriteDenied,
+	LegacyDbReplayed,
 	Other(Box<dyn std::error::Error>),
 }
 
@@ -0,0 +1,81 @@
+//! Sealed owners of the NFTs with secrets, as last observed on the parentchain, along with the
+//! secrets handed over to them.
+//!
+//! The owner at the time of the first write is recorded along with the secret, on every worker
+//! the write is replicated to. Later owners are recorded by the block importer of every worker
+//! from the verified parentchain state, hence the records are not part of a snapshot.
+
+use codec::{Decode, Encode};
+use itp_types::NftSecretHandover;
+use sp_core::crypto::AccountId32 as AccountId;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+/// Owner of a NFT and, if the NFT changed hands to that owner, the secrets handed over.
+#[derive(Debug, Encode, Decode, Clone, PartialEq)]
+pub struct NftOwnerRecord {
+	nft_id: u32,
+	owner: AccountId,
+	handover: Option<NftSecretHandover>,
+}
+
+impl NftOwnerRecord {
+	pub fn new(nft_id: u32, owner: AccountId, handover: Option<NftSecretHandover>) -> Self {
+		Self { nft_id, owner, handover }
+	}
+
+	pub fn nft_id(&self) -> u32 {
+		self.nft_id
+	}
+
+	pub fn owner(&self) -> &AccountId {
+		&self.owner
+	}
+
+	pub fn into_handover(self) -> Option<NftSecretHandover> {
+		self.handover
+	}
+}
+
+#[cfg(feature = "sgx")]
+mod sgx {
+	use super::*;
+	use crate::error::{Error, Result};
+	use derive_more::Display;
+	use itp_settings::files::NFT_DB_PATH;
+	use itp_sgx_io::{remove_versioned, seal_versioned, unseal_versioned};
+	use std::{format, string::String};
+
+	fn owner_path(id: u32) -> String {
+		format!("{}/{}.owner.bin", NFT_DB_PATH, id)
+	}
+
+	/// Sealed owner record storage, one file per NFT next to its sealed secret.
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct NftOwnerRecordSeal;
+
+	impl NftOwnerRecordSeal {
+		/// Returns `None` if the owner of the NFT has not been observed yet.
+		pub fn get(id: u32) -> Result<Option<NftOwnerRecord>> {
+			let record: NftOwnerRecord = match unseal_versioned(&owner_path(id))? {
+				Some(b) => Decode::decode(&mut b.as_slice())?,
+				None => return Ok(None),
+			};
+			// The host could swap the files of two records, hence we check the sealed id.
+			if record.nft_id() != id {
+				return Err(Error::RecordIdMismatch)
+			}
+			Ok(Some(record))
+		}
+
+		pub fn seal(record: NftOwnerRecord) -> Result<()> {
+			Ok(record.using_encoded(|bytes| seal_versioned(bytes, &owner_path(record.nft_id())))?)
+		}
+
+		/// Removes the owner record of the NFT. Succeeds if there is none.
+		pub fn remove(id: u32) -> Result<()> {
+			Ok(remove_versioned(&owner_path(id))?)
+		}
+	}
+}
@@ -9,12 +9,28 @@ extern crate sgx_tstd as std;
 #[cfg(feature = "sgx")]
 pub use sgx::*;
 
+pub mod acl;
+pub mod capsule;
 pub mod error;
+pub mod handover;
+pub mod metadata;
+pub mod recipient_key;
+pub mod request_nonce;
+pub mod series;
+pub mod shamir;
+pub mod snapshot;
+pub mod versions;
 
 use crate::error::{Error, Result};
 use codec::{Decode, Encode};
 use std::vec::Vec;
 
+/// Index of a secret share of a NFT.
+pub type ShareIndex = u8;
+
+/// A single NFT secret, bound to the id of its NFT.
+///
+/// This is the record format of the legacy single-file `NftDb`.
 #[derive(Debug, Default, Encode, Decode, Clone)]
 pub struct Nft(u32, Vec<u8>);
 
@@ -22,8 +38,46 @@ impl Nft {
 	pub fn new(id: u32, secret: Vec<u8>) -> Self {
 		Self(id, secret)
 	}
+
+	pub fn id(&self) -> u32 {
+		self.0
+	}
+
+	pub fn into_secret(self) -> Vec<u8> {
+		self.1
+	}
+}
+
+/// A single secret share, bound to the id of its NFT and its share index.
+#[derive(Debug, Default, Encode, Decode, Clone)]
+pub struct NftShare {
+	nft_id: u32,
+	share_index: ShareIndex,
+	secret: Vec<u8>,
+}
+
+impl NftShare {
+	pub fn new(nft_id: u32, share_index: ShareIndex, secret: Vec<u8>) -> Self {
+		Self { nft_id, share_index, secret }
+	}
+
+	pub fn nft_id(&self) -> u32 {
+		self.nft_id
+	}
+
+	pub fn share_index(&self) -> ShareIndex {
+		self.share_index
+	}
+
+	pub fn into_secret(self) -> Vec<u8> {
+		self.secret
+	}
 }
 
+/// In-memory NFT secret database.
+///
+/// This is the format of the legacy single sealed file `NFT_DB`, which has been replaced by
+/// per-record sealing (see `NftDbSeal`).
 #[derive(Debug, Encode, Decode)]
 pub struct NftDb(Vec<Nft>);
 
@@ -47,29 +101,409 @@ impl NftDb {
 			Err(_) => Err(Error::NftNotFound),
 		}
 	}
+
+	pub fn into_nfts(self) -> Vec<Nft> {
+		self.0
+	}
+}
+
+/// Sorted `(nft_id, share_index)` pairs of all stored secret shares.
+#[derive(Debug, Default, Encode, Decode)]
+pub struct NftIndex(Vec<(u32, ShareIndex)>);
+
+impl NftIndex {
+	pub fn contains(&self, id: u32, share_index: ShareIndex) -> bool {
+		self.0.binary_search(&(id, share_index)).is_ok()
+	}
+
+	/// Whether any share of the NFT is stored.
+	pub fn contains_nft(&self, id: u32) -> bool {
+		!self.shares(id).is_empty()
+	}
+
+	/// Returns `false` if the share was already present.
+	pub fn insert(&mut self, id: u32, share_index: ShareIndex) -> bool {
+		match self.0.binary_search(&(id, share_index)) {
+			Ok(_) => false,
+			Err(p) => {
+				self.0.insert(p, (id, share_index));
+				true
+			},
+		}
+	}
+
+	/// Returns `false` if the share was not present.
+	pub fn remove(&mut self, id: u32, share_index: ShareIndex) -> bool {
+		match self.0.binary_search(&(id, share_index)) {
+			Ok(p) => {
+				self.0.remove(p);
+				true
+			},
+			Err(_) => false,
+		}
+	}
+
+	/// Sorted share indices of the NFT.
+	pub fn shares(&self, id: u32) -> Vec<ShareIndex> {
+		self.0
+			.iter()
+			.filter(|(nft_id, _)| *nft_id == id)
+			.map(|(_, share_index)| *share_index)
+			.collect()
+	}
+
+	/// Sorted ids of all NFTs that have at least one share stored.
+	pub fn nft_ids(&self) -> Vec<u32> {
+		let mut ids: Vec<u32> = self.0.iter().map(|(id, _)| *id).collect();
+		ids.dedup();
+		ids
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn nft_index_keeps_shares_per_nft() {
+		let mut index = NftIndex::default();
+		assert!(index.insert(2, 1));
+		assert!(index.insert(1, 3));
+		assert!(index.insert(2, 0));
+		assert!(!index.insert(2, 1));
+
+		assert_eq!(index.shares(2), vec![0, 1]);
+		assert_eq!(index.shares(1), vec![3]);
+		assert_eq!(index.nft_ids(), vec![1, 2]);
+		assert!(index.contains(1, 3));
+		assert!(!index.contains(1, 0));
+	}
+
+	#[test]
+	fn nft_index_removes_single_share() {
+		let mut index = NftIndex::default();
+		index.insert(1, 0);
+		index.insert(1, 1);
+
+		assert!(index.remove(1, 0));
+		assert!(!index.remove(1, 0));
+		assert!(index.contains_nft(1));
+		assert!(index.remove(1, 1));
+		assert!(!index.contains_nft(1));
+		assert!(index.nft_ids().is_empty());
+	}
 }
 
 #[cfg(feature = "sgx")]
 mod sgx {
 	use super::*;
+	use crate::{
+		acl::NftAclSeal,
+		capsule::CapsuleKeySeal,
+		handover::NftOwnerRecordSeal,
+		metadata::NftSecretMetadataSeal,
+		recipient_key::RecipientKeySeal,
+		series::NftSeriesSecretSeal,
+		snapshot::NftDbRecord,
+		versions::{NftDbVersions, WriteVersion},
+	};
 	use derive_more::Display;
-	use itp_settings::files::NFT_DB;
-	use itp_sgx_io::{seal, unseal, SealedIO};
+	use itp_settings::files::{
+		NFT_DB, NFT_DB_PATH, NFT_DB_SHARES_INDEX_FILE, NFT_DB_VERSIONS_FILE,
+	};
+	use itp_sgx_io::{
+		has_versioned_files, remove_versioned, seal_versioned, unseal, unseal_versioned, SealedIO,
+	};
+	use log::*;
+	use std::{format, fs, path::Path, string::String};
+
+	fn index_path() -> String {
+		format!("{}/{}", NFT_DB_PATH, NFT_DB_SHARES_INDEX_FILE)
+	}
+
+	fn versions_path() -> String {
+		format!("{}/{}", NFT_DB_PATH, NFT_DB_VERSIONS_FILE)
+	}
+
+	fn share_path(id: u32, share_index: ShareIndex) -> String {
+		format!("{}/{}_{}.bin", NFT_DB_PATH, id, share_index)
+	}
 
 	#[derive(Copy, Clone, Debug, Display)]
-	pub struct NftDbSeal;
+	pub struct NftIndexSeal;
+
+	impl SealedIO for NftIndexSeal {
+		type Error = Error;
+		type Unsealed = NftIndex;
+
+		fn unseal() -> Result<Self::Unsealed> {
+			Ok(unseal_versioned(&index_path())?
+				.map_or(Ok(NftIndex::default()), |b| Decode::decode(&mut b.as_slice()))?)
+		}
+
+		fn seal(index: Self::Unsealed) -> Result<()> {
+			Ok(index.using_encoded(|bytes| seal_versioned(bytes, &index_path()))?)
+		}
+	}
+
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct NftDbVersionsSeal;
 
-	impl SealedIO for NftDbSeal {
+	impl SealedIO for NftDbVersionsSeal {
 		type Error = Error;
-		type Unsealed = NftDb;
+		type Unsealed = NftDbVersions;
 
 		fn unseal() -> Result<Self::Unsealed> {
-			Ok(unseal(NFT_DB)
-				.map_or(Ok(NftDb::default()), |b| Decode::decode(&mut b.as_slice()))?)
+			Ok(unseal_versioned(&versions_path())?
+				.map_or(Ok(NftDbVersions::default()), |b| Decode::decode(&mut b.as_slice()))?)
+		}
+
+		fn seal(versions: Self::Unsealed) -> Result<()> {
+			Ok(versions.using_encoded(|bytes| seal_versioned(bytes, &versions_path()))?)
+		}
+	}
+
+	/// Sealed NFT secret storage.
+	///
+	/// Every secret share is sealed into its own file, next to a sealed index of all stored
+	/// shares. Storing or retrieving a share therefore only touches the affected record.
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct NftDbSeal;
+
+	impl NftDbSeal {
+		/// Creates the storage directory and migrates the legacy single-file `NftDb`, if present.
+		///
+		/// The legacy file is only migrated into an empty storage. Next to per-record files, it
+		/// has either been migrated before or been put back by the host, and migrating it would
+		/// overwrite or resurrect secrets. The enclave then refuses to start.
+		pub fn init() -> Result<()> {
+			fs::create_dir_all(NFT_DB_PATH)?;
+			if !Path::new(NFT_DB).exists() {
+				return Ok(())
+			}
+			let storage_in_use = Path::new(&index_path()).exists()
+				|| Path::new(&versions_path()).exists()
+				|| has_versioned_files()?;
+			if storage_in_use {
+				error!(
+					"Found legacy {} next to the NFT secret storage, refusing to migrate it",
+					NFT_DB
+				);
+				return Err(Error::LegacyDbReplayed)
+			}
+			Self::migrate_legacy_db()
+		}
+
+		pub fn get(id: u32, share_index: ShareIndex) -> Result<Vec<u8>> {
+			let share: NftShare = match unseal_versioned(&share_path(id, share_index))? {
+				Some(b) => Decode::decode(&mut b.as_slice())?,
+				None => return Err(Error::NftNotFound),
+			};
+			// The host could swap the files of two records, hence we check the sealed ids.
+			if share.nft_id() != id || share.share_index() != share_index {
+				return Err(Error::RecordIdMismatch)
+			}
+			Ok(share.into_secret())
+		}
+
+		pub fn upsert(id: u32, share_index: ShareIndex, secret: Vec<u8>) -> Result<()> {
+			NftShare::new(id, share_index, secret)
+				.using_encoded(|bytes| seal_versioned(bytes, &share_path(id, share_index)))?;
+
+			let mut index = NftIndexSeal::unseal()?;
+			if index.insert(id, share_index) {
+				NftIndexSeal::seal(index)?;
+			}
+			Ok(())
+		}
+
+		/// Sorted indices of the stored shares of the NFT.
+		pub fn shares(id: u32) -> Result<Vec<ShareIndex>> {
+			Ok(NftIndexSeal::unseal()?.shares(id))
+		}
+
+		/// Removes all shares, access grants and metadata of the NFT, at the given block.
+		///
+		/// The removal is recorded even if no share is stored, such that older writes that are
+		/// replicated afterwards are discarded.
+		pub fn remove(id: u32, block_number: u32) -> Result<()> {
+			let mut versions = NftDbVersionsSeal::unseal()?;
+			versions.remove_all(id, WriteVersion::removal(block_number));
+			NftDbVersionsSeal::seal(versions)?;
+
+			let shares = Self::shares(id)?;
+			if shares.is_empty() {
+				return Err(Error::NftNotFound)
+			}
+			Self::remove_shares(id, &shares)
+		}
+
+		/// Seals the share, unless the share has been written or the NFT has been removed by a
+		/// newer write. Returns whether the share has been sealed.
+		pub fn upsert_versioned(
+			id: u32,
+			share_index: ShareIndex,
+			secret: Vec<u8>,
+			version: WriteVersion,
+		) -> Result<bool> {
+			let mut versions = NftDbVersionsSeal::unseal()?;
+			if !versions.accept_upsert(id, share_index, version) {
+				return Ok(false)
+			}
+			Self::upsert(id, share_index, secret)?;
+			NftDbVersionsSeal::seal(versions)?;
+			Ok(true)
+		}
+
+		/// Removes all shares of the NFT that are older than the removal, and the access grants
+		/// if no share remains. Returns `false` if the NFT has been removed by a newer write.
+		pub fn remove_versioned(id: u32, version: WriteVersion) -> Result<bool> {
+			let mut versions = NftDbVersionsSeal::unseal()?;
+			if !versions.accept_removal(id, version) {
+				return Ok(false)
+			}
+			let outdated: Vec<ShareIndex> = Self::shares(id)?
+				.into_iter()
+				.filter(|share_index| versions.share_version(id, *share_index).is_none())
+				.collect();
+			Self::remove_shares(id, &outdated)?;
+			NftDbVersionsSeal::seal(versions)?;
+			Ok(true)
+		}
+
+		pub fn unseal_versions() -> Result<NftDbVersions> {
+			NftDbVersionsSeal::unseal()
+		}
+
+		/// Removes the given shares of the NFT, and its access grants, metadata and owner record
+		/// if no share remains.
+		fn remove_shares(id: u32, shares: &[ShareIndex]) -> Result<()> {
+			if shares.is_empty() {
+				return Ok(())
+			}
+			let mut index = NftIndexSeal::unseal()?;
+			for share_index in shares.iter() {
+				index.remove(id, *share_index);
+			}
+			let remaining = index.contains_nft(id);
+			// Update the index first, such that a failure leaves no dangling index entry.
+			NftIndexSeal::seal(index)?;
+
+			for share_index in shares {
+				remove_versioned(&share_path(id, *share_index))?;
+			}
+			if remaining {
+				return Ok(())
+			}
+			NftAclSeal::remove(id)?;
+			NftOwnerRecordSeal::remove(id)?;
+			NftSecretMetadataSeal::remove(id)
+		}
+
+		pub fn unseal_index() -> Result<NftIndex> {
+			NftIndexSeal::unseal()
 		}
 
-		fn seal(nft_db: Self::Unsealed) -> Result<()> {
-			Ok(nft_db.using_encoded(|bytes| seal(bytes, NFT_DB))?)
+		/// All sealed shares, access grants, metadata, removals, series secrets, capsule keys and
+		/// recipient keys. The removals come first, such that importing the records in order
+		/// yields the same state.
+		pub fn records() -> Result<Vec<NftDbRecord>> {
+			let index = NftIndexSeal::unseal()?;
+			let versions = NftDbVersionsSeal::unseal()?;
+			let mut records: Vec<NftDbRecord> = versions
+				.removals()
+				.iter()
+				.map(|(id, version)| NftDbRecord::Removal(*id, *version))
+				.collect();
+			for id in index.nft_ids() {
+				for share_index in index.shares(id) {
+					let secret = Self::get(id, share_index)?;
+					records.push(NftDbRecord::Share(
+						NftShare::new(id, share_index, secret),
+						versions.share_version(id, share_index),
+					));
+				}
+				if let Some(acl) = NftAclSeal::get(id)? {
+					records.push(NftDbRecord::Acl(acl));
+				}
+				if let Some(metadata) = NftSecretMetadataSeal::get(id)? {
+					records.push(NftDbRecord::Metadata(metadata));
+				}
+			}
+			for secret in NftSeriesSecretSeal::secrets()? {
+				records.push(NftDbRecord::SeriesSecret(secret));
+			}
+			for key in CapsuleKeySeal::keys()? {
+				records.push(NftDbRecord::CapsuleKey(key));
+			}
+			for key in RecipientKeySeal::keys()? {
+				records.push(NftDbRecord::RecipientKey(key));
+			}
+			Ok(records)
+		}
+
+		/// Applies the given records on top of the present ones, resolving conflicts by their
+		/// write versions. Unversioned shares only fill in shares that have never been written.
+		pub fn import(records: Vec<NftDbRecord>) -> Result<()> {
+			for record in records {
+				match record {
+					NftDbRecord::Share(share, Some(version)) => {
+						Self::upsert_versioned(
+							share.nft_id,
+							share.share_index,
+							share.secret,
+							version,
+						)?;
+					},
+					NftDbRecord::Share(share, None) => {
+						let versions = NftDbVersionsSeal::unseal()?;
+						if versions.share_version(share.nft_id, share.share_index).is_none()
+							&& versions.removal_version(share.nft_id).is_none()
+						{
+							Self::upsert(share.nft_id, share.share_index, share.secret)?;
+						}
+					},
+					NftDbRecord::Acl(acl) => {
+						NftAclSeal::import(acl)?;
+					},
+					NftDbRecord::Metadata(metadata) => NftSecretMetadataSeal::import(metadata)?,
+					NftDbRecord::Removal(id, version) => {
+						Self::remove_versioned(id, version)?;
+					},
+					NftDbRecord::SeriesSecret(secret) => {
+						NftSeriesSecretSeal::upsert(secret)?;
+					},
+					NftDbRecord::CapsuleKey(key) => {
+						CapsuleKeySeal::upsert(key)?;
+					},
+					NftDbRecord::RecipientKey(key) => {
+						RecipientKeySeal::upsert(key)?;
+					},
+				}
+			}
+			Ok(())
+		}
+
+		/// Moves all records of the legacy `NFT_DB` file into per-record files, as share 0.
+		///
+		/// If this is interrupted, the enclave refuses to start thereafter, as the partly migrated
+		/// storage cannot be told apart from a legacy file that has been put back.
+		fn migrate_legacy_db() -> Result<()> {
+			let legacy_db: NftDb = unseal(NFT_DB).map(|b| Decode::decode(&mut b.as_slice()))??;
+			let nfts = legacy_db.into_nfts();
+			info!("Migrating {} NFT secrets from {} to {}", nfts.len(), NFT_DB, NFT_DB_PATH);
+
+			let mut index = NftIndexSeal::unseal()?;
+			for nft in nfts {
+				let id = nft.id();
+				NftShare::new(id, 0, nft.into_secret())
+					.using_encoded(|bytes| seal_versioned(bytes, &share_path(id, 0)))?;
+				index.insert(id, 0);
+			}
+			NftIndexSeal::seal(index)?;
+
+			fs::remove_file(NFT_DB)?;
+			Ok(())
 		}
 	}
 }
@@ -0,0 +1,211 @@
+//! Sealed per-NFT metadata of the stored secrets, that can be disclosed without revealing
+//! anything about the secrets themselves.
+
+use crate::ShareIndex;
+use codec::{Decode, Encode};
+use itp_types::{NftSecretWriteMode, UnlockCondition};
+use sp_core::crypto::AccountId32 as AccountId;
+use std::vec::Vec;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+/// Latest write of a single secret share.
+#[derive(Debug, Encode, Decode, Clone, PartialEq)]
+pub struct ShareWrite {
+	pub share_index: ShareIndex,
+	/// Length of the written secret in bytes.
+	pub byte_length: u32,
+	/// Parentchain block number at which the write has been accepted.
+	pub block_number: u32,
+	pub writer: AccountId,
+	/// The share must not be released before the condition is met.
+	pub unlock: Option<UnlockCondition>,
+	pub mode: NftSecretWriteMode,
+}
+
+/// Latest writes of the secret shares of a NFT, sorted by share index.
+#[derive(Debug, Encode, Decode, Clone, PartialEq)]
+pub struct NftSecretMetadata {
+	nft_id: u32,
+	writes: Vec<ShareWrite>,
+}
+
+impl NftSecretMetadata {
+	pub fn new(nft_id: u32) -> Self {
+		Self { nft_id, writes: Vec::new() }
+	}
+
+	pub fn nft_id(&self) -> u32 {
+		self.nft_id
+	}
+
+	/// Records the write of a share, unless a newer write of the share is already recorded.
+	/// Replicated writes can arrive out of order, hence the latest one wins.
+	pub fn record_write(&mut self, write: ShareWrite) {
+		match self.writes.binary_search_by_key(&write.share_index, |w| w.share_index) {
+			Ok(p) if self.writes[p].block_number > write.block_number => {},
+			Ok(p) => self.writes[p] = write,
+			Err(p) => self.writes.insert(p, write),
+		}
+	}
+
+	/// Latest write of the share.
+	pub fn share_write(&self, share_index: ShareIndex) -> Option<&ShareWrite> {
+		self.writes
+			.binary_search_by_key(&share_index, |w| w.share_index)
+			.ok()
+			.map(|p| &self.writes[p])
+	}
+
+	/// Whether a write of the share in `mode` may overwrite its latest write, see
+	/// `NftSecretWriteMode::may_overwrite`.
+	pub fn may_write(&self, share_index: ShareIndex, mode: NftSecretWriteMode) -> bool {
+		self.share_write(share_index)
+			.map_or(true, |current| mode.may_overwrite(current.mode))
+	}
+
+	/// Records all writes of `other`, e.g. of an imported snapshot.
+	pub fn merge(&mut self, other: NftSecretMetadata) {
+		other.writes.into_iter().for_each(|write| self.record_write(write));
+	}
+
+	/// Latest write among the given stored shares. Writes of shares that have been removed
+	/// since are ignored.
+	pub fn latest_write(&self, stored: &[ShareIndex]) -> Option<&ShareWrite> {
+		self.writes
+			.iter()
+			.filter(|w| stored.contains(&w.share_index))
+			.max_by_key(|w| w.block_number)
+	}
+
+	/// Total length of the given stored shares in bytes.
+	pub fn byte_length(&self, stored: &[ShareIndex]) -> u32 {
+		self.writes
+			.iter()
+			.filter(|w| stored.contains(&w.share_index))
+			.map(|w| w.byte_length)
+			.sum()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn write(share_index: ShareIndex, byte_length: u32, block_number: u32) -> ShareWrite {
+		ShareWrite {
+			share_index,
+			byte_length,
+			block_number,
+			writer: AccountId::new([block_number as u8; 32]),
+			unlock: None,
+			mode: NftSecretWriteMode::Owner,
+		}
+	}
+
+	#[test]
+	fn outdated_write_does_not_supersede_newer_one() {
+		let mut metadata = NftSecretMetadata::new(1);
+		metadata.record_write(write(0, 10, 7));
+		metadata.record_write(write(0, 20, 5));
+
+		assert_eq!(metadata.latest_write(&[0]), Some(&write(0, 10, 7)));
+		assert_eq!(metadata.share_write(0), Some(&write(0, 10, 7)));
+		assert_eq!(metadata.byte_length(&[0]), 10);
+	}
+
+	#[test]
+	fn removed_shares_are_ignored() {
+		let mut metadata = NftSecretMetadata::new(1);
+		metadata.record_write(write(0, 10, 5));
+		metadata.record_write(write(1, 20, 7));
+
+		assert_eq!(metadata.byte_length(&[0, 1]), 30);
+		assert_eq!(metadata.latest_write(&[0, 1]), Some(&write(1, 20, 7)));
+		assert_eq!(metadata.latest_write(&[0]), Some(&write(0, 10, 5)));
+		assert_eq!(metadata.latest_write(&[]), None);
+	}
+
+	#[test]
+	fn locked_in_share_may_only_be_written_by_creator() {
+		let creator = NftSecretWriteMode::Creator { locked_in: true };
+		let mut metadata = NftSecretMetadata::new(1);
+		metadata.record_write(ShareWrite { mode: creator, ..write(0, 10, 5) });
+
+		assert!(!metadata.may_write(0, NftSecretWriteMode::Owner));
+		assert!(metadata.may_write(0, creator));
+		assert!(metadata.may_write(1, NftSecretWriteMode::Owner));
+	}
+}
+
+#[cfg(feature = "sgx")]
+mod sgx {
+	use super::*;
+	use crate::error::{Error, Result};
+	use derive_more::Display;
+	use itp_settings::files::NFT_DB_PATH;
+	use itp_sgx_io::{remove_versioned, seal_versioned, unseal_versioned};
+	use std::{format, string::String};
+
+	fn metadata_path(id: u32) -> String {
+		format!("{}/{}.meta.bin", NFT_DB_PATH, id)
+	}
+
+	/// Sealed metadata storage, one file per NFT next to its sealed secret.
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct NftSecretMetadataSeal;
+
+	impl NftSecretMetadataSeal {
+		/// Returns `None` if no write of the NFT has been recorded, e.g. for secrets that have
+		/// been stored before the metadata was introduced.
+		pub fn get(id: u32) -> Result<Option<NftSecretMetadata>> {
+			let metadata: NftSecretMetadata = match unseal_versioned(&metadata_path(id))? {
+				Some(b) => Decode::decode(&mut b.as_slice())?,
+				None => return Ok(None),
+			};
+			// The host could swap the files of two records, hence we check the sealed id.
+			if metadata.nft_id() != id {
+				return Err(Error::RecordIdMismatch)
+			}
+			Ok(Some(metadata))
+		}
+
+		pub fn seal(metadata: NftSecretMetadata) -> Result<()> {
+			Ok(metadata
+				.using_encoded(|bytes| seal_versioned(bytes, &metadata_path(metadata.nft_id())))?)
+		}
+
+		pub fn record_write(id: u32, write: ShareWrite) -> Result<()> {
+			let mut metadata = Self::get(id)?.unwrap_or_else(|| NftSecretMetadata::new(id));
+			metadata.record_write(write);
+			Self::seal(metadata)
+		}
+
+		/// Fails with `Error::WriteDenied` if a write of the share in `mode` may not overwrite
+		/// its latest write.
+		pub fn check_write(
+			id: u32,
+			share_index: ShareIndex,
+			mode: NftSecretWriteMode,
+		) -> Result<()> {
+			match Self::get(id)? {
+				Some(metadata) if !metadata.may_write(share_index, mode) => Err(Error::WriteDenied),
+				_ => Ok(()),
+			}
+		}
+
+		/// Merges the metadata into the present one of its NFT.
+		pub fn import(metadata: NftSecretMetadata) -> Result<()> {
+			let id = metadata.nft_id();
+			let mut present = Self::get(id)?.unwrap_or_else(|| NftSecretMetadata::new(id));
+			present.merge(metadata);
+			Self::seal(present)
+		}
+
+		/// Removes the metadata of the NFT. Succeeds if there is none.
+		pub fn remove(id: u32) -> Result<()> {
+			Ok(remove_versioned(&metadata_path(id))?)
+		}
+	}
+}
@@ -0,0 +1,144 @@
+//! Sealed recipient keys, that the secrets of a NFT are re-encrypted to once the account that
+//! registered the key acquires the NFT.
+
+use crate::versions::WriteVersion;
+use codec::{Decode, Encode};
+use sp_core::crypto::AccountId32 as AccountId;
+use std::vec::Vec;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+/// JSON encoded RSA-3072 public key registered by an account.
+#[derive(Debug, Encode, Decode, Clone, PartialEq)]
+pub struct RecipientKey {
+	account: AccountId,
+	/// Version of the write, replicated writes can arrive out of order.
+	version: WriteVersion,
+	key: Vec<u8>,
+}
+
+impl RecipientKey {
+	pub fn new(account: AccountId, block_number: u32, key: Vec<u8>) -> Self {
+		let version = WriteVersion::upsert(block_number, &key);
+		Self { account, version, key }
+	}
+
+	pub fn account(&self) -> &AccountId {
+		&self.account
+	}
+
+	pub fn version(&self) -> WriteVersion {
+		self.version
+	}
+
+	pub fn key(&self) -> &[u8] {
+		&self.key
+	}
+}
+
+/// Sorted accounts that registered a recipient key.
+#[derive(Debug, Default, Encode, Decode)]
+pub struct RecipientKeyIndex(Vec<AccountId>);
+
+impl RecipientKeyIndex {
+	/// Returns `false` if the account was already present.
+	pub fn insert(&mut self, account: &AccountId) -> bool {
+		match self.0.binary_search(account) {
+			Ok(_) => false,
+			Err(p) => {
+				self.0.insert(p, account.clone());
+				true
+			},
+		}
+	}
+
+	pub fn accounts(&self) -> &[AccountId] {
+		&self.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recipient_key_index_is_sorted_and_deduplicated() {
+		let mut index = RecipientKeyIndex::default();
+		assert!(index.insert(&AccountId::new([2; 32])));
+		assert!(index.insert(&AccountId::new([1; 32])));
+		assert!(!index.insert(&AccountId::new([2; 32])));
+
+		assert_eq!(index.accounts(), &[AccountId::new([1; 32]), AccountId::new([2; 32])]);
+	}
+}
+
+#[cfg(feature = "sgx")]
+mod sgx {
+	use super::*;
+	use crate::error::{Error, Result};
+	use derive_more::Display;
+	use itp_settings::files::{NFT_DB_PATH, NFT_DB_RECIPIENT_KEY_INDEX_FILE};
+	use itp_sgx_io::{seal_versioned, unseal_versioned};
+	use std::{format, string::String};
+
+	fn index_path() -> String {
+		format!("{}/{}", NFT_DB_PATH, NFT_DB_RECIPIENT_KEY_INDEX_FILE)
+	}
+
+	fn recipient_key_path(account: &AccountId) -> String {
+		let account: &[u8] = account.as_ref();
+		let hex: String = account.iter().map(|b| format!("{:02x}", b)).collect();
+		format!("{}/recipient_{}.bin", NFT_DB_PATH, hex)
+	}
+
+	fn unseal_index() -> Result<RecipientKeyIndex> {
+		Ok(unseal_versioned(&index_path())?
+			.map_or(Ok(RecipientKeyIndex::default()), |b| Decode::decode(&mut b.as_slice()))?)
+	}
+
+	/// Sealed recipient key storage, one file per account next to a sealed index of all accounts.
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct RecipientKeySeal;
+
+	impl RecipientKeySeal {
+		/// Returns `None` if the account has not registered a key.
+		pub fn get(account: &AccountId) -> Result<Option<RecipientKey>> {
+			let key: RecipientKey = match unseal_versioned(&recipient_key_path(account))? {
+				Some(b) => Decode::decode(&mut b.as_slice())?,
+				None => return Ok(None),
+			};
+			// The host could swap the files of two records, hence we check the sealed account.
+			if key.account() != account {
+				return Err(Error::RecordIdMismatch)
+			}
+			Ok(Some(key))
+		}
+
+		/// Seals the key, unless a newer write of the account is present. Returns whether the
+		/// key has been sealed.
+		pub fn upsert(key: RecipientKey) -> Result<bool> {
+			if let Some(present) = Self::get(key.account())? {
+				if present.version() >= key.version() {
+					return Ok(false)
+				}
+			}
+			key.using_encoded(|bytes| seal_versioned(bytes, &recipient_key_path(key.account())))?;
+
+			let mut index = unseal_index()?;
+			if index.insert(key.account()) {
+				index.using_encoded(|bytes| seal_versioned(bytes, &index_path()))?;
+			}
+			Ok(true)
+		}
+
+		/// All sealed recipient keys.
+		pub fn keys() -> Result<Vec<RecipientKey>> {
+			let mut keys = Vec::new();
+			for account in unseal_index()?.accounts() {
+				keys.extend(Self::get(account)?);
+			}
+			Ok(keys)
+		}
+	}
+}
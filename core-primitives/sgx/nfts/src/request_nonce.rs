@@ -0,0 +1,54 @@
+//! Sealed store of the per-account nonces of signed requests, used to reject replayed requests.
+
+use crate::error::{Error, Result};
+use codec::{Decode, Encode};
+use sp_core::sr25519::Public;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+#[derive(Debug, Default, Encode, Decode)]
+pub struct RequestNonceDb(BTreeMap<Public, u32>);
+
+impl RequestNonceDb {
+	/// Nonce the next signed request of `account` has to carry.
+	pub fn next_nonce(&self, account: &Public) -> u32 {
+		self.0.get(account).copied().unwrap_or_default()
+	}
+
+	/// Consume `nonce` for `account`. Fails if it is not the expected next nonce.
+	pub fn use_nonce(&mut self, account: Public, nonce: u32) -> Result<()> {
+		let expected = self.next_nonce(&account);
+		if nonce != expected {
+			return Err(Error::InvalidNonce)
+		}
+		self.0.insert(account, expected.saturating_add(1));
+		Ok(())
+	}
+}
+
+#[cfg(feature = "sgx")]
+mod sgx {
+	use super::*;
+	use derive_more::Display;
+	use itp_settings::files::REQUEST_NONCE_DB;
+	use itp_sgx_io::{seal_versioned, unseal_versioned, SealedIO};
+
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct RequestNonceDbSeal;
+
+	impl SealedIO for RequestNonceDbSeal {
+		type Error = Error;
+		type Unsealed = RequestNonceDb;
+
+		fn unseal() -> Result<Self::Unsealed> {
+			Ok(unseal_versioned(REQUEST_NONCE_DB)?
+				.map_or(Ok(RequestNonceDb::default()), |b| Decode::decode(&mut b.as_slice()))?)
+		}
+
+		fn seal(nonce_db: Self::Unsealed) -> Result<()> {
+			Ok(nonce_db.using_encoded(|bytes| seal_versioned(bytes, REQUEST_NONCE_DB))?)
+		}
+	}
+}
@@ -0,0 +1,154 @@
+//! Sealed secrets of NFT series. The creator of a series stores a single secret for the whole
+//! series, which the owners of all NFTs in the series can read.
+
+use crate::versions::WriteVersion;
+use codec::{Decode, Encode};
+use sp_core::crypto::AccountId32 as AccountId;
+use std::vec::Vec;
+
+#[cfg(feature = "sgx")]
+pub use sgx::*;
+
+/// Id of a NFT series, as in `NFTData::series_id`.
+pub type SeriesId = Vec<u8>;
+
+/// Secret of a NFT series, bound to its series id.
+#[derive(Debug, Encode, Decode, Clone, PartialEq)]
+pub struct SeriesSecret {
+	series_id: SeriesId,
+	/// Version of the write, replicated writes can arrive out of order.
+	version: WriteVersion,
+	writer: AccountId,
+	secret: Vec<u8>,
+}
+
+impl SeriesSecret {
+	pub fn new(series_id: SeriesId, block_number: u32, writer: AccountId, secret: Vec<u8>) -> Self {
+		let version = WriteVersion::upsert(block_number, &secret);
+		Self { series_id, version, writer, secret }
+	}
+
+	pub fn series_id(&self) -> &[u8] {
+		&self.series_id
+	}
+
+	pub fn version(&self) -> WriteVersion {
+		self.version
+	}
+
+	pub fn writer(&self) -> &AccountId {
+		&self.writer
+	}
+
+	pub fn into_secret(self) -> Vec<u8> {
+		self.secret
+	}
+}
+
+/// Sorted ids of all series with a stored secret.
+#[derive(Debug, Default, Encode, Decode)]
+pub struct SeriesIndex(Vec<SeriesId>);
+
+impl SeriesIndex {
+	/// Returns `false` if the series was already present.
+	pub fn insert(&mut self, series_id: &[u8]) -> bool {
+		match self.0.binary_search_by(|id| id.as_slice().cmp(series_id)) {
+			Ok(_) => false,
+			Err(p) => {
+				self.0.insert(p, series_id.to_vec());
+				true
+			},
+		}
+	}
+
+	pub fn series_ids(&self) -> &[SeriesId] {
+		&self.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn series_index_is_sorted_and_deduplicated() {
+		let mut index = SeriesIndex::default();
+		assert!(index.insert(b"series-b"));
+		assert!(index.insert(b"series-a"));
+		assert!(!index.insert(b"series-b"));
+
+		assert_eq!(index.series_ids(), &[b"series-a".to_vec(), b"series-b".to_vec()][..]);
+	}
+}
+
+#[cfg(feature = "sgx")]
+mod sgx {
+	use super::*;
+	use crate::error::{Error, Result};
+	use derive_more::Display;
+	use itp_settings::files::{NFT_DB_PATH, NFT_DB_SERIES_INDEX_FILE};
+	use itp_sgx_io::{seal_versioned, unseal_versioned};
+	use sp_core::hashing::blake2_128;
+	use std::{format, string::String};
+
+	fn index_path() -> String {
+		format!("{}/{}", NFT_DB_PATH, NFT_DB_SERIES_INDEX_FILE)
+	}
+
+	/// Series ids are arbitrary bytes, hence the file is named after their digest.
+	fn series_path(series_id: &[u8]) -> String {
+		let digest: String = blake2_128(series_id).iter().map(|b| format!("{:02x}", b)).collect();
+		format!("{}/series_{}.bin", NFT_DB_PATH, digest)
+	}
+
+	fn unseal_index() -> Result<SeriesIndex> {
+		Ok(unseal_versioned(&index_path())?
+			.map_or(Ok(SeriesIndex::default()), |b| Decode::decode(&mut b.as_slice()))?)
+	}
+
+	/// Sealed series secret storage, one file per series next to a sealed index of all series.
+	#[derive(Copy, Clone, Debug, Display)]
+	pub struct NftSeriesSecretSeal;
+
+	impl NftSeriesSecretSeal {
+		/// Returns `None` if no secret has been stored for the series.
+		pub fn get(series_id: &[u8]) -> Result<Option<SeriesSecret>> {
+			let secret: SeriesSecret = match unseal_versioned(&series_path(series_id))? {
+				Some(b) => Decode::decode(&mut b.as_slice())?,
+				None => return Ok(None),
+			};
+			// The host could swap the files of two records, hence we check the sealed id.
+			if secret.series_id() != series_id {
+				return Err(Error::RecordIdMismatch)
+			}
+			Ok(Some(secret))
+		}
+
+		/// Seals the secret, unless a newer write of the series is present. Returns whether the
+		/// secret has been sealed.
+		pub fn upsert(secret: SeriesSecret) -> Result<bool> {
+			if let Some(present) = Self::get(secret.series_id())? {
+				if present.version() >= secret.version() {
+					return Ok(false)
+				}
+			}
+			secret
+				.using_encoded(|bytes| seal_versioned(bytes, &series_path(secret.series_id())))?;
+
+			let mut index = unseal_index()?;
+			if index.insert(secret.series_id()) {
+				index.using_encoded(|bytes| seal_versioned(bytes, &index_path()))?;
+			}
+			Ok(true)
+		}
+
+		/// All sealed series secrets.
+		pub fn secrets() -> Result<Vec<SeriesSecret>> {
+			let mut secrets = Vec::new();
+			for series_id in unseal_index()?.series_ids() {
+				secrets.extend(Self::get(series_id)?);
+			}
+			Ok(secrets)
+		}
+	}
+}
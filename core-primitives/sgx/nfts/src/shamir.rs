@@ -0,0 +1,234 @@
+//! Shamir secret sharing over GF(256), used to distribute a NFT secret across the registered
+//! workers, such that any `threshold` of them can reconstruct it but fewer learn nothing.
+
+use crate::error::{Error, Result};
+use codec::{Decode, Encode};
+use std::{vec, vec::Vec};
+
+/// A single Shamir share: the evaluation of the sharing polynomials at `x` for every secret byte.
+#[derive(Debug, Default, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct SecretShare {
+	pub x: u8,
+	pub y: Vec<u8>,
+}
+
+/// Shamir share of a NFT secret as it is held by a single worker.
+///
+/// All shares of one sharing carry the same `tag`, such that shares of an outdated sharing
+/// (e.g. on a worker that missed an update) are never combined with those of a newer one.
+#[derive(Debug, Default, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct ThresholdShare {
+	/// Parentchain block number at which the secret was shared.
+	pub block_number: u32,
+	/// Random identifier of the sharing.
+	pub tag: [u8; 16],
+	/// Number of shares required for reconstruction.
+	pub threshold: u8,
+	pub share: SecretShare,
+}
+
+/// Splits `secret` into `share_count` shares, any `threshold` of which reconstruct it.
+///
+/// The shares are evaluated at `x = 1..=share_count`. `fill_random` has to fill the
+/// given buffer with cryptographically secure random bytes.
+pub fn split<F: FnMut(&mut [u8])>(
+	secret: &[u8],
+	threshold: u8,
+	share_count: u8,
+	mut fill_random: F,
+) -> Result<Vec<SecretShare>> {
+	if threshold == 0 || threshold > share_count {
+		return Err(Error::InvalidThreshold)
+	}
+
+	let mut shares: Vec<SecretShare> = (1..=share_count)
+		.map(|x| SecretShare { x, y: Vec::with_capacity(secret.len()) })
+		.collect();
+
+	// coefficients[0] is the secret byte, the others are random.
+	let mut coefficients = vec![0u8; threshold as usize];
+	for byte in secret {
+		coefficients[0] = *byte;
+		fill_random(&mut coefficients[1..]);
+		for share in shares.iter_mut() {
+			share.y.push(evaluate(&coefficients, share.x));
+		}
+	}
+	for c in coefficients.iter_mut() {
+		*c = 0;
+	}
+
+	Ok(shares)
+}
+
+/// Reconstructs the secret from at least `threshold` distinct shares of the same sharing.
+///
+/// Combining fewer than `threshold` shares does not fail, but yields a wrong secret.
+pub fn combine(shares: &[SecretShare]) -> Result<Vec<u8>> {
+	let first = shares.first().ok_or(Error::NotEnoughShares)?;
+	let len = first.y.len();
+	for (i, share) in shares.iter().enumerate() {
+		if share.x == 0
+			|| share.y.len() != len
+			|| shares[..i].iter().any(|other| other.x == share.x)
+		{
+			return Err(Error::InconsistentShares)
+		}
+	}
+
+	// Lagrange interpolation at x = 0.
+	let basis: Vec<u8> = shares
+		.iter()
+		.map(|share| {
+			shares
+				.iter()
+				.filter(|other| other.x != share.x)
+				.fold(1u8, |acc, other| gf_mul(acc, gf_mul(other.x, gf_inv(other.x ^ share.x))))
+		})
+		.collect();
+
+	Ok((0..len)
+		.map(|i| {
+			shares
+				.iter()
+				.zip(basis.iter())
+				.fold(0u8, |acc, (share, l)| acc ^ gf_mul(share.y[i], *l))
+		})
+		.collect())
+}
+
+/// Reconstructs the secret from the newest sharing among the shares.
+///
+/// Fails if fewer than `threshold` shares of the newest sharing are present. Falling back to an
+/// older sharing would silently return an outdated secret.
+pub fn combine_newest(shares: &[ThresholdShare]) -> Result<Vec<u8>> {
+	let newest = shares
+		.iter()
+		.max_by_key(|s| (s.block_number, s.tag))
+		.ok_or(Error::NotEnoughShares)?;
+
+	let mut candidates: Vec<SecretShare> = shares
+		.iter()
+		.filter(|s| s.block_number == newest.block_number && s.tag == newest.tag)
+		.map(|s| s.share.clone())
+		.collect();
+	candidates.sort_by_key(|s| s.x);
+	candidates.dedup_by_key(|s| s.x);
+	if candidates.len() < newest.threshold as usize {
+		return Err(Error::NotEnoughShares)
+	}
+	combine(&candidates[..newest.threshold as usize])
+}
+
+/// Evaluates the polynomial with the given coefficients at `x` (Horner's method).
+fn evaluate(coefficients: &[u8], x: u8) -> u8 {
+	coefficients.iter().rev().fold(0u8, |acc, c| gf_mul(acc, x) ^ c)
+}
+
+/// Multiplication in GF(2^8) with the AES reduction polynomial, without data dependent branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+	let mut product = 0u8;
+	for _ in 0..8 {
+		product ^= a & 0u8.wrapping_sub(b & 1);
+		let carry = 0u8.wrapping_sub(a >> 7);
+		a = (a << 1) ^ (0x1b & carry);
+		b >>= 1;
+	}
+	product
+}
+
+/// Multiplicative inverse in GF(2^8), computed as a^254.
+fn gf_inv(a: u8) -> u8 {
+	let a2 = gf_mul(a, a);
+	let a4 = gf_mul(a2, a2);
+	let a8 = gf_mul(a4, a4);
+	let a16 = gf_mul(a8, a8);
+	let a32 = gf_mul(a16, a16);
+	let a64 = gf_mul(a32, a32);
+	let a128 = gf_mul(a64, a64);
+	gf_mul(a128, gf_mul(a64, gf_mul(a32, gf_mul(a16, gf_mul(a8, gf_mul(a4, a2))))))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn counter_rng() -> impl FnMut(&mut [u8]) {
+		let mut state = 7u8;
+		move |buf: &mut [u8]| {
+			for b in buf.iter_mut() {
+				state = state.wrapping_mul(31).wrapping_add(17);
+				*b = state;
+			}
+		}
+	}
+
+	fn threshold_share(block_number: u32, tag: u8, share: SecretShare) -> ThresholdShare {
+		ThresholdShare { block_number, tag: [tag; 16], threshold: 2, share }
+	}
+
+	#[test]
+	fn gf_inv_is_inverse() {
+		for a in 1..=255u8 {
+			assert_eq!(gf_mul(a, gf_inv(a)), 1);
+		}
+	}
+
+	#[test]
+	fn any_threshold_shares_reconstruct_secret() {
+		let secret = b"a very secret nft secret".to_vec();
+		let shares = split(&secret, 3, 5, counter_rng()).unwrap();
+		assert_eq!(shares.len(), 5);
+
+		assert_eq!(combine(&shares[..3]).unwrap(), secret);
+		assert_eq!(combine(&shares[2..]).unwrap(), secret);
+		assert_eq!(
+			combine(&[shares[4].clone(), shares[0].clone(), shares[2].clone()]).unwrap(),
+			secret
+		);
+		assert_eq!(combine(&shares).unwrap(), secret);
+	}
+
+	#[test]
+	fn less_than_threshold_shares_do_not_reconstruct_secret() {
+		let secret = b"a very secret nft secret".to_vec();
+		let shares = split(&secret, 3, 5, counter_rng()).unwrap();
+
+		assert_ne!(combine(&shares[..2]).unwrap(), secret);
+	}
+
+	#[test]
+	fn split_rejects_invalid_threshold() {
+		assert!(matches!(split(b"secret", 0, 3, counter_rng()), Err(Error::InvalidThreshold)));
+		assert!(matches!(split(b"secret", 4, 3, counter_rng()), Err(Error::InvalidThreshold)));
+	}
+
+	#[test]
+	fn combine_rejects_duplicate_shares() {
+		let shares = split(b"secret", 2, 3, counter_rng()).unwrap();
+
+		assert!(matches!(
+			combine(&[shares[0].clone(), shares[0].clone()]),
+			Err(Error::InconsistentShares)
+		));
+	}
+
+	#[test]
+	fn combine_newest_ignores_outdated_sharing_and_fails_on_incomplete_newest() {
+		let old = split(b"old secret", 2, 3, counter_rng()).unwrap();
+		let new = split(b"new secret", 2, 3, counter_rng()).unwrap();
+
+		let shares = vec![
+			threshold_share(1, 1, old[0].clone()),
+			threshold_share(1, 1, old[1].clone()),
+			threshold_share(5, 2, new[2].clone()),
+			threshold_share(5, 2, new[0].clone()),
+		];
+		assert_eq!(combine_newest(&shares).unwrap(), b"new secret".to_vec());
+
+		// The newer sharing lacks shares, the outdated secret must not be returned instead.
+		assert!(matches!(combine_newest(&shares[..3]), Err(Error::NotEnoughShares)));
+		assert!(matches!(combine_newest(&shares[2..3]), Err(Error::NotEnoughShares)));
+		assert!(matches!(combine_newest(&[]), Err(Error::NotEnoughShares)));
+	}
+}
@@ -0,0 +1,118 @@
+//! Versioned snapshot of the NFT secret database, streamed to a newly joined worker during
+//! key provisioning.
+//!
+//! A snapshot consists of a `NftDbSnapshotHeader`, the announced number of `NftDbRecord`s and
+//! the final `NftDbSnapshotDigest` over all records.
+//!
+//! The same records make up the encrypted `NftDbBackup`.
+
+use crate::{
+	acl::NftAcl, capsule::CapsuleKey, metadata::NftSecretMetadata, recipient_key::RecipientKey,
+	series::SeriesSecret, versions::WriteVersion, NftShare,
+};
+use codec::{Decode, Encode};
+use sp_core::hashing::blake2_256;
+use std::vec::Vec;
+
+/// Version of the snapshot format, has to be increased on every incompatible change.
+pub const NFT_DB_SNAPSHOT_VERSION: u16 = 1;
+
+#[derive(Debug, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct NftDbSnapshotHeader {
+	pub version: u16,
+	pub record_count: u32,
+}
+
+impl NftDbSnapshotHeader {
+	pub fn new(record_count: u32) -> Self {
+		Self { version: NFT_DB_SNAPSHOT_VERSION, record_count }
+	}
+}
+
+/// A single sealed record of the NFT secret database.
+#[derive(Debug, Encode, Decode, Clone)]
+pub enum NftDbRecord {
+	/// A secret share and the version of its latest write, if it has been written since
+	/// writes are versioned.
+	Share(NftShare, Option<WriteVersion>),
+	Acl(NftAcl),
+	/// Latest removal of the secrets of a NFT.
+	Removal(u32, WriteVersion),
+	Metadata(NftSecretMetadata),
+	SeriesSecret(SeriesSecret),
+	CapsuleKey(CapsuleKey),
+	RecipientKey(RecipientKey),
+}
+
+/// AES-GCM encrypted backup of the NFT secret database.
+///
+/// The plaintext is the encoding of all `NftDbRecord`s, the encoded `version` is authenticated
+/// as additional data.
+#[derive(Debug, Encode, Decode, Clone)]
+pub struct NftDbBackup {
+	pub version: u16,
+	pub nonce: [u8; 12],
+	pub ciphertext: Vec<u8>,
+	pub mac: [u8; 16],
+}
+
+/// Running digest over the records of a snapshot, chaining the encoding of every record onto
+/// the digest of its predecessors.
+///
+/// The receiver recomputes the digest and discards the snapshot on a mismatch, such that a
+/// truncated or reordered stream is never imported.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NftDbSnapshotDigest([u8; 32]);
+
+impl NftDbSnapshotDigest {
+	pub fn update(&mut self, record: &NftDbRecord) {
+		let mut data = self.0.to_vec();
+		record.encode_to(&mut data);
+		self.0 = blake2_256(&data);
+	}
+
+	pub fn finalize(self) -> [u8; 32] {
+		self.0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::crypto::AccountId32 as AccountId;
+
+	fn records() -> Vec<NftDbRecord> {
+		vec![
+			NftDbRecord::Removal(2, WriteVersion::removal(3)),
+			NftDbRecord::Share(NftShare::new(1, 0, b"first".to_vec()), None),
+			NftDbRecord::Share(
+				NftShare::new(1, 1, b"second".to_vec()),
+				Some(WriteVersion::upsert(5, b"second")),
+			),
+			NftDbRecord::Acl(NftAcl::new(1, AccountId::new([1u8; 32]))),
+		]
+	}
+
+	fn digest(records: &[NftDbRecord]) -> [u8; 32] {
+		let mut digest = NftDbSnapshotDigest::default();
+		records.iter().for_each(|record| digest.update(record));
+		digest.finalize()
+	}
+
+	#[test]
+	fn digest_detects_reordered_records() {
+		let records = records();
+		let mut reordered = records.clone();
+		reordered.swap(0, 1);
+
+		assert_eq!(digest(&records), digest(&records()));
+		assert_ne!(digest(&records), digest(&reordered));
+	}
+
+	#[test]
+	fn digest_detects_missing_records() {
+		let records = records();
+
+		assert_ne!(digest(&records), digest(&records[..3]));
+	}
+}
@@ -0,0 +1,179 @@
+//! Versions of the writes to the NFT secret database, used to resolve conflicting writes that
+//! are replicated between workers.
+//!
+//! Every write is versioned by the parentchain block number at which it has been accepted.
+//! A replicated write is only applied if it is newer than the present state, hence all replicas
+//! converge to the same state regardless of the order in which they receive the writes.
+
+use crate::ShareIndex;
+use codec::{Decode, Encode};
+use sp_core::hashing::blake2_256;
+use std::vec::Vec;
+
+/// Version of a single write.
+///
+/// Writes accepted at the same block are ordered by the digest of the written secret, such that
+/// all replicas agree on the winner. A removal wins against upserts of the same block.
+#[derive(Debug, Default, Encode, Decode, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WriteVersion {
+	pub block_number: u32,
+	pub digest: [u8; 32],
+}
+
+impl WriteVersion {
+	pub fn upsert(block_number: u32, secret: &[u8]) -> Self {
+		Self { block_number, digest: blake2_256(secret) }
+	}
+
+	pub fn removal(block_number: u32) -> Self {
+		Self { block_number, digest: [u8::MAX; 32] }
+	}
+}
+
+/// Versions of the stored shares and of the latest removal of every NFT.
+///
+/// Shares stored before versioning was introduced have no version, they are superseded by any
+/// versioned write.
+#[derive(Debug, Default, Encode, Decode, Clone, PartialEq, Eq)]
+pub struct NftDbVersions {
+	shares: Vec<(u32, ShareIndex, WriteVersion)>,
+	removals: Vec<(u32, WriteVersion)>,
+}
+
+impl NftDbVersions {
+	pub fn share_version(&self, id: u32, share_index: ShareIndex) -> Option<WriteVersion> {
+		self.shares
+			.binary_search_by_key(&(id, share_index), |(id, share_index, _)| (*id, *share_index))
+			.ok()
+			.map(|p| self.shares[p].2)
+	}
+
+	pub fn removal_version(&self, id: u32) -> Option<WriteVersion> {
+		self.removals
+			.binary_search_by_key(&id, |(id, _)| *id)
+			.ok()
+			.map(|p| self.removals[p].1)
+	}
+
+	/// Latest removal of every NFT, sorted by NFT id.
+	pub fn removals(&self) -> &[(u32, WriteVersion)] {
+		&self.removals
+	}
+
+	/// Records the version of an upsert of the share. Returns `false` if the share has been
+	/// written or the NFT has been removed by a newer write, in which case the upsert must be
+	/// discarded.
+	pub fn accept_upsert(
+		&mut self,
+		id: u32,
+		share_index: ShareIndex,
+		version: WriteVersion,
+	) -> bool {
+		if self.removal_version(id).map_or(false, |removal| removal >= version) {
+			return false
+		}
+		match self
+			.shares
+			.binary_search_by_key(&(id, share_index), |(id, share_index, _)| (*id, *share_index))
+		{
+			Ok(p) if self.shares[p].2 >= version => false,
+			Ok(p) => {
+				self.shares[p].2 = version;
+				true
+			},
+			Err(p) => {
+				self.shares.insert(p, (id, share_index, version));
+				true
+			},
+		}
+	}
+
+	/// Records the version of a removal of the NFT and forgets the versions of all older shares,
+	/// which have to be removed. Returns `false` if the NFT has been removed by a newer write.
+	///
+	/// Shares written after the removal are kept.
+	pub fn accept_removal(&mut self, id: u32, version: WriteVersion) -> bool {
+		match self.removals.binary_search_by_key(&id, |(id, _)| *id) {
+			Ok(p) if self.removals[p].1 >= version => return false,
+			Ok(p) => self.removals[p].1 = version,
+			Err(p) => self.removals.insert(p, (id, version)),
+		}
+		self.shares
+			.retain(|(share_id, _, share_version)| *share_id != id || *share_version > version);
+		true
+	}
+
+	/// Records the removal of all shares of the NFT, e.g. once it has been burned, and forgets
+	/// their versions. The removal is kept, such that an older write that arrives late is
+	/// discarded.
+	pub fn remove_all(&mut self, id: u32, version: WriteVersion) {
+		self.shares.retain(|(share_id, _, _)| *share_id != id);
+		match self.removals.binary_search_by_key(&id, |(id, _)| *id) {
+			Ok(p) => self.removals[p].1 = self.removals[p].1.max(version),
+			Err(p) => self.removals.insert(p, (id, version)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn newer_upsert_supersedes_older_one() {
+		let mut versions = NftDbVersions::default();
+
+		assert!(versions.accept_upsert(1, 0, WriteVersion::upsert(5, b"old")));
+		assert!(versions.accept_upsert(1, 0, WriteVersion::upsert(7, b"new")));
+		assert!(!versions.accept_upsert(1, 0, WriteVersion::upsert(6, b"outdated")));
+		assert_eq!(versions.share_version(1, 0), Some(WriteVersion::upsert(7, b"new")));
+	}
+
+	#[test]
+	fn conflicting_upserts_of_same_block_are_ordered_independent_of_arrival() {
+		let a = WriteVersion::upsert(5, b"a");
+		let b = WriteVersion::upsert(5, b"b");
+
+		let mut first = NftDbVersions::default();
+		first.accept_upsert(1, 0, a);
+		first.accept_upsert(1, 0, b);
+		let mut second = NftDbVersions::default();
+		second.accept_upsert(1, 0, b);
+		second.accept_upsert(1, 0, a);
+
+		assert_eq!(first, second);
+	}
+
+	#[test]
+	fn removal_discards_older_shares_only() {
+		let mut versions = NftDbVersions::default();
+		versions.accept_upsert(1, 0, WriteVersion::upsert(5, b"old"));
+		versions.accept_upsert(1, 1, WriteVersion::upsert(9, b"new"));
+		versions.accept_upsert(2, 0, WriteVersion::upsert(5, b"other"));
+
+		assert!(versions.accept_removal(1, WriteVersion::removal(7)));
+		assert_eq!(versions.share_version(1, 0), None);
+		assert!(versions.share_version(1, 1).is_some());
+		assert!(versions.share_version(2, 0).is_some());
+
+		assert!(!versions.accept_upsert(1, 0, WriteVersion::upsert(6, b"outdated")));
+		assert!(!versions.accept_upsert(1, 0, WriteVersion::upsert(7, b"same block")));
+		assert!(!versions.accept_removal(1, WriteVersion::removal(6)));
+		assert!(versions.accept_upsert(1, 0, WriteVersion::upsert(8, b"newer")));
+	}
+
+	#[test]
+	fn removal_of_all_shares_discards_late_older_writes() {
+		let mut versions = NftDbVersions::default();
+		versions.accept_upsert(1, 0, WriteVersion::upsert(5, b"secret"));
+		versions.accept_upsert(1, 1, WriteVersion::upsert(9, b"secret"));
+
+		versions.remove_all(1, WriteVersion::removal(8));
+		versions.remove_all(1, WriteVersion::removal(7));
+
+		assert_eq!(versions.share_version(1, 0), None);
+		assert_eq!(versions.share_version(1, 1), None);
+		assert_eq!(versions.removal_version(1), Some(WriteVersion::removal(8)));
+		assert!(!versions.accept_upsert(1, 0, WriteVersion::upsert(6, b"late")));
+	}
+}
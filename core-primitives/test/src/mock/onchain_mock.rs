@@ -106,6 +106,10 @@ impl EnclaveAttestationOCallApi for OnchainMock {
 		Ok(42)
 	}
 
+	fn get_mu_ra_socket(&self, _worker_url: &str) -> SgxResult<i32> {
+		Ok(43)
+	}
+
 	fn get_quote(
 		&self,
 		_sig_rl: Vec<u8>,
@@ -5,6 +5,7 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "sgx")]
 use sgx_tstd as std;
 use sp_core::{
+	hashing::{blake2_128, blake2_256},
 	sr25519::{Pair as KeyPair, Signature},
 	Pair,
 };
@@ -166,47 +167,591 @@ pub struct NFTData {
 	pub converted_to_capsule: bool,
 }
 
+/// Data related to a NFT series, as kept by the `Nfts` pallet.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct NFTSeriesData {
+	// Series owner, i.e. the creator of the series
+	pub owner: [u8; 32],
+	// Is the series still open for new NFTs
+	pub draft: bool,
+}
+
+/// Data related to a capsule, as kept by the `Capsules` pallet.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+pub struct CapsuleData {
+	// Capsule owner
+	pub owner: [u8; 32],
+	// IPFS reference of the capsule content
+	pub ipfs_reference: String,
+}
+
+/// Lifecycle state of a NFT that denies access to its secret.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NftSecretAccessDenial {
+	/// The NFT is being transmitted to a new owner.
+	InTransmission,
+	/// The NFT is listed for sale on the marketplace.
+	ListedForSale,
+}
+
+impl NftSecretAccessDenial {
+	/// JSON-RPC error code returned to the client.
+	pub fn error_code(&self) -> i64 {
+		match self {
+			NftSecretAccessDenial::InTransmission => NFT_IN_TRANSMISSION_ERROR_CODE,
+			NftSecretAccessDenial::ListedForSale => NFT_LISTED_FOR_SALE_ERROR_CODE,
+		}
+	}
+}
+
+/// Deployment policy on the lifecycle states in which NFT secrets may be retrieved or updated.
+///
+/// Access is always denied while a NFT is in transmission.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NftSecretPolicy {
+	/// Deny access while the NFT is listed for sale.
+	pub block_listed_for_sale: bool,
+}
+
+impl NftSecretPolicy {
+	pub fn new(block_listed_for_sale: bool) -> Self {
+		Self { block_listed_for_sale }
+	}
+
+	pub fn check(&self, data: &NFTData) -> Result<(), NftSecretAccessDenial> {
+		if data.in_transmission {
+			return Err(NftSecretAccessDenial::InTransmission)
+		}
+		if self.block_listed_for_sale && data.listed_for_sale {
+			return Err(NftSecretAccessDenial::ListedForSale)
+		}
+		Ok(())
+	}
+}
+
+/// Per-account counter that must be incremented with every signed request to prevent replays.
+pub type RequestNonce = u32;
+
+/// Domain separation context that is signed along with every request.
+///
+/// Binds a signature to the parentchain network (genesis hash), the enclave build (MRENCLAVE) and
+/// the worker, such that it is not valid on another network, enclave or worker. Each worker keeps
+/// its own request nonces, hence a request valid on all workers could be replayed to each of them.
+#[derive(Encode, Decode, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RequestContext {
+	pub genesis_hash: H256,
+	pub mr_enclave: MrEnclave,
+	/// Ed25519 signing key of the worker's enclave, that the request is meant for.
+	pub worker: [u8; 32],
+}
+
+impl RequestContext {
+	pub fn new(genesis_hash: H256, mr_enclave: MrEnclave, worker: [u8; 32]) -> Self {
+		Self { genesis_hash, mr_enclave, worker }
+	}
+}
+
 pub trait SignableRequest
 where
 	Self: Encode + Sized + Clone,
 {
-	fn sign(&self, pair: &KeyPair) -> SignedRequest<Self> {
-		let signature = pair.sign(self.encode().as_slice());
-		SignedRequest { request: self.clone(), signer: pair.public(), signature }
+	/// Name of the rpc method the request is meant for. It is part of the signed payload.
+	const METHOD: &'static str;
+
+	/// Sign the request together with the signer's `nonce` and the parentchain block number
+	/// `expiry` after which the request is no longer accepted.
+	fn sign(
+		&self,
+		pair: &KeyPair,
+		context: &RequestContext,
+		nonce: RequestNonce,
+		expiry: BlockNumber,
+	) -> SignedRequest<Self> {
+		let signature = pair.sign(signature_payload(self, context, nonce, expiry).as_slice());
+		SignedRequest { request: self.clone(), nonce, expiry, signer: pair.public(), signature }
 	}
 }
 
+fn signature_payload<T: SignableRequest>(
+	request: &T,
+	context: &RequestContext,
+	nonce: RequestNonce,
+	expiry: BlockNumber,
+) -> Vec<u8> {
+	(context, T::METHOD, request, nonce, expiry).encode()
+}
+
 #[derive(Encode, Decode, Clone, Debug)]
 pub struct SignedRequest<T> {
 	request: T,
+	pub nonce: RequestNonce,
+	pub expiry: BlockNumber,
 	pub signer: sp_core::sr25519::Public,
 	pub signature: Signature,
 }
 
 impl<T: SignableRequest> SignedRequest<T> {
-	pub fn verify_signature(&self) -> bool {
-		self.signature.verify(self.request.encode().as_slice(), &self.signer)
+	pub fn verify_signature(&self, context: &RequestContext) -> bool {
+		self.signature.verify(
+			signature_payload(&self.request, context, self.nonce, self.expiry).as_slice(),
+			&self.signer,
+		)
 	}
 
-	pub fn get_request(&self) -> Option<T> {
-		self.verify_signature().then(|| self.request.clone())
+	pub fn get_request(&self, context: &RequestContext) -> Option<T> {
+		self.verify_signature(context).then(|| self.request.clone())
 	}
 }
 
-#[derive(Encode, Decode, Clone, Copy, Debug)]
+#[derive(Encode, Decode, Clone, Debug)]
 pub struct RetrieveNftSecretRequest {
 	pub nft_id: u32,
+	pub share_index: u8,
+	/// JSON encoded RSA-3072 public key the secret is encrypted to, see `EncryptedNftSecret`.
+	/// If absent, the secret is returned in plaintext.
+	pub recipient_key: Option<Vec<u8>>,
+}
+
+/// NFT secret encrypted to the public key of a requester.
+///
+/// The secret is encrypted with a fresh AES-128-GCM key, with the encoded NFT id and share index
+/// as associated data. The AES key in turn is encrypted to the RSA-3072 key of the requester.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedNftSecret {
+	pub encrypted_key: Vec<u8>,
+	pub nonce: [u8; 12],
+	pub ciphertext: Vec<u8>,
+	pub mac: [u8; 16],
+}
+
+impl SignableRequest for RetrieveNftSecretRequest {
+	const METHOD: &'static str = "nft_retrieveSecret";
+}
+
+/// Condition that has to be met before a stored NFT secret is released, e.g. a reveal date.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnlockCondition {
+	/// Released from this parentchain block number on.
+	BlockNumber(BlockNumber),
+	/// Released from this unix timestamp in milliseconds on, as kept by the `Timestamp` pallet.
+	Timestamp(u64),
 }
 
-impl SignableRequest for RetrieveNftSecretRequest {}
+impl UnlockCondition {
+	/// Whether the condition is met at a parentchain block with the given number and timestamp.
+	pub fn is_met(&self, block_number: BlockNumber, timestamp: u64) -> bool {
+		match self {
+			Self::BlockNumber(unlock_at) => block_number >= *unlock_at,
+			Self::Timestamp(unlock_at) => timestamp >= *unlock_at,
+		}
+	}
+}
+
+/// Role in which a NFT secret share is written.
+///
+/// Owner writes take precedence: the creator cannot overwrite a share written by an owner.
+/// Conversely, an owner can overwrite a share written by the creator, unless the creator locked
+/// it in. A locked in share can be neither overwritten nor removed by an owner, which allows
+/// creators to attach unlockable content that survives sales of the NFT.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NftSecretWriteMode {
+	/// Written by the current owner of the NFT.
+	Owner,
+	/// Written by the creator of the NFT, regardless of who owns it.
+	Creator { locked_in: bool },
+}
+
+impl Default for NftSecretWriteMode {
+	fn default() -> Self {
+		Self::Owner
+	}
+}
 
+impl NftSecretWriteMode {
+	/// Whether a write in this mode may overwrite a share that has been written in `current`.
+	pub fn may_overwrite(&self, current: NftSecretWriteMode) -> bool {
+		match (self, current) {
+			(Self::Owner, Self::Creator { locked_in }) => !locked_in,
+			(Self::Owner, Self::Owner) => true,
+			(Self::Creator { .. }, Self::Owner) => false,
+			(Self::Creator { .. }, Self::Creator { .. }) => true,
+		}
+	}
+
+	/// Whether an owner may remove a share that has been written in this mode.
+	pub fn is_removable_by_owner(&self) -> bool {
+		Self::Owner.may_overwrite(*self)
+	}
+}
+
+/// Returned by the requests that write NFT secrets. A write is pushed to the other registered
+/// workers once, a worker that misses it does not catch up on it later.
+#[derive(Encode, Decode, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NftSecretReplication {
+	/// Registered workers that have not applied the write.
+	pub missed_by: Vec<AccountId>,
+}
+
+/// Stores a secret share of a NFT. Returns a `NftSecretReplication`.
 #[derive(Encode, Decode, Clone, Debug)]
 pub struct StoreNftSecretRequest {
 	pub nft_id: u32,
+	pub share_index: u8,
+	pub secret: Vec<u8>,
+	/// The secret is not retrievable, not even by the owner, before the condition is met.
+	pub unlock: Option<UnlockCondition>,
+	/// The signer has to be the owner or the creator of the NFT, respectively. Retrieval stays
+	/// restricted to the owner either way.
+	pub mode: NftSecretWriteMode,
+}
+
+impl SignableRequest for StoreNftSecretRequest {
+	const METHOD: &'static str = "nft_storeSecret";
+}
+
+/// Variant of `StoreNftSecretRequest` whose secret is encrypted with the shielding key of the
+/// enclave (see `author_getShieldingKey`), such that it can be relayed by untrusted parties.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct StoreShieldedNftSecretRequest {
+	pub nft_id: u32,
+	pub share_index: u8,
+	pub encrypted_secret: Vec<u8>,
+	pub unlock: Option<UnlockCondition>,
+	pub mode: NftSecretWriteMode,
+}
+
+impl SignableRequest for StoreShieldedNftSecretRequest {
+	const METHOD: &'static str = "nft_storeShieldedSecret";
+}
+
+/// Result of a single item of a batch request, or the error message if the item failed.
+pub type NftSecretBatchResult<T> = core::result::Result<T, String>;
+
+/// Stores the secret shares of multiple NFTs at once. Returns a
+/// `NftSecretBatchResult<NftSecretReplication>` per secret, in the order of the request.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct StoreNftSecretsRequest {
+	pub secrets: Vec<StoreNftSecretRequest>,
+}
+
+impl SignableRequest for StoreNftSecretsRequest {
+	const METHOD: &'static str = "nft_storeSecrets";
+}
+
+/// Retrieves the secret shares of multiple NFTs at once. Returns a `NftSecretBatchResult` per
+/// secret, in the order of the request, holding what `nft_retrieveSecret` would return.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct RetrieveNftSecretsRequest {
+	pub secrets: Vec<RetrieveNftSecretRequest>,
+}
+
+impl SignableRequest for RetrieveNftSecretsRequest {
+	const METHOD: &'static str = "nft_retrieveSecrets";
+}
+
+/// Stores a single secret for all NFTs of a series. The signer has to be the creator of the
+/// series. Returns a `NftSecretReplication`.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct StoreNftSeriesSecretRequest {
+	pub series_id: String,
 	pub secret: Vec<u8>,
 }
 
-impl SignableRequest for StoreNftSecretRequest {}
+impl SignableRequest for StoreNftSeriesSecretRequest {
+	const METHOD: &'static str = "nft_storeSeriesSecret";
+}
+
+/// Retrieves the secret of a NFT series. The signer has to own the NFT `nft_id`, whose verified
+/// `NFTData` proves that it is a member of the series.
+///
+/// An `EncryptedNftSecret` of a series secret has the encoded series id as associated data.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct RetrieveNftSeriesSecretRequest {
+	pub series_id: String,
+	pub nft_id: u32,
+	pub recipient_key: Option<Vec<u8>>,
+}
+
+impl SignableRequest for RetrieveNftSeriesSecretRequest {
+	const METHOD: &'static str = "nft_retrieveSeriesSecret";
+}
+
+/// Stores the key of the capsule that the NFT has been converted to. The key is kept apart from
+/// the secrets of the NFT. Returns a `NftSecretReplication`.
+///
+/// Capsule keys can only be stored and retrieved by the owner, while the NFT is converted.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct StoreCapsuleKeyRequest {
+	pub nft_id: u32,
+	pub key: Vec<u8>,
+}
+
+impl SignableRequest for StoreCapsuleKeyRequest {
+	const METHOD: &'static str = "capsule_storeKey";
+}
+
+/// Retrieves the key of a capsule, see `StoreCapsuleKeyRequest`.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct RetrieveCapsuleKeyRequest {
+	pub nft_id: u32,
+	pub recipient_key: Option<Vec<u8>>,
+}
+
+impl RetrieveCapsuleKeyRequest {
+	/// Associated data of an `EncryptedNftSecret` holding the capsule key, distinct from the one
+	/// of the NFT secrets.
+	pub fn aad(nft_id: u32) -> Vec<u8> {
+		(b"capsule", nft_id).encode()
+	}
+}
+
+impl SignableRequest for RetrieveCapsuleKeyRequest {
+	const METHOD: &'static str = "capsule_retrieveKey";
+}
+
+/// Lists the indices of the stored secret shares of a NFT.
+#[derive(Encode, Decode, Clone, Copy, Debug)]
+pub struct ListNftSecretSharesRequest {
+	pub nft_id: u32,
+}
+
+impl SignableRequest for ListNftSecretSharesRequest {
+	const METHOD: &'static str = "nft_listSecretShares";
+}
+
+/// Removes all secret shares of a NFT. Returns a `NftSecretReplication`.
+#[derive(Encode, Decode, Clone, Copy, Debug)]
+pub struct RemoveNftSecretRequest {
+	pub nft_id: u32,
+}
+
+impl SignableRequest for RemoveNftSecretRequest {
+	const METHOD: &'static str = "nft_removeSecret";
+}
+
+/// Grants an account read access to the secrets of a NFT. Returns a `NftSecretReplication`.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct GrantNftAccessRequest {
+	pub nft_id: u32,
+	pub grantee: AccountId,
+}
+
+impl SignableRequest for GrantNftAccessRequest {
+	const METHOD: &'static str = "nft_grantAccess";
+}
+
+/// Revokes the read access of an account to the secrets of a NFT. Returns a
+/// `NftSecretReplication`.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct RevokeNftAccessRequest {
+	pub nft_id: u32,
+	pub grantee: AccountId,
+}
+
+impl SignableRequest for RevokeNftAccessRequest {
+	const METHOD: &'static str = "nft_revokeAccess";
+}
+
+/// Prefix of the parentchain remarks that publish a `NftSecretCommitment`.
+pub const NFT_SECRET_COMMITMENT_PREFIX: &[u8] = b"ternoa:nft-secret-commitment";
+
+/// Commitment to a stored NFT secret share, published on the parentchain by the enclave that
+/// accepted the share.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct NftSecretCommitment {
+	pub nft_id: u32,
+	pub share_index: u8,
+	/// `blake2_256(secret ++ salt)`
+	pub commitment: H256,
+}
+
+impl NftSecretCommitment {
+	pub fn new(nft_id: u32, share_index: u8, secret: &[u8], salt: &[u8]) -> Self {
+		let commitment = blake2_256(&[secret, salt].concat()).into();
+		Self { nft_id, share_index, commitment }
+	}
+
+	/// Remark of the enclave account that publishes the commitment.
+	pub fn remark(&self) -> Vec<u8> {
+		(NFT_SECRET_COMMITMENT_PREFIX, self).encode()
+	}
+}
+
+/// Opening of a `NftSecretCommitment`, which proves that the commitment binds the secret.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct NftSecretCommitmentProof {
+	pub commitment: NftSecretCommitment,
+	pub salt: [u8; 16],
+}
+
+impl NftSecretCommitmentProof {
+	pub fn verify(&self, secret: &[u8]) -> bool {
+		let commitment = &self.commitment;
+		NftSecretCommitment::new(commitment.nft_id, commitment.share_index, secret, &self.salt)
+			== *commitment
+	}
+}
+
+/// Returns the `NftSecretCommitmentProof` of a secret share to the owner of the NFT.
+#[derive(Encode, Decode, Clone, Copy, Debug)]
+pub struct GetNftSecretCommitmentRequest {
+	pub nft_id: u32,
+	pub share_index: u8,
+}
+
+impl SignableRequest for GetNftSecretCommitmentRequest {
+	const METHOD: &'static str = "nft_getSecretCommitment";
+}
+
+/// Public metadata of the secrets of a NFT, as returned by the unauthenticated
+/// `nft_getSecretInfo`. Reveals nothing about the secrets themselves.
+#[derive(Encode, Decode, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NftSecretInfo {
+	pub exists: bool,
+	/// Total length of the stored secret shares in bytes, as far as their writes have been
+	/// recorded.
+	pub byte_length: u32,
+	pub share_count: u8,
+	/// Parentchain block number of the latest write, `None` if it predates the recording of
+	/// writes.
+	pub updated_at: Option<BlockNumber>,
+	/// Account that made the latest write.
+	pub writer: Option<AccountId>,
+}
+
+/// Lists those of the candidate NFTs that are owned by the signer and have secrets stored.
+/// Returns the ids of the NFTs along with their `NftSecretInfo`, sorted by id.
+///
+/// The ownership of every candidate with secrets is proven against the parentchain state, hence
+/// the candidates are limited to a batch, e.g. the NFTs the signer owns on the parentchain.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct ListMyNftSecretsRequest {
+	pub nft_ids: Vec<u32>,
+}
+
+impl SignableRequest for ListMyNftSecretsRequest {
+	const METHOD: &'static str = "nft_listMySecrets";
+}
+
+/// Public key of the key pair of a NFT, a NIST P-256 point with little-endian coordinates as
+/// used by the SGX crypto library.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NftPublicKey {
+	pub gx: [u8; 32],
+	pub gy: [u8; 32],
+}
+
+/// Returns the `NftPublicKey` of a NFT to its owner. The key pair is derived within the enclave
+/// and its private key never leaves it, so the same key is returned on every request.
+#[derive(Encode, Decode, Clone, Copy, Debug)]
+pub struct GenerateNftKeyRequest {
+	pub nft_id: u32,
+}
+
+impl SignableRequest for GenerateNftKeyRequest {
+	const METHOD: &'static str = "nft_generateKey";
+}
+
+/// ECDSA signature over the SHA-256 digest of a message, made with the key pair of a NFT. The
+/// coordinates are little-endian, like the ones of `NftPublicKey`.
+#[derive(Encode, Decode, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NftSignature {
+	pub x: [u8; 32],
+	pub y: [u8; 32],
+}
+
+/// Signs a message with the key pair of a NFT on behalf of its owner, returns a `NftSignature`.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct SignWithNftKeyRequest {
+	pub nft_id: u32,
+	pub message: Vec<u8>,
+}
+
+impl SignableRequest for SignWithNftKeyRequest {
+	const METHOD: &'static str = "nft_sign";
+}
+
+/// Domain separation of the `NftCiphertext` keys.
+const NFT_CIPHERTEXT_KEY_CONTEXT: &[u8] = b"ternoa-nft-ciphertext";
+
+/// Message encrypted to the `NftPublicKey` of a NFT.
+///
+/// The message is encrypted with AES-128-GCM, whose key is derived from the ECDH shared secret of
+/// a fresh ephemeral key pair and the key of the NFT. As the ephemeral key pair must not be
+/// reused, the AES key is used once and the nonce is fixed.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct NftCiphertext {
+	pub ephemeral_key: NftPublicKey,
+	pub ciphertext: Vec<u8>,
+	pub mac: [u8; 16],
+}
+
+impl NftCiphertext {
+	pub const NONCE: [u8; 12] = [0; 12];
+
+	/// AES-128-GCM key of the ciphertext, derived from the x-coordinate of the shared secret.
+	pub fn key(shared_secret: &[u8; 32], ephemeral_key: &NftPublicKey) -> [u8; 16] {
+		blake2_128(&(NFT_CIPHERTEXT_KEY_CONTEXT, shared_secret, ephemeral_key).encode())
+	}
+
+	/// Associated data of the ciphertext, which binds it to the NFT.
+	pub fn aad(nft_id: u32) -> Vec<u8> {
+		(b"nft-ciphertext", nft_id).encode()
+	}
+}
+
+/// Decrypts a `NftCiphertext` with the key pair of a NFT on behalf of its owner.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct DecryptWithNftKeyRequest {
+	pub nft_id: u32,
+	pub ciphertext: NftCiphertext,
+	pub recipient_key: Option<Vec<u8>>,
+}
+
+impl DecryptWithNftKeyRequest {
+	/// Associated data of an `EncryptedNftSecret` holding the decrypted message.
+	pub fn aad(nft_id: u32) -> Vec<u8> {
+		(b"nft-decrypted", nft_id).encode()
+	}
+}
+
+impl SignableRequest for DecryptWithNftKeyRequest {
+	const METHOD: &'static str = "nft_decrypt";
+}
+
+/// Registers the JSON encoded RSA-3072 public key of the signer, that the secrets of a NFT are
+/// re-encrypted to once the signer acquires the NFT, see `NftSecretHandover`. Returns a
+/// `NftSecretReplication`.
+#[derive(Encode, Decode, Clone, Debug)]
+pub struct RegisterRecipientKeyRequest {
+	pub key: Vec<u8>,
+}
+
+impl SignableRequest for RegisterRecipientKeyRequest {
+	const METHOD: &'static str = "nft_registerRecipientKey";
+}
+
+/// Secret shares of a NFT that changed hands, encrypted to the registered recipient key of the
+/// new owner by the block importer of the enclave.
+///
+/// Every share is an `EncryptedNftSecret` with the same associated data as a retrieved share.
+#[derive(Encode, Decode, Clone, Debug, PartialEq, Eq)]
+pub struct NftSecretHandover {
+	pub owner: AccountId,
+	/// Parentchain block number at which the transfer has been observed.
+	pub block_number: BlockNumber,
+	pub shares: Vec<(u8, EncryptedNftSecret)>,
+}
+
+/// Returns the `NftSecretHandover` of a NFT to its owner.
+#[derive(Encode, Decode, Clone, Copy, Debug)]
+pub struct RetrieveNftSecretHandoverRequest {
+	pub nft_id: u32,
+}
+
+impl SignableRequest for RetrieveNftSecretHandoverRequest {
+	const METHOD: &'static str = "nft_retrieveSecretHandover";
+}
 
 #[cfg(test)]
 mod tests {
@@ -218,4 +763,155 @@ mod tests {
 		let call = OpaqueCall::from_tuple(&call_tuple);
 		assert_eq!(call.encode(), call_tuple.encode())
 	}
-}
\ No newline at end of file
+
+	fn test_context() -> RequestContext {
+		RequestContext::new(H256::from([1; 32]), [2; 32], [5; 32])
+	}
+
+	fn nft_data(listed_for_sale: bool, in_transmission: bool) -> NFTData {
+		NFTData {
+			owner: [1; 32],
+			creator: [1; 32],
+			ipfs_reference: "".into(),
+			series_id: "".into(),
+			listed_for_sale,
+			in_transmission,
+			converted_to_capsule: false,
+		}
+	}
+
+	#[test]
+	fn nft_secret_policy_always_denies_access_in_transmission() {
+		let default_policy = NftSecretPolicy::default();
+		let strict_policy = NftSecretPolicy::new(true);
+
+		assert_eq!(
+			default_policy.check(&nft_data(false, true)),
+			Err(NftSecretAccessDenial::InTransmission)
+		);
+		assert_eq!(
+			strict_policy.check(&nft_data(true, true)),
+			Err(NftSecretAccessDenial::InTransmission)
+		);
+	}
+
+	#[test]
+	fn nft_secret_policy_denies_access_when_listed_for_sale_if_configured() {
+		let default_policy = NftSecretPolicy::default();
+		let strict_policy = NftSecretPolicy::new(true);
+
+		assert_eq!(default_policy.check(&nft_data(true, false)), Ok(()));
+		assert_eq!(
+			strict_policy.check(&nft_data(true, false)),
+			Err(NftSecretAccessDenial::ListedForSale)
+		);
+		assert_eq!(strict_policy.check(&nft_data(false, false)), Ok(()));
+	}
+
+	#[test]
+	fn nft_secret_commitment_proof_only_verifies_committed_secret() {
+		let salt = [7u8; 16];
+		let proof = NftSecretCommitmentProof {
+			commitment: NftSecretCommitment::new(1, 0, b"secret", &salt),
+			salt,
+		};
+		let mut other_salt = proof.clone();
+		other_salt.salt = [8u8; 16];
+
+		assert!(proof.verify(b"secret"));
+		assert!(!proof.verify(b"other secret"));
+		assert!(!other_salt.verify(b"secret"));
+	}
+
+	#[test]
+	fn unlock_condition_is_met_from_its_block_or_timestamp_on() {
+		let at_block = UnlockCondition::BlockNumber(10);
+		let at_time = UnlockCondition::Timestamp(1_000);
+
+		assert!(!at_block.is_met(9, u64::MAX));
+		assert!(at_block.is_met(10, 0));
+		assert!(!at_time.is_met(BlockNumber::MAX, 999));
+		assert!(at_time.is_met(0, 1_000));
+	}
+
+	#[test]
+	fn owner_writes_take_precedence_unless_creator_locked_in() {
+		let owner = NftSecretWriteMode::Owner;
+		let creator = NftSecretWriteMode::Creator { locked_in: false };
+		let locked_in = NftSecretWriteMode::Creator { locked_in: true };
+
+		assert!(owner.may_overwrite(creator));
+		assert!(!owner.may_overwrite(locked_in));
+		assert!(!creator.may_overwrite(owner));
+		assert!(creator.may_overwrite(locked_in));
+		assert!(!locked_in.is_removable_by_owner());
+	}
+
+	#[test]
+	fn signed_request_verifies_correctly() {
+		let pair = sp_keyring::Sr25519Keyring::Alice.pair();
+		let req = RetrieveNftSecretRequest { nft_id: 1, share_index: 0, recipient_key: None };
+		let signed_req = req.sign(&pair, &test_context(), 3, 100);
+
+		assert!(signed_req.verify_signature(&test_context()));
+	}
+
+	#[test]
+	fn signed_request_with_tampered_nonce_or_expiry_fails_verification() {
+		let pair = sp_keyring::Sr25519Keyring::Alice.pair();
+		let req = RetrieveNftSecretRequest { nft_id: 1, share_index: 0, recipient_key: None };
+		let signed_req = req.sign(&pair, &test_context(), 3, 100);
+
+		let mut tampered_nonce = signed_req.clone();
+		tampered_nonce.nonce = 4;
+		let mut tampered_expiry = signed_req;
+		tampered_expiry.expiry = 200;
+
+		assert!(!tampered_nonce.verify_signature(&test_context()));
+		assert!(!tampered_expiry.verify_signature(&test_context()));
+	}
+
+	#[test]
+	fn signed_request_fails_verification_in_other_context() {
+		let pair = sp_keyring::Sr25519Keyring::Alice.pair();
+		let req = RetrieveNftSecretRequest { nft_id: 1, share_index: 0, recipient_key: None };
+		let signed_req = req.sign(&pair, &test_context(), 3, 100);
+
+		let other_network = RequestContext::new(H256::from([3; 32]), [2; 32], [5; 32]);
+		let other_enclave = RequestContext::new(H256::from([1; 32]), [4; 32], [5; 32]);
+		let other_worker = RequestContext::new(H256::from([1; 32]), [2; 32], [6; 32]);
+
+		assert!(!signed_req.verify_signature(&other_network));
+		assert!(!signed_req.verify_signature(&other_enclave));
+		assert!(!signed_req.verify_signature(&other_worker));
+	}
+
+	#[test]
+	fn signed_request_fails_verification_for_other_method() {
+		let pair = sp_keyring::Sr25519Keyring::Alice.pair();
+		let signed_req =
+			ListNftSecretSharesRequest { nft_id: 1 }.sign(&pair, &test_context(), 3, 100);
+
+		// Both requests have the same encoding, only the method name differs.
+		let signed_remove_req =
+			SignedRequest::<RemoveNftSecretRequest>::decode(&mut signed_req.encode().as_slice())
+				.unwrap();
+
+		assert!(!signed_remove_req.verify_signature(&test_context()));
+	}
+
+	#[test]
+	fn signed_grant_access_request_cannot_be_used_as_revoke() {
+		let pair = sp_keyring::Sr25519Keyring::Alice.pair();
+		let grantee = sp_keyring::Sr25519Keyring::Bob.to_account_id();
+		let signed_req =
+			GrantNftAccessRequest { nft_id: 1, grantee }.sign(&pair, &test_context(), 3, 100);
+
+		let signed_revoke_req =
+			SignedRequest::<RevokeNftAccessRequest>::decode(&mut signed_req.encode().as_slice())
+				.unwrap();
+
+		assert!(signed_req.verify_signature(&test_context()));
+		assert!(!signed_revoke_req.verify_signature(&test_context()));
+	}
+}
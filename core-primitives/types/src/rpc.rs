@@ -5,6 +5,14 @@ use alloc::{borrow::ToOwned, string::String, vec::Vec};
 use codec::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
+/// JSON-RPC error code of a NFT secret request that is denied because the NFT is in transmission.
+pub const NFT_IN_TRANSMISSION_ERROR_CODE: i64 = -32010;
+/// JSON-RPC error code of a NFT secret request that is denied because the NFT is listed for sale.
+pub const NFT_LISTED_FOR_SALE_ERROR_CODE: i64 = -32011;
+/// JSON-RPC error code of a NFT secret request that is denied because the worker has not yet
+/// seen its latest version anchor on the parentchain.
+pub const NFT_SECRETS_UNAVAILABLE_ERROR_CODE: i64 = -32012;
+
 #[derive(Encode, Decode, Debug)]
 pub struct RpcReturnValue {
 	pub value: Vec<u8>,
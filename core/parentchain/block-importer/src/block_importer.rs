@@ -20,10 +20,11 @@
 use crate::{
 	beefy_merkle_tree::{merkle_root, Keccak256},
 	error::Result,
-	ImportParentchainBlocks,
+	AnchorSealedVersions, ImportParentchainBlocks, ObserveNftOwnership,
 };
 use itc_parentchain_light_client::{
-	concurrent_access::ValidatorAccess, BlockNumberOps, LightClientState, Validator,
+	check_block_body, concurrent_access::ValidatorAccess, error::Error as LightClientError,
+	BlockNumberOps, LightClientState, Validator,
 };
 use itp_extrinsics_factory::CreateExtrinsics;
 use itp_ocall_api::{EnclaveAttestationOCallApi, EnclaveOnChainOCallApi};
@@ -34,54 +35,87 @@ use sp_runtime::{
 	generic::SignedBlock as SignedBlockG,
 	traits::{Block as BlockT, NumberFor},
 };
-use std::{marker::PhantomData, sync::Arc, vec::Vec};
+use std::{format, marker::PhantomData, sync::Arc, vec::Vec};
 
 /// Parentchain block import implementation.
-pub struct ParentchainBlockImporter<PB, ValidatorAccessor, OCallApi, ExtrinsicsFactory>
-where
+pub struct ParentchainBlockImporter<
+	PB,
+	ValidatorAccessor,
+	OCallApi,
+	ExtrinsicsFactory,
+	NftOwnershipObserver,
+	VersionAnchor,
+> where
 	PB: BlockT<Hash = H256>,
 	NumberFor<PB>: BlockNumberOps,
 	ValidatorAccessor: ValidatorAccess<PB>,
 	OCallApi: EnclaveOnChainOCallApi + EnclaveAttestationOCallApi,
 	ExtrinsicsFactory: CreateExtrinsics,
+	NftOwnershipObserver: ObserveNftOwnership<PB>,
+	VersionAnchor: AnchorSealedVersions<PB>,
 {
 	validator_accessor: Arc<ValidatorAccessor>,
 	ocall_api: Arc<OCallApi>,
 	extrinsics_factory: Arc<ExtrinsicsFactory>,
+	nft_ownership_observer: Arc<NftOwnershipObserver>,
+	version_anchor: Arc<VersionAnchor>,
 	_phantom: PhantomData<PB>,
 }
 
-impl<PB, ValidatorAccessor, OCallApi, ExtrinsicsFactory>
-	ParentchainBlockImporter<PB, ValidatorAccessor, OCallApi, ExtrinsicsFactory>
+impl<PB, ValidatorAccessor, OCallApi, ExtrinsicsFactory, NftOwnershipObserver, VersionAnchor>
+	ParentchainBlockImporter<
+		PB,
+		ValidatorAccessor,
+		OCallApi,
+		ExtrinsicsFactory,
+		NftOwnershipObserver,
+		VersionAnchor,
+	>
 where
 	PB: BlockT<Hash = H256, Header = Header>,
 	NumberFor<PB>: BlockNumberOps,
 	ValidatorAccessor: ValidatorAccess<PB>,
 	OCallApi: EnclaveOnChainOCallApi + EnclaveAttestationOCallApi,
 	ExtrinsicsFactory: CreateExtrinsics,
+	NftOwnershipObserver: ObserveNftOwnership<PB>,
+	VersionAnchor: AnchorSealedVersions<PB>,
 {
 	pub fn new(
 		validator_accessor: Arc<ValidatorAccessor>,
 		ocall_api: Arc<OCallApi>,
 		extrinsics_factory: Arc<ExtrinsicsFactory>,
+		nft_ownership_observer: Arc<NftOwnershipObserver>,
+		version_anchor: Arc<VersionAnchor>,
 	) -> Self {
 		ParentchainBlockImporter {
 			validator_accessor,
 			ocall_api,
 			extrinsics_factory,
+			nft_ownership_observer,
+			version_anchor,
 			_phantom: Default::default(),
 		}
 	}
 }
 
-impl<PB, ValidatorAccessor, OCallApi, ExtrinsicsFactory> ImportParentchainBlocks
-	for ParentchainBlockImporter<PB, ValidatorAccessor, OCallApi, ExtrinsicsFactory>
+impl<PB, ValidatorAccessor, OCallApi, ExtrinsicsFactory, NftOwnershipObserver, VersionAnchor>
+	ImportParentchainBlocks
+	for ParentchainBlockImporter<
+		PB,
+		ValidatorAccessor,
+		OCallApi,
+		ExtrinsicsFactory,
+		NftOwnershipObserver,
+		VersionAnchor,
+	>
 where
 	PB: BlockT<Hash = H256, Header = Header>,
 	NumberFor<PB>: BlockNumberOps,
 	ValidatorAccessor: ValidatorAccess<PB>,
 	OCallApi: EnclaveOnChainOCallApi + EnclaveAttestationOCallApi,
 	ExtrinsicsFactory: CreateExtrinsics,
+	NftOwnershipObserver: ObserveNftOwnership<PB>,
+	VersionAnchor: AnchorSealedVersions<PB>,
 {
 	type SignedBlockType = SignedBlockG<PB>;
 
@@ -90,6 +124,7 @@ where
 		blocks_to_import: Vec<Self::SignedBlockType>,
 	) -> Result<()> {
 		let mut calls = Vec::<OpaqueCall>::new();
+		let mut imported_blocks = Vec::with_capacity(blocks_to_import.len());
 
 		debug!("Import blocks to light-client!");
 		for signed_block in blocks_to_import.into_iter() {
@@ -99,15 +134,44 @@ where
 			// Check if there are any extrinsics in the to-be-imported block that we sent and cached in the light-client before.
 			// If so, remove them now from the cache.
 			if let Err(e) = self.validator_accessor.execute_mut_on_validator(|v| {
+				check_block_body(&block)?;
 				v.check_xt_inclusion(v.num_relays(), &block)?;
 
-				v.submit_simple_header(v.num_relays(), block.header().clone(), justifications)
+				v.submit_simple_header(v.num_relays(), block.header().clone(), justifications)?;
+
+				// An anchor newer than the sealed files reveals that they have been rolled back,
+				// along with the light client that imported the block before. The anchors are
+				// only read from a validated header and body, and the rollback fails the sealing
+				// of the light client, such that the block is imported again after a restart.
+				self.version_anchor.check_version_anchors(&block).map_err(|e| {
+					error!("Failed to check the version anchors of the block: {:?}", e);
+					LightClientError::Other(format!("{:?}", e).into())
+				})
 			}) {
 				error!("[Validator] Header submission failed: {:?}", e);
 				return Err(e.into())
 			}
 
 			calls.push(create_processed_parentchain_block_call(block.hash(), Vec::new()));
+			imported_blocks.push(block);
+		}
+
+		// The imported blocks may have burned or transferred NFTs that secrets are stored for.
+		// A failure here must not prevent the block import.
+		if let Some(latest_block) = imported_blocks.last() {
+			if let Err(e) = self
+				.nft_ownership_observer
+				.observe_nft_ownership(&imported_blocks, latest_block.header())
+			{
+				error!("Failed to observe the ownership of NFTs: {:?}", e);
+			}
+		}
+
+		// Anchor the files sealed by this import and by the requests served since the last one.
+		match self.version_anchor.publish_version_anchor() {
+			Ok(Some(call)) => calls.push(call),
+			Ok(None) => {},
+			Err(e) => error!("Failed to publish the version anchor: {:?}", e),
 		}
 
 		// Create extrinsics for all `unshielding` and `block processed` calls we've gathered.
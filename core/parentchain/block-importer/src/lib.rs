@@ -40,6 +40,7 @@ pub mod block_importer_mock;
 pub use block_importer::*;
 
 use error::Result;
+use itp_types::{Header, OpaqueCall};
 use std::vec::Vec;
 
 /// Block import from the parentchain.
@@ -54,3 +55,22 @@ pub trait ImportParentchainBlocks {
 	fn import_parentchain_blocks(&self, blocks_to_import: Vec<Self::SignedBlockType>)
 		-> Result<()>;
 }
+
+/// Keeps the NFT secrets in line with the ownership of the NFTs on the parentchain.
+pub trait ObserveNftOwnership<PB> {
+	/// Removes the secrets of the NFTs that the imported `blocks` burned, and the access grants
+	/// of the NFTs that they transferred, whose secrets are handed over to the new owner. `header`
+	/// is the header of the latest imported block.
+	fn observe_nft_ownership(&self, blocks: &[PB], header: &Header) -> Result<()>;
+}
+
+/// Anchors the versions of the sealed files of the enclave on the parentchain, such that a
+/// rollback of all of them together is detected when the anchoring blocks are imported again.
+pub trait AnchorSealedVersions<PB> {
+	/// Fails if the block carries an anchor that has been published from newer sealed files.
+	fn check_version_anchors(&self, block: &PB) -> Result<()>;
+
+	/// The call publishing the anchor of the sealed files, `None` if no file has been sealed
+	/// since the anchor was last published.
+	fn publish_version_anchor(&self) -> Result<Option<OpaqueCall>>;
+}
@@ -49,6 +49,8 @@ pub enum Error {
 	InvalidFinalityProof(#[from] JustificationError),
 	#[error("Header ancestry mismatch")]
 	HeaderAncestryMismatch,
+	#[error("Block body does not match the extrinsics root of its header")]
+	ExtrinsicsRootMismatch,
 	#[error("Poisoned validator lock")]
 	PoisonedLock,
 	#[error(transparent)]
@@ -19,12 +19,12 @@ use crate::{error::Result, Error, LightClientState, LightValidation, NumberFor,
 use codec::{Decode, Encode};
 use derive_more::Display;
 use itp_settings::files::LIGHT_CLIENT_DB;
-use itp_sgx_io::{seal, unseal, SealedIO};
+use itp_sgx_io::{seal_versioned, unseal_versioned, SealedIO};
 use itp_storage::StorageProof;
 use log::*;
 use sp_finality_grandpa::VersionedAuthorityList;
 use sp_runtime::traits::{Block, Header};
-use std::{fs, sgxfs::SgxFile};
+use std::io;
 
 #[derive(Copy, Clone, Debug, Display)]
 pub struct LightClientSeal<B> {
@@ -36,16 +36,16 @@ impl<B: Block> SealedIO for LightClientSeal<B> {
 	type Unsealed = LightValidation<B>;
 
 	fn unseal() -> Result<Self::Unsealed> {
-		Ok(unseal(LIGHT_CLIENT_DB).map(|b| Decode::decode(&mut b.as_slice()))??)
+		let bytes = unseal_versioned(LIGHT_CLIENT_DB)?
+			.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "light client db not found"))?;
+		Ok(Decode::decode(&mut bytes.as_slice())?)
 	}
 
+	// No backup of the previous state is kept, as it could not be unsealed anymore: a sealed file
+	// that is older than its latest version is rejected as a rollback.
 	fn seal(unsealed: Self::Unsealed) -> Result<()> {
-		debug!("backup light client state");
-		if fs::copy(LIGHT_CLIENT_DB, format!("{}.1", LIGHT_CLIENT_DB)).is_err() {
-			warn!("could not backup previous light client state");
-		};
 		debug!("Seal light client State. Current state: {:?}", unsealed);
-		Ok(unsealed.using_encoded(|bytes| seal(bytes, LIGHT_CLIENT_DB))?)
+		Ok(unsealed.using_encoded(|bytes| seal_versioned(bytes, LIGHT_CLIENT_DB))?)
 	}
 }
 
@@ -57,12 +57,14 @@ pub fn read_or_init_validator<B: Block>(
 where
 	NumberFor<B>: finality_grandpa::BlockNumberOps,
 {
-	if SgxFile::open(LIGHT_CLIENT_DB).is_err() {
-		info!("[Enclave] ChainRelay DB not found, creating new! {}", LIGHT_CLIENT_DB);
-		return init_validator::<B>(header, auth, proof)
-	}
-
-	let validator = LightClientSeal::<B>::unseal()?;
+	// A rolled back or deleted light client db must not silently be replaced by a new one.
+	let validator: LightValidation<B> = match unseal_versioned(LIGHT_CLIENT_DB)? {
+		Some(bytes) => Decode::decode(&mut bytes.as_slice())?,
+		None => {
+			info!("[Enclave] ChainRelay DB not found, creating new! {}", LIGHT_CLIENT_DB);
+			return init_validator::<B>(header, auth, proof)
+		},
+	};
 
 	let genesis = validator.genesis_hash(validator.num_relays()).unwrap();
 	if genesis == header.hash() {
@@ -47,6 +47,7 @@ use sp_runtime::{
 	traits::{Block as BlockT, Hash as HashT, Header as HeaderT},
 	Justification, Justifications, OpaqueExtrinsic,
 };
+use sp_trie::{Layout, TrieConfiguration};
 use state::RelayState;
 use std::{collections::BTreeMap, fmt, vec::Vec};
 
@@ -466,6 +467,17 @@ pub fn pending_change<Block: BlockT>(digest: &Digest) -> Option<ScheduledChange<
 	grandpa_log::<Block>(digest).and_then(|log| log.try_into_change())
 }
 
+/// Checks that the extrinsics of the block are the ones its header commits to. Only the header
+/// is validated by the light client, the body is provided by the untrusted host.
+pub fn check_block_body<Block: BlockT>(block: &Block) -> Result<(), Error> {
+	let extrinsics = block.extrinsics().iter().map(Encode::encode);
+	let root = Layout::<HashingFor<Block>>::ordered_trie_root(extrinsics);
+	if &root != block.header().extrinsics_root() {
+		return Err(Error::ExtrinsicsRootMismatch)
+	}
+	Ok(())
+}
+
 impl<B: BlockT> fmt::Debug for LightValidation<B> {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		write!(
@@ -18,10 +18,14 @@
 //! Interface for direct access to a workers rpc.
 
 use crate::ws_client::WsClient;
-use codec::Decode;
-use itp_types::{DirectRequestStatus, RpcRequest, RpcResponse, RpcReturnValue};
+use codec::{Decode, Encode};
+use itp_types::{
+	DirectRequestStatus, NftSecretInfo, RequestContext, RequestNonce, RpcRequest, RpcResponse,
+	RpcReturnValue,
+};
 use log::*;
 use sgx_crypto_helper::rsa3072::Rsa3072PubKey;
+use sp_core::sr25519::Public;
 use std::{
 	sync::mpsc::{channel, Sender as MpscSender},
 	thread,
@@ -42,6 +46,9 @@ pub trait DirectApi {
 	fn get_rsa_pubkey(&self) -> Result<Rsa3072PubKey>;
 	fn get_mu_ra_url(&self) -> Result<String>;
 	fn get_untrusted_worker_url(&self) -> Result<String>;
+	fn get_request_nonce(&self, account: &Public) -> Result<RequestNonce>;
+	fn get_request_context(&self) -> Result<RequestContext>;
+	fn get_nft_secret_info(&self, nft_id: u32) -> Result<NftSecretInfo>;
 }
 
 impl DirectClient {
@@ -110,14 +117,56 @@ impl DirectApi for DirectClient {
 		info!("[+] Got untrusted websocket url of worker: {}", untrusted_url);
 		Ok(untrusted_url)
 	}
+
+	fn get_request_nonce(&self, account: &Public) -> Result<RequestNonce> {
+		let jsonrpc_call: String = RpcRequest::compose_jsonrpc_call(
+			"author_getRequestNonce".to_string(),
+			account.encode(),
+		);
+
+		// Send json rpc call to ws server.
+		let response_str = Self::get(self, &jsonrpc_call)?;
+
+		let nonce: RequestNonce = decode_from_rpc_response(&response_str)?;
+
+		info!("[+] Got request nonce of {}: {}", account, nonce);
+		Ok(nonce)
+	}
+
+	fn get_request_context(&self) -> Result<RequestContext> {
+		let jsonrpc_call: String = RpcRequest::compose_jsonrpc_call(
+			"author_getRequestContext".to_string(),
+			Vec::<u8>::new(),
+		);
+
+		// Send json rpc call to ws server.
+		let response_str = Self::get(self, &jsonrpc_call)?;
+
+		let context: RequestContext = decode_from_rpc_response(&response_str)?;
+
+		info!("[+] Got request context of enclave: {:?}", context);
+		Ok(context)
+	}
+
+	fn get_nft_secret_info(&self, nft_id: u32) -> Result<NftSecretInfo> {
+		let jsonrpc_call: String =
+			RpcRequest::compose_jsonrpc_call("nft_getSecretInfo".to_string(), nft_id.encode());
+
+		// Send json rpc call to ws server.
+		let response_str = Self::get(self, &jsonrpc_call)?;
+
+		let secret_info: NftSecretInfo = decode_from_rpc_response(&response_str)?;
+
+		info!("[+] Got secret info of NFT {}: {:?}", nft_id, secret_info);
+		Ok(secret_info)
+	}
 }
 
-fn decode_from_rpc_response(json_rpc_response: &str) -> Result<String> {
+fn decode_from_rpc_response<T: Decode>(json_rpc_response: &str) -> Result<T> {
 	let rpc_response: RpcResponse<Vec<u8>> = serde_json::from_str(json_rpc_response)?;
 	let rpc_return_value = RpcReturnValue::decode(&mut rpc_response.result.as_slice())?;
-	let response_message = String::decode(&mut rpc_return_value.value.as_slice())?;
 	match rpc_return_value.status {
-		DirectRequestStatus::Ok => Ok(response_message),
-		_ => Err(Error::Status(response_message)),
+		DirectRequestStatus::Ok => Ok(T::decode(&mut rpc_return_value.value.as_slice())?),
+		_ => Err(Error::Status(String::decode(&mut rpc_return_value.value.as_slice())?)),
 	}
 }
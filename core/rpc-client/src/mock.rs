@@ -18,7 +18,9 @@
 //! Interface for direct access to a workers rpc.
 
 use crate::{direct_client::DirectApi, error::Result};
+use itp_types::{NftSecretInfo, RequestContext, RequestNonce};
 use sgx_crypto_helper::rsa3072::Rsa3072PubKey;
+use sp_core::sr25519::Public;
 use std::{sync::mpsc::Sender as MpscSender, thread::JoinHandle};
 
 #[derive(Clone, Default)]
@@ -26,11 +28,21 @@ pub struct DirectClientMock {
 	rsa_pubkey: Rsa3072PubKey,
 	mu_ra_url: String,
 	untrusted_worker_url: String,
+	request_nonce: RequestNonce,
+	request_context: RequestContext,
+	nft_secret_info: NftSecretInfo,
 }
 
 impl DirectClientMock {
 	pub fn new(rsa_pubkey: Rsa3072PubKey, mu_ra_url: String, untrusted_worker_url: String) -> Self {
-		Self { rsa_pubkey, mu_ra_url, untrusted_worker_url }
+		Self {
+			rsa_pubkey,
+			mu_ra_url,
+			untrusted_worker_url,
+			request_nonce: Default::default(),
+			request_context: Default::default(),
+			nft_secret_info: Default::default(),
+		}
 	}
 
 	pub fn with_rsa_pubkey(mut self, key: Rsa3072PubKey) -> Self {
@@ -47,6 +59,21 @@ impl DirectClientMock {
 		self.untrusted_worker_url = url.to_string();
 		self
 	}
+
+	pub fn with_request_nonce(mut self, nonce: RequestNonce) -> Self {
+		self.request_nonce = nonce;
+		self
+	}
+
+	pub fn with_request_context(mut self, context: RequestContext) -> Self {
+		self.request_context = context;
+		self
+	}
+
+	pub fn with_nft_secret_info(mut self, info: NftSecretInfo) -> Self {
+		self.nft_secret_info = info;
+		self
+	}
 }
 
 impl DirectApi for DirectClientMock {
@@ -69,4 +96,16 @@ impl DirectApi for DirectClientMock {
 	fn get_untrusted_worker_url(&self) -> Result<String> {
 		Ok(self.untrusted_worker_url.clone())
 	}
+
+	fn get_request_nonce(&self, _account: &Public) -> Result<RequestNonce> {
+		Ok(self.request_nonce)
+	}
+
+	fn get_request_context(&self) -> Result<RequestContext> {
+		Ok(self.request_context)
+	}
+
+	fn get_nft_secret_info(&self, _nft_id: u32) -> Result<NftSecretInfo> {
+		Ok(self.nft_secret_info.clone())
+	}
 }
@@ -34,6 +34,7 @@ pub enum Error {
 	ParentchainBlockImportDispatch(itc_parentchain::block_import_dispatcher::error::Error),
 	PrimitivesAccess(itp_primitives_cache::error::Error),
 	MutexAccess,
+	NftDb(ternoa_sgx_nft::error::Error),
 	Other(Box<dyn std::error::Error>),
 }
 
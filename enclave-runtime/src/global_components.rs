@@ -20,7 +20,10 @@
 //! This allows the crates themselves to stay as generic as possible
 //! and ensures that the global instances are initialized once.
 
-use crate::ocall::OcallApi;
+use crate::{
+	nft_ownership_observer::NftOwnershipObserver, ocall::OcallApi,
+	version_anchor::ParentchainVersionAnchor,
+};
 use itc_parentchain::{
 	block_import_dispatcher::immediate_dispatcher::ImmediateDispatcher,
 	block_importer::ParentchainBlockImporter, light_client::ValidatorAccessor,
@@ -33,11 +36,14 @@ use sp_core::ed25519::Pair;
 
 pub type EnclaveExtrinsicsFactory = ExtrinsicsFactory<Pair, NonceCache>;
 pub type EnclaveValidatorAccessor = ValidatorAccessor<ParentchainBlock>;
+pub type EnclaveNftOwnershipObserver = NftOwnershipObserver<OcallApi>;
 pub type EnclaveParentChainBlockImporter = ParentchainBlockImporter<
 	ParentchainBlock,
 	EnclaveValidatorAccessor,
 	OcallApi,
 	EnclaveExtrinsicsFactory,
+	EnclaveNftOwnershipObserver,
+	ParentchainVersionAnchor,
 >;
 pub type EnclaveParentchainBlockImportImmediateDispatcher =
 	ImmediateDispatcher<EnclaveParentChainBlockImporter>;
@@ -45,3 +51,7 @@ pub type EnclaveParentchainBlockImportImmediateDispatcher =
 pub static GLOBAL_PARENTCHAIN_IMPORT_IMMEDIATE_DISPATCHER_COMPONENT: ComponentContainer<
 	EnclaveParentchainBlockImportImmediateDispatcher,
 > = ComponentContainer::new();
+
+/// Extrinsics factory for extrinsics that are not sent by the parentchain block import.
+pub static GLOBAL_EXTRINSICS_FACTORY_COMPONENT: ComponentContainer<EnclaveExtrinsicsFactory> =
+	ComponentContainer::new();
@@ -35,11 +35,14 @@ use sgx_types::size_t;
 use crate::{
 	error::{Error, Result},
 	global_components::{
-		EnclaveValidatorAccessor, GLOBAL_PARENTCHAIN_IMPORT_IMMEDIATE_DISPATCHER_COMPONENT,
+		EnclaveValidatorAccessor, GLOBAL_EXTRINSICS_FACTORY_COMPONENT,
+		GLOBAL_PARENTCHAIN_IMPORT_IMMEDIATE_DISPATCHER_COMPONENT,
 	},
+	nft_ownership_observer::NftOwnershipObserver,
 	ocall::OcallApi,
 	rpc::worker_api_direct::public_api_rpc_handler,
 	utils::{hash_from_slice, write_slice_and_whitespace_pad, DecodeRaw},
+	version_anchor::ParentchainVersionAnchor,
 };
 use codec::{alloc::string::String, Decode, Encode};
 use itc_direct_rpc_server::{
@@ -60,23 +63,32 @@ use itp_primitives_cache::GLOBAL_PRIMITIVES_CACHE;
 use itp_settings::node::{
 	REGISTER_ENCLAVE, RUNTIME_SPEC_VERSION, RUNTIME_TRANSACTION_VERSION, TEEREX_MODULE,
 };
-use itp_sgx_crypto::{aes, ed25519, rsa3072, Ed25519Seal, Rsa3072Seal};
+use itp_sgx_crypto::{aes, ed25519, nft_key_seed, rsa3072, Ed25519Seal, Rsa3072Seal};
 use itp_sgx_io as io;
 use itp_sgx_io::SealedIO;
 use itp_storage::StorageProof;
-use itp_types::{Block, Header, SignedBlock};
+use itp_types::{AccountId, Block, Header, SignedBlock};
 use log::*;
-use sgx_types::sgx_status_t;
+use sgx_types::{c_int, sgx_status_t};
 use sp_core::crypto::Pair;
 use sp_finality_grandpa::VersionedAuthorityList;
 use std::{slice, sync::Arc, vec::Vec};
 use substrate_api_client::compose_extrinsic_offline;
+use ternoa_sgx_nft::NftDbSeal;
 
 mod attestation;
 mod global_components;
 mod ipfs;
+mod nft_db_backup;
+mod nft_keys;
+mod nft_ownership_observer;
+mod nft_secret_commitment;
+mod nft_secret_replication;
+mod nft_secret_sharing;
+mod nft_secret_unlock;
 mod ocall;
 mod utils;
+mod version_anchor;
 
 pub mod cert;
 pub mod error;
@@ -108,6 +120,7 @@ pub unsafe extern "C" fn init(
 	mu_ra_addr_size: u32,
 	untrusted_worker_addr: *const u8,
 	untrusted_worker_addr_size: u32,
+	block_listed_nft_secrets: c_int,
 ) -> sgx_status_t {
 	// Initialize the logging environment in the enclave.
 	env_logger::init();
@@ -131,6 +144,16 @@ pub unsafe extern "C" fn init(
 		return e.into()
 	}
 
+	// Like the aes key, the seed of the NFT key pairs is overwritten by mutual remote attestation.
+	if let Err(e) = nft_key_seed::create_sealed_if_absent().map_err(Error::Crypto) {
+		return e.into()
+	}
+
+	// Prepare the per-record NFT secret storage and migrate a legacy single-file database.
+	if let Err(e) = NftDbSeal::init() {
+		return e.into()
+	}
+
 	let mu_ra_url =
 		match String::decode(&mut slice::from_raw_parts(mu_ra_addr, mu_ra_addr_size as usize))
 			.map_err(Error::Codec)
@@ -153,6 +176,7 @@ pub unsafe extern "C" fn init(
 		GLOBAL_PRIMITIVES_CACHE.as_ref(),
 		&mu_ra_url,
 		&untrusted_worker_url,
+		block_listed_nft_secrets == 1,
 	)
 	.map_err(Error::PrimitivesAccess)
 	{
@@ -369,10 +393,16 @@ pub unsafe extern "C" fn init_light_client(
 	let ocall_api = Arc::new(OcallApi);
 	let extrinsics_factory =
 		Arc::new(ExtrinsicsFactory::new(genesis_hash, signer.clone(), GLOBAL_NONCE_CACHE.clone()));
+	GLOBAL_EXTRINSICS_FACTORY_COMPONENT.initialize(extrinsics_factory.clone());
+	let nft_ownership_observer = Arc::new(NftOwnershipObserver::new(ocall_api.clone()));
+	let version_anchor =
+		Arc::new(ParentchainVersionAnchor::new(AccountId::from(signer.public().0)));
 	let parentchain_block_importer = Arc::new(ParentchainBlockImporter::new(
 		validator_access,
 		ocall_api.clone(),
 		extrinsics_factory,
+		nft_ownership_observer,
+		version_anchor,
 	));
 	let block_import_dispatcher = Arc::new(ImmediateDispatcher::new(parentchain_block_importer));
 
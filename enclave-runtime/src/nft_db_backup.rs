@@ -0,0 +1,242 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Encrypted backups of the NFT secret database to IPFS.
+//!
+//! A backup is encrypted with a key derived from the state encryption key, which is only ever
+//! provisioned to attested workers running this enclave (see `tls_ra`). Any of them can restore
+//! the backup, while neither the host nor the IPFS network learn anything about the secrets.
+
+use crate::{
+	error::{Error, Result},
+	ipfs::IpfsContent,
+	ocall::OcallApi,
+	sync::{EnclaveLock, NftDbRwLock},
+};
+use codec::{Decode, Encode};
+use itp_ocall_api::{EnclaveIpfsOCallApi, IpfsCid};
+use itp_sgx_crypto::AesSeal;
+use itp_sgx_io::SealedIO;
+use log::*;
+use sgx_rand::{os::SgxRng, Rng};
+use sgx_tcrypto::{
+	rsgx_rijndael128GCM_decrypt, rsgx_rijndael128GCM_encrypt, rsgx_rijndael128_cmac_slice,
+};
+use sgx_types::sgx_status_t;
+use std::{format, slice, str, vec::Vec};
+use ternoa_sgx_nft::{
+	snapshot::{NftDbBackup, NftDbRecord, NFT_DB_SNAPSHOT_VERSION},
+	NftDbSeal,
+};
+
+/// Domain separation of the backup key from the state encryption key it is derived from.
+const BACKUP_KEY_CONTEXT: &[u8] = b"ternoa-nft-db-backup";
+
+#[no_mangle]
+pub unsafe extern "C" fn backup_nft_db(cid: *mut u8, cid_size: u32) -> sgx_status_t {
+	let cid_slice = slice::from_raw_parts_mut(cid, cid_size as usize);
+
+	let backup_cid = match write_backup() {
+		Ok(backup_cid) => backup_cid,
+		Err(e) => return e.into(),
+	};
+
+	if backup_cid.0.len() != cid_slice.len() {
+		error!("Provided CID buffer has the wrong size: {}", cid_slice.len());
+		return sgx_status_t::SGX_ERROR_INVALID_PARAMETER
+	}
+	cid_slice.clone_from_slice(&backup_cid.0);
+
+	sgx_status_t::SGX_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn restore_nft_db(
+	cid: *const u8,
+	cid_size: u32,
+	backup: *const u8,
+	backup_size: u32,
+) -> sgx_status_t {
+	let cid = match str::from_utf8(slice::from_raw_parts(cid, cid_size as usize)) {
+		Ok(cid) => cid,
+		Err(e) => return Error::Other(e.into()).into(),
+	};
+	let backup = slice::from_raw_parts(backup, backup_size as usize).to_vec();
+
+	match restore_backup(cid, backup) {
+		Ok(count) => info!("Restored {} NFT records from backup {}", count, cid),
+		Err(e) => return e.into(),
+	}
+
+	sgx_status_t::SGX_SUCCESS
+}
+
+fn write_backup() -> Result<IpfsCid> {
+	let records = {
+		let _nft_db_lock = EnclaveLock::read_nft_db()?;
+		NftDbSeal::records()?
+	};
+	let cid = upload_backup(&OcallApi, &backup_key()?, &records)?;
+	info!("Backed up {} NFT records to IPFS", records.len());
+	Ok(cid)
+}
+
+fn restore_backup(cid: &str, backup: Vec<u8>) -> Result<usize> {
+	let records = open_backup(&backup_key()?, cid, backup)?;
+	let count = records.len();
+
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	NftDbSeal::import(records)?;
+	Ok(count)
+}
+
+/// Encrypts the records and adds the backup to IPFS.
+fn upload_backup<O: EnclaveIpfsOCallApi>(
+	ocall_api: &O,
+	key: &[u8; 16],
+	records: &[NftDbRecord],
+) -> Result<IpfsCid> {
+	let backup = encrypt_backup(key, &records.encode())?;
+	Ok(ocall_api.write_ipfs(&backup.encode())?)
+}
+
+/// Verifies the backup fetched from IPFS against its CID, and decrypts its records.
+fn open_backup(key: &[u8; 16], cid: &str, backup: Vec<u8>) -> Result<Vec<NftDbRecord>> {
+	// The content must match the requested CID, such that the host cannot tamper with it. An
+	// older backup can still be requested, hence its records are only imported where they are
+	// newer than the present ones.
+	let mut content = IpfsContent::new(cid, backup);
+	content
+		.verify()
+		.map_err(|e| Error::Other(format!("backup does not match CID: {:?}", e).into()))?;
+
+	let backup = NftDbBackup::decode(&mut content.file_content.as_slice())?;
+	if backup.version != NFT_DB_SNAPSHOT_VERSION {
+		return Err(Error::Other(
+			format!(
+				"unsupported NFT db backup version {}, expected {}",
+				backup.version, NFT_DB_SNAPSHOT_VERSION
+			)
+			.into(),
+		))
+	}
+	Ok(Decode::decode(&mut decrypt_backup(key, &backup)?.as_slice())?)
+}
+
+fn encrypt_backup(key: &[u8; 16], plaintext: &[u8]) -> Result<NftDbBackup> {
+	let mut nonce = [0u8; 12];
+	SgxRng::new()?.fill_bytes(&mut nonce);
+
+	let mut ciphertext = vec![0u8; plaintext.len()];
+	let mut mac = [0u8; 16];
+	let aad = NFT_DB_SNAPSHOT_VERSION.encode();
+	rsgx_rijndael128GCM_encrypt(key, plaintext, &nonce, &aad, &mut ciphertext, &mut mac)?;
+
+	Ok(NftDbBackup { version: NFT_DB_SNAPSHOT_VERSION, nonce, ciphertext, mac })
+}
+
+fn decrypt_backup(key: &[u8; 16], backup: &NftDbBackup) -> Result<Vec<u8>> {
+	let mut plaintext = vec![0u8; backup.ciphertext.len()];
+	let aad = backup.version.encode();
+	rsgx_rijndael128GCM_decrypt(
+		key,
+		&backup.ciphertext,
+		&backup.nonce,
+		&aad,
+		&backup.mac,
+		&mut plaintext,
+	)?;
+	Ok(plaintext)
+}
+
+fn backup_key() -> Result<[u8; 16]> {
+	derive_backup_key(&AesSeal::unseal()?.key, BACKUP_KEY_CONTEXT)
+}
+
+fn derive_backup_key(state_key: &[u8; 16], context: &[u8]) -> Result<[u8; 16]> {
+	Ok(rsgx_rijndael128_cmac_slice(state_key, context)?)
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+	use super::*;
+	use crate::{test::mocks::ipfs_ocall_mock::IpfsOCallMock, tests::state_key};
+	use std::string::String;
+	use ternoa_sgx_nft::{versions::WriteVersion, NftShare};
+
+	fn records() -> Vec<NftDbRecord> {
+		vec![
+			NftDbRecord::Removal(2, WriteVersion::removal(7)),
+			NftDbRecord::Share(
+				NftShare::new(1, 0, b"secret".to_vec()),
+				Some(WriteVersion::upsert(5, b"secret")),
+			),
+		]
+	}
+
+	fn test_backup_key() -> [u8; 16] {
+		derive_backup_key(&state_key().key, BACKUP_KEY_CONTEXT).unwrap()
+	}
+
+	/// Backs the records up to the IPFS stand-in and returns the CID and content of the backup.
+	fn upload(ipfs: &IpfsOCallMock, key: &[u8; 16], records: &[NftDbRecord]) -> (String, Vec<u8>) {
+		let cid = upload_backup(ipfs, key, records).unwrap();
+		let content = ipfs.file(&cid).unwrap();
+		(str::from_utf8(&cid.0).unwrap().into(), content)
+	}
+
+	pub fn backup_restores_the_backed_up_records() {
+		let ipfs = IpfsOCallMock::default();
+		let key = test_backup_key();
+
+		let (cid, content) = upload(&ipfs, &key, &records());
+		let restored = open_backup(&key, &cid, content).unwrap();
+
+		assert_eq!(restored.encode(), records().encode());
+	}
+
+	pub fn backup_not_matching_its_cid_is_rejected() {
+		let ipfs = IpfsOCallMock::default();
+		let key = test_backup_key();
+		let (cid, _) = upload(&ipfs, &key, &records());
+		let (_, other_content) = upload(&ipfs, &key, &records()[..1]);
+
+		assert!(open_backup(&key, &cid, other_content).is_err());
+	}
+
+	pub fn tampered_backup_is_rejected() {
+		let ipfs = IpfsOCallMock::default();
+		let key = test_backup_key();
+		let mut backup = encrypt_backup(&key, &records().encode()).unwrap();
+		backup.ciphertext[0] ^= 1;
+
+		// The tampered backup is added to IPFS, hence it matches its CID.
+		let cid = ipfs.write_ipfs(&backup.encode()).unwrap();
+		let content = ipfs.file(&cid).unwrap();
+
+		assert!(open_backup(&key, str::from_utf8(&cid.0).unwrap(), content).is_err());
+	}
+
+	pub fn backup_encrypted_in_other_context_is_rejected() {
+		let ipfs = IpfsOCallMock::default();
+		let other_key = derive_backup_key(&state_key().key, b"ternoa-other-context").unwrap();
+
+		let (cid, content) = upload(&ipfs, &other_key, &records());
+
+		assert!(open_backup(&test_backup_key(), &cid, content).is_err());
+	}
+}
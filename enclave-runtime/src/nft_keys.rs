@@ -0,0 +1,107 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Key pairs bound to NFTs, that never leave the enclave.
+//!
+//! The key pair of a NFT is a NIST P-256 key pair, derived from the sealed master seed and the
+//! NFT id. The private key is never stored, but derived again whenever it is used. The master
+//! seed is provisioned along with the other keys, hence every worker derives the same key pair
+//! for a NFT.
+//!
+//! The owner of a NFT uses its key pair through the enclave, which signs messages and decrypts
+//! `NftCiphertext`s with it.
+
+use crate::error::{Error, Result};
+use codec::Encode;
+use itp_sgx_crypto::NftKeySeedSeal;
+use itp_sgx_io::SealedIO;
+use itp_types::{NftCiphertext, NftPublicKey, NftSignature};
+use sgx_tcrypto::{rsgx_ecc256_pub_from_priv, rsgx_rijndael128GCM_decrypt, SgxEccHandle};
+use sgx_types::{sgx_ec256_private_t, sgx_ec256_public_t};
+use sp_core::hashing::blake2_256;
+use std::vec::Vec;
+
+/// Domain separation of the NFT key pairs from anything else derived from the master seed.
+const NFT_KEY_CONTEXT: &[u8] = b"ternoa-nft-key";
+
+/// Key pair of a NFT.
+pub struct NftKeyPair {
+	pub private: sgx_ec256_private_t,
+	pub public: sgx_ec256_public_t,
+}
+
+impl NftKeyPair {
+	pub fn public_key(&self) -> NftPublicKey {
+		NftPublicKey { gx: self.public.gx, gy: self.public.gy }
+	}
+
+	pub fn sign(&self, message: &[u8]) -> Result<NftSignature> {
+		let ecc_handle = SgxEccHandle::new();
+		ecc_handle.open()?;
+		let signature = ecc_handle.ecdsa_sign_slice(message, &self.private)?;
+		Ok(NftSignature { x: le_bytes(&signature.x), y: le_bytes(&signature.y) })
+	}
+
+	/// Decrypts a ciphertext that has been encrypted to the key of the NFT `nft_id`. Fails if
+	/// the ciphertext has been tampered with or is addressed to another NFT.
+	pub fn decrypt(&self, nft_id: u32, ciphertext: &NftCiphertext) -> Result<Vec<u8>> {
+		let ephemeral_key = &ciphertext.ephemeral_key;
+		let ecc_handle = SgxEccHandle::new();
+		ecc_handle.open()?;
+		let shared_secret = ecc_handle.compute_shared_dhkey(
+			&self.private,
+			&sgx_ec256_public_t { gx: ephemeral_key.gx, gy: ephemeral_key.gy },
+		)?;
+
+		let key = NftCiphertext::key(&shared_secret.s, ephemeral_key);
+		let mut message = vec![0u8; ciphertext.ciphertext.len()];
+		rsgx_rijndael128GCM_decrypt(
+			&key,
+			&ciphertext.ciphertext,
+			&NftCiphertext::NONCE,
+			&NftCiphertext::aad(nft_id),
+			&ciphertext.mac,
+			&mut message,
+		)?;
+		Ok(message)
+	}
+}
+
+/// Signature coordinates are kept as little-endian 32-bit words by the SGX crypto library.
+fn le_bytes(words: &[u32; 8]) -> [u8; 32] {
+	let mut bytes = [0u8; 32];
+	for (chunk, word) in bytes.chunks_exact_mut(4).zip(words.iter()) {
+		chunk.copy_from_slice(&word.to_le_bytes());
+	}
+	bytes
+}
+
+/// Derives the key pair of the NFT from the master seed.
+pub fn derive_key_pair(nft_id: u32) -> Result<NftKeyPair> {
+	let seed = NftKeySeedSeal::unseal()?;
+	// A digest is not a valid private key with a probability of about 2^-32, in which case the
+	// derivation continues with the next counter.
+	(0u32..)
+		.find_map(|counter| {
+			let r = blake2_256(&(NFT_KEY_CONTEXT, seed, nft_id, counter).encode());
+			let private = sgx_ec256_private_t { r };
+			rsgx_ecc256_pub_from_priv(&private)
+				.ok()
+				.map(|public| NftKeyPair { private, public })
+		})
+		.ok_or_else(|| Error::Other("failed to derive the NFT key pair".into()))
+}
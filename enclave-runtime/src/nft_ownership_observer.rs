@@ -0,0 +1,331 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Removes the sealed secrets of NFTs that have been burned on the parentchain and the access
+//! grants of NFTs that have been transferred to a new owner.
+//!
+//! The secrets of a transferred NFT are handed over to the new owner: they are re-encrypted to
+//! the recipient key the new owner has registered, and held as a `NftSecretHandover` until the
+//! NFT changes hands again. Shares that are still time-locked are left out.
+//!
+//! Only the NFTs that the imported blocks transfer, sell or burn are checked against the
+//! parentchain state, along with a few more stored NFTs on every import. The latter sweep
+//! through all stored NFTs over time, and catch ownership changes by calls that are not
+//! scanned, e.g. calls nested in a batch.
+
+use crate::{
+	nft_secret_sharing, nft_secret_unlock,
+	rpc::worker_api_direct::encrypt_nft_secret,
+	sync::{EnclaveLock, NftDbRwLock},
+};
+use codec::{Decode, Encode};
+use core::sync::atomic::{AtomicU32, Ordering};
+use itc_parentchain::block_importer::{
+	error::{Error as ImportError, Result as ImportResult},
+	ObserveNftOwnership,
+};
+use itp_nfts_storage::{NFTsStorage, NFTsStorageKeys};
+use itp_ocall_api::EnclaveOnChainOCallApi;
+use itp_settings::{
+	enclave::NFT_OWNERSHIP_SWEEP_SIZE,
+	node::{MARKETPLACE_BUY, MARKETPLACE_MODULE, NFTS_MODULE, NFT_BURN, NFT_TRANSFER},
+};
+use itp_storage_verifier::GetStorageVerified;
+use itp_types::{AccountId, Block as ParentchainBlock, Header, NFTData, NftSecretHandover};
+use log::*;
+use sp_runtime::traits::Block as BlockT;
+use std::{format, sync::Arc, vec::Vec};
+use substrate_api_client::UncheckedExtrinsicV4;
+use ternoa_sgx_nft::{
+	acl::NftAclSeal,
+	error::Error as NftError,
+	handover::{NftOwnerRecord, NftOwnerRecordSeal},
+	recipient_key::{RecipientKey, RecipientKeySeal},
+	NftDbSeal, ShareIndex,
+};
+
+/// Call index and first argument of a call, which is the NFT id for the scanned calls.
+type NftCallPrefix = ([u8; 2], u32);
+
+pub struct NftOwnershipObserver<OCallApi> {
+	ocall_api: Arc<OCallApi>,
+	/// Id of the stored NFT the next sweep starts at.
+	sweep_cursor: AtomicU32,
+}
+
+impl<OCallApi> NftOwnershipObserver<OCallApi> {
+	pub fn new(ocall_api: Arc<OCallApi>) -> Self {
+		NftOwnershipObserver { ocall_api, sweep_cursor: AtomicU32::new(0) }
+	}
+
+	/// The next `NFT_OWNERSHIP_SWEEP_SIZE` of the sorted `stored` NFTs, wrapping around.
+	fn sweep(&self, stored: &[u32]) -> Vec<u32> {
+		let cursor = self.sweep_cursor.load(Ordering::Relaxed);
+		let start = match stored.binary_search(&cursor) {
+			Ok(p) | Err(p) => p,
+		};
+		let ids: Vec<u32> = stored
+			.iter()
+			.cycle()
+			.skip(start)
+			.take(NFT_OWNERSHIP_SWEEP_SIZE.min(stored.len()))
+			.copied()
+			.collect();
+		if let Some(last) = ids.last() {
+			self.sweep_cursor.store(last.wrapping_add(1), Ordering::Relaxed);
+		}
+		ids
+	}
+}
+
+impl<OCallApi: EnclaveOnChainOCallApi> ObserveNftOwnership<ParentchainBlock>
+	for NftOwnershipObserver<OCallApi>
+{
+	fn observe_nft_ownership(
+		&self,
+		blocks: &[ParentchainBlock],
+		header: &Header,
+	) -> ImportResult<()> {
+		let mut ids = {
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(to_import_error)?;
+			let stored = NftDbSeal::unseal_index().map_err(to_import_error)?.nft_ids();
+			let mut ids: Vec<u32> = blocks
+				.iter()
+				.flat_map(touched_nfts)
+				.filter(|id| stored.binary_search(id).is_ok())
+				.collect();
+			ids.extend(self.sweep(&stored));
+			ids
+		};
+		ids.sort_unstable();
+		ids.dedup();
+		if ids.is_empty() {
+			return Ok(())
+		}
+
+		let keys: Vec<Vec<u8>> = ids.iter().map(|id| NFTsStorage::data(*id)).collect();
+		let entries = self
+			.ocall_api
+			.get_multiple_storages_verified::<_, NFTData>(keys.clone(), header)
+			.map_err(to_import_error)?;
+		if entries.len() != keys.len() {
+			return Err(ImportError::Other("unexpected number of NFT storage entries".into()))
+		}
+
+		for ((id, key), entry) in ids.iter().zip(keys.iter()).zip(entries.iter()) {
+			// The worker could reorder the responses, so we must not rely on their order.
+			if entry.key() != key.as_slice() {
+				return Err(ImportError::Other(
+					format!("unexpected storage key for NFT {}", id).into(),
+				))
+			}
+			match entry.value() {
+				None => {
+					info!("NFT {} has been burned, removing its secrets", id);
+					let _nft_db_lock = EnclaveLock::write_nft_db().map_err(to_import_error)?;
+					match NftDbSeal::remove(*id, header.number) {
+						// The owner may have removed the secrets in the meantime.
+						Ok(()) | Err(NftError::NftNotFound) => {},
+						Err(e) => return Err(to_import_error(e)),
+					}
+				},
+				Some(data) => observe_owner(*id, AccountId::from(data.owner), header)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Ids of the NFTs that the calls of the block transfer, sell or burn. All of these calls take
+/// the NFT id as their first argument. The calls are not required to have succeeded.
+fn touched_nfts(block: &ParentchainBlock) -> Vec<u32> {
+	block
+		.extrinsics()
+		.iter()
+		.filter_map(|xt| {
+			UncheckedExtrinsicV4::<NftCallPrefix>::decode(&mut xt.encode().as_slice()).ok()
+		})
+		.filter_map(|xt| {
+			let ([module, call], nft_id) = xt.function;
+			is_nft_ownership_call(module, call).then(|| nft_id)
+		})
+		.collect()
+}
+
+fn is_nft_ownership_call(module: u8, call: u8) -> bool {
+	(module == NFTS_MODULE && (call == NFT_TRANSFER || call == NFT_BURN))
+		|| (module == MARKETPLACE_MODULE && call == MARKETPLACE_BUY)
+}
+
+/// Records the owner of the NFT and removes the access grants of a previous owner. Hands the
+/// secrets over, if the NFT has been transferred since its owner was last observed.
+///
+/// The secrets are encrypted to the new owner without holding the NFT db lock.
+fn observe_owner(id: u32, owner: AccountId, header: &Header) -> ImportResult<()> {
+	let (previous_owner, released) = {
+		let _nft_db_lock = EnclaveLock::read_nft_db().map_err(to_import_error)?;
+		let previous_owner = NftOwnerRecordSeal::get(id)
+			.map_err(to_import_error)?
+			.map(|record| record.owner().clone());
+		let released = match &previous_owner {
+			Some(previous_owner) if previous_owner != &owner => {
+				info!("NFT {} has been transferred, handing its secrets over to the new owner", id);
+				released_shares(id, &owner, header)?
+			},
+			_ => None,
+		};
+		(previous_owner, released)
+	};
+	let handover = match released {
+		Some((recipient_key, shares)) =>
+			Some(hand_over_secrets(id, &owner, header, &recipient_key, shares)?),
+		None => None,
+	};
+
+	let _nft_db_lock = EnclaveLock::write_nft_db().map_err(to_import_error)?;
+	// The secrets may have been removed in the meantime, along with their records.
+	if NftDbSeal::shares(id).map_err(to_import_error)?.is_empty() {
+		return Ok(())
+	}
+	match NftAclSeal::get(id).map_err(to_import_error)? {
+		Some(acl) if acl.owner() != &owner => {
+			info!("NFT {} has been transferred, removing its access grants", id);
+			// The emptied ACL keeps its revision, such that the grants are not imported again.
+			NftAclSeal::seal(acl.for_owner(owner.clone())).map_err(to_import_error)?;
+		},
+		_ => {},
+	}
+	// Another import may have observed the owner in the meantime.
+	let current_owner = NftOwnerRecordSeal::get(id)
+		.map_err(to_import_error)?
+		.map(|record| record.owner().clone());
+	if current_owner.as_ref() == Some(&owner) || current_owner != previous_owner {
+		return Ok(())
+	}
+	NftOwnerRecordSeal::seal(NftOwnerRecord::new(id, owner, handover)).map_err(to_import_error)
+}
+
+/// The shares of the NFT that are released to the new owner, i.e. that are no longer
+/// time-locked, along with the recipient key of the new owner. `None` if the new owner has not
+/// registered a key, or if the workers only hold shares of the secrets under threshold sharing.
+///
+/// The NFT db has to be locked by the caller.
+fn released_shares(
+	id: u32,
+	owner: &AccountId,
+	header: &Header,
+) -> ImportResult<Option<(RecipientKey, Vec<(ShareIndex, Vec<u8>)>)>> {
+	if nft_secret_sharing::threshold_sharing_config()
+		.map_err(to_import_error)?
+		.is_some()
+	{
+		return Ok(None)
+	}
+	let recipient_key = match RecipientKeySeal::get(owner).map_err(to_import_error)? {
+		Some(key) => key,
+		None => {
+			info!("New owner of NFT {} has no recipient key registered", id);
+			return Ok(None)
+		},
+	};
+
+	let mut shares = Vec::new();
+	for share_index in NftDbSeal::shares(id).map_err(to_import_error)? {
+		if let Some(condition) =
+			nft_secret_unlock::unlock_condition(id, share_index).map_err(to_import_error)?
+		{
+			if !nft_secret_unlock::is_met_at(&condition, header).map_err(to_import_error)? {
+				continue
+			}
+		}
+		shares.push((share_index, NftDbSeal::get(id, share_index).map_err(to_import_error)?));
+	}
+	Ok(Some((recipient_key, shares)))
+}
+
+/// Encrypts the released shares of the NFT to the recipient key of the new owner.
+fn hand_over_secrets(
+	id: u32,
+	owner: &AccountId,
+	header: &Header,
+	recipient_key: &RecipientKey,
+	shares: Vec<(ShareIndex, Vec<u8>)>,
+) -> ImportResult<NftSecretHandover> {
+	let mut encrypted_shares = Vec::with_capacity(shares.len());
+	for (share_index, secret) in shares {
+		let aad = (id, share_index).encode();
+		let encrypted =
+			encrypt_nft_secret(&aad, &secret, recipient_key.key()).map_err(to_import_error)?;
+		encrypted_shares.push((share_index, encrypted));
+	}
+	Ok(NftSecretHandover {
+		owner: owner.clone(),
+		block_number: header.number,
+		shares: encrypted_shares,
+	})
+}
+
+fn to_import_error<E: core::fmt::Debug>(e: E) -> ImportError {
+	ImportError::Other(format!("{:?}", e).into())
+}
+
+#[cfg(feature = "test")]
+pub mod tests {
+	use super::*;
+	use crate::{nft_secret_replication, tests::latest_parentchain_header};
+	use itp_types::NftSecretWriteMode;
+	use sgx_crypto_helper::{rsa3072::Rsa3072KeyPair, RsaKeyPair};
+	use sgx_rand::{os::SgxRng, Rng};
+	use ternoa_sgx_nft::versions::WriteVersion;
+
+	pub fn first_transfer_after_store_hands_the_secret_over() {
+		// The sealed files outlive the test enclave, hence every run stores another NFT.
+		let nft_id = SgxRng::new().unwrap().next_u32();
+		let seller = AccountId::new([1u8; 32]);
+		let buyer = AccountId::new([2u8; 32]);
+		let header = latest_parentchain_header();
+		let recipient_key = Rsa3072KeyPair::new().unwrap().export_pubkey().unwrap();
+		let recipient_key = serde_json::to_vec(&recipient_key).unwrap();
+		{
+			let _nft_db_lock = EnclaveLock::write_nft_db().unwrap();
+			RecipientKeySeal::upsert(RecipientKey::new(buyer.clone(), 1, recipient_key)).unwrap();
+		}
+
+		let version = WriteVersion::upsert(header.number, b"secret");
+		let stored = nft_secret_replication::upsert(
+			nft_id,
+			0,
+			b"secret".to_vec(),
+			version,
+			seller.clone(),
+			seller,
+			None,
+			NftSecretWriteMode::Owner,
+		)
+		.unwrap();
+		observe_owner(nft_id, buyer.clone(), &header).unwrap();
+
+		let handover = NftOwnerRecordSeal::get(nft_id).unwrap().unwrap().into_handover().unwrap();
+		assert!(stored);
+		assert_eq!(handover.owner, buyer);
+		assert_eq!(handover.block_number, header.number);
+		assert_eq!(handover.shares.len(), 1);
+
+		let _nft_db_lock = EnclaveLock::write_nft_db().unwrap();
+		NftDbSeal::remove(nft_id, header.number).unwrap();
+	}
+}
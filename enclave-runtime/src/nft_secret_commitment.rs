@@ -0,0 +1,79 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Commitments to the stored NFT secrets, published on the parentchain.
+//!
+//! Whenever a secret share is stored, the enclave remarks a `NftSecretCommitment` to it from its
+//! registered account. Hence anybody can check that an enclave holds a secret for a NFT, while
+//! the owner can open the commitment with the salt and audit it against the retrieved secret.
+//!
+//! The salt is derived from the state encryption key, such that every worker that shares the
+//! keys can open the commitment, without the salt having to be stored.
+
+use crate::{
+	error::{Error, Result},
+	global_components::GLOBAL_EXTRINSICS_FACTORY_COMPONENT,
+	EnclaveValidatorAccessor, OcallApi,
+};
+use codec::Encode;
+use itc_parentchain::light_client::{concurrent_access::ValidatorAccess, Validator};
+use itp_component_container::ComponentGetter;
+use itp_extrinsics_factory::CreateExtrinsics;
+use itp_settings::node::{REMARK, SYSTEM_MODULE};
+use itp_sgx_crypto::AesSeal;
+use itp_sgx_io::SealedIO;
+use itp_types::{NftSecretCommitment, NftSecretCommitmentProof, OpaqueCall};
+use sgx_tcrypto::rsgx_rijndael128_cmac_slice;
+use sp_core::hashing::blake2_256;
+
+/// Domain separation of the salt key from the state encryption key it is derived from.
+const SALT_KEY_CONTEXT: &[u8] = b"ternoa-nft-secret-commitment";
+
+/// Publishes the commitment to the secret share on the parentchain.
+pub fn commit_secret(nft_id: u32, share_index: u8, secret: &[u8]) -> Result<NftSecretCommitment> {
+	let commitment = commitment_proof(nft_id, share_index, secret)?.commitment;
+	let call = OpaqueCall::from_tuple(&([SYSTEM_MODULE, REMARK], commitment.remark()));
+
+	let extrinsics_factory = GLOBAL_EXTRINSICS_FACTORY_COMPONENT
+		.get()
+		.ok_or(Error::ComponentNotInitialized)?;
+	let extrinsics = extrinsics_factory.create_extrinsics(&[call])?;
+
+	// The validator caches the sent extrinsics to check their inclusion.
+	EnclaveValidatorAccessor::default()
+		.execute_mut_on_validator(|v| v.send_extrinsics(&OcallApi, extrinsics))?;
+	Ok(commitment)
+}
+
+/// Returns the commitment to the secret share along with its opening.
+pub fn commitment_proof(
+	nft_id: u32,
+	share_index: u8,
+	secret: &[u8],
+) -> Result<NftSecretCommitmentProof> {
+	let salt = salt(nft_id, share_index, secret)?;
+	let commitment = NftSecretCommitment::new(nft_id, share_index, secret, &salt);
+	Ok(NftSecretCommitmentProof { commitment, salt })
+}
+
+/// Salt of the commitment, unique per secret share and unpredictable outside of the enclave.
+fn salt(nft_id: u32, share_index: u8, secret: &[u8]) -> Result<[u8; 16]> {
+	let aes = AesSeal::unseal()?;
+	let salt_key = rsgx_rijndael128_cmac_slice(&aes.key, SALT_KEY_CONTEXT)?;
+	let salt_input = (nft_id, share_index, blake2_256(secret)).encode();
+	Ok(rsgx_rijndael128_cmac_slice(&salt_key, &salt_input)?)
+}
@@ -0,0 +1,374 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Replication of the NFT secrets between the registered workers.
+//!
+//! Unless threshold sharing is enabled, every worker holds all secrets. A write accepted by one
+//! worker is pushed to all other registered workers running this enclave, over the mutually
+//! remote attested TLS channel of `tls_ra`. Writes are versioned by the parentchain block number
+//! at which they have been accepted, such that every replica resolves conflicting writes alike.
+//!
+//! Pushing is best effort: a worker that is unavailable misses the write, and does not catch up
+//! on it later. The workers that missed a write are reported to the caller, who may repeat it.
+//! The write is pushed to all workers in parallel, and a worker that has not applied it within
+//! `NFT_SECRET_REPLICATION_TIMEOUT` is reported to have missed it.
+//!
+//! Series secrets, capsule keys and recipient keys are replicated alike. They are not split under
+//! threshold sharing, hence they are only supported while threshold sharing is disabled.
+//!
+//! The access grants of the NFTs are replicated in either case, such that every worker answers
+//! alike. Conflicting grants are resolved by the revision of the ACL.
+
+use crate::{
+	error::{Error, Result},
+	nft_secret_sharing::{
+		registered_workers, self_account, send_to_worker, sharing_config, NftSecretSharingConfig,
+	},
+	sync::{EnclaveLock, NftDbRwLock},
+	tls_ra::{MuRaRequest, MuRaResponse},
+};
+use itp_settings::enclave::NFT_SECRET_REPLICATION_TIMEOUT;
+use itp_types::{
+	AccountId, Enclave, Header, NftSecretReplication, NftSecretWriteMode, UnlockCondition,
+};
+use lazy_static::lazy_static;
+use log::*;
+use std::{
+	sync::{
+		mpsc::{channel, Sender},
+		Arc, SgxMutex,
+	},
+	thread,
+	time::Instant,
+	vec::Vec,
+};
+use ternoa_sgx_nft::{
+	acl::{NftAcl, NftAclSeal},
+	capsule::{CapsuleKey, CapsuleKeySeal},
+	handover::{NftOwnerRecord, NftOwnerRecordSeal},
+	metadata::{NftSecretMetadataSeal, ShareWrite},
+	recipient_key::{RecipientKey, RecipientKeySeal},
+	series::{NftSeriesSecretSeal, SeriesSecret},
+	versions::WriteVersion,
+	NftDbSeal, ShareIndex,
+};
+
+lazy_static! {
+	/// Workers that a write is being pushed to.
+	static ref PUSHING_TO: SgxMutex<Vec<AccountId>> = Default::default();
+}
+
+/// The other registered workers, that a write is pushed to.
+pub struct Peers {
+	config: NftSecretSharingConfig,
+	workers: Vec<Enclave>,
+}
+
+/// Seals the secret share written by `writer` while `owner` owns the NFT, and pushes it to the
+/// other workers. Returns `None` if the share has been written or the NFT has been removed by a
+/// newer write. Fails with `WriteDenied` if the share has been written in a mode that a write in
+/// `mode` may not overwrite.
+#[allow(clippy::too_many_arguments)]
+pub fn store_secret(
+	header: &Header,
+	nft_id: u32,
+	share_index: ShareIndex,
+	secret: Vec<u8>,
+	writer: AccountId,
+	owner: AccountId,
+	unlock: Option<UnlockCondition>,
+	mode: NftSecretWriteMode,
+) -> Result<Option<NftSecretReplication>> {
+	let peers = peers(header)?;
+	let version = WriteVersion::upsert(header.number, &secret);
+	let write = upsert(
+		nft_id,
+		share_index,
+		secret.clone(),
+		version,
+		writer.clone(),
+		owner.clone(),
+		unlock,
+		mode,
+	)?;
+	if !write {
+		return Ok(None)
+	}
+	let request = MuRaRequest::ReplicateNftSecret {
+		nft_id,
+		share_index,
+		secret,
+		version,
+		writer,
+		owner,
+		unlock,
+		mode,
+	};
+	Ok(Some(replicate(&peers, request)))
+}
+
+/// Seals the series secret and pushes it to the other workers. Returns `None` if the series
+/// secret has been written by a newer write.
+pub fn store_series_secret(
+	header: &Header,
+	secret: SeriesSecret,
+) -> Result<Option<NftSecretReplication>> {
+	let peers = peers(header)?;
+	if !upsert_series_secret(secret.clone())? {
+		return Ok(None)
+	}
+	Ok(Some(replicate(&peers, MuRaRequest::ReplicateNftSeriesSecret(secret))))
+}
+
+/// Seals the capsule key and pushes it to the other workers. Returns `None` if the capsule key
+/// has been written by a newer write.
+pub fn store_capsule_key(header: &Header, key: CapsuleKey) -> Result<Option<NftSecretReplication>> {
+	let peers = peers(header)?;
+	if !upsert_capsule_key(key.clone())? {
+		return Ok(None)
+	}
+	Ok(Some(replicate(&peers, MuRaRequest::ReplicateCapsuleKey(key))))
+}
+
+/// Seals the recipient key and pushes it to the other workers. Returns `None` if the recipient
+/// key has been written by a newer write.
+pub fn store_recipient_key(
+	header: &Header,
+	key: RecipientKey,
+) -> Result<Option<NftSecretReplication>> {
+	let peers = peers(header)?;
+	if !upsert_recipient_key(key.clone())? {
+		return Ok(None)
+	}
+	Ok(Some(replicate(&peers, MuRaRequest::ReplicateRecipientKey(key))))
+}
+
+/// Removes the secrets of the NFT and pushes the removal to the other workers. Returns `None`
+/// if the NFT has been removed by a newer write.
+pub fn remove_secrets(header: &Header, nft_id: u32) -> Result<Option<NftSecretReplication>> {
+	let peers = peers(header)?;
+	let version = WriteVersion::removal(header.number);
+	if !remove(nft_id, version)? {
+		return Ok(None)
+	}
+	Ok(Some(replicate(&peers, MuRaRequest::ReplicateNftSecretRemoval { nft_id, version })))
+}
+
+/// Pushes the ACL of a NFT, that has been sealed after looking up the `peers`, to them.
+pub fn replicate_acl(peers: &Peers, acl: NftAcl) -> NftSecretReplication {
+	replicate(peers, MuRaRequest::ReplicateNftAcl(acl))
+}
+
+/// Serves a write that has been pushed by a fellow worker over MU-RA.
+pub fn handle_worker_request(request: MuRaRequest) -> MuRaResponse {
+	let result = sharing_config().and_then(|config| {
+		// Every worker checks the access grants itself, also under threshold sharing.
+		if let MuRaRequest::ReplicateNftAcl(acl) = request {
+			return upsert_acl(acl).map(|_| MuRaResponse::Done)
+		}
+		// The secrets must not be replicated if the workers only hold shares of them.
+		if config.threshold > 0 {
+			return Err(Error::Other("secrets are not replicated under threshold sharing".into()))
+		}
+		match request {
+			MuRaRequest::ReplicateNftSecret {
+				nft_id,
+				share_index,
+				secret,
+				version,
+				writer,
+				owner,
+				unlock,
+				mode,
+			} => upsert(nft_id, share_index, secret, version, writer, owner, unlock, mode)
+				.map(|_| MuRaResponse::Done),
+			MuRaRequest::ReplicateNftSecretRemoval { nft_id, version } =>
+				remove(nft_id, version).map(|_| MuRaResponse::Done),
+			MuRaRequest::ReplicateNftSeriesSecret(secret) =>
+				upsert_series_secret(secret).map(|_| MuRaResponse::Done),
+			MuRaRequest::ReplicateCapsuleKey(key) =>
+				upsert_capsule_key(key).map(|_| MuRaResponse::Done),
+			MuRaRequest::ReplicateRecipientKey(key) =>
+				upsert_recipient_key(key).map(|_| MuRaResponse::Done),
+			_ => Err(Error::Other("unexpected worker request".into())),
+		}
+	});
+	result.unwrap_or_else(|e| {
+		error!("Failed to serve worker request: {:?}", e);
+		MuRaResponse::Failed
+	})
+}
+
+/// Seals the secret share, unless it has been written or the NFT has been removed by a newer
+/// write. Returns whether the share has been sealed.
+#[allow(clippy::too_many_arguments)]
+pub fn upsert(
+	nft_id: u32,
+	share_index: ShareIndex,
+	secret: Vec<u8>,
+	version: WriteVersion,
+	writer: AccountId,
+	owner: AccountId,
+	unlock: Option<UnlockCondition>,
+	mode: NftSecretWriteMode,
+) -> Result<bool> {
+	let byte_length = secret.len() as u32;
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	NftSecretMetadataSeal::check_write(nft_id, share_index, mode)?;
+	if !NftDbSeal::upsert_versioned(nft_id, share_index, secret, version)? {
+		return Ok(false)
+	}
+	let block_number = version.block_number;
+	let write = ShareWrite { share_index, byte_length, block_number, writer, unlock, mode };
+	NftSecretMetadataSeal::record_write(nft_id, write)?;
+	// Later owners are recorded by the `NftOwnershipObserver`, which hands the secrets over
+	// once the NFT changes hands. Without a record of the first owner, it would miss the first
+	// transfer.
+	if NftOwnerRecordSeal::get(nft_id)?.is_none() {
+		NftOwnerRecordSeal::seal(NftOwnerRecord::new(nft_id, owner, None))?;
+	}
+	Ok(true)
+}
+
+fn upsert_series_secret(secret: SeriesSecret) -> Result<bool> {
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	Ok(NftSeriesSecretSeal::upsert(secret)?)
+}
+
+fn upsert_capsule_key(key: CapsuleKey) -> Result<bool> {
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	Ok(CapsuleKeySeal::upsert(key)?)
+}
+
+fn upsert_recipient_key(key: RecipientKey) -> Result<bool> {
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	Ok(RecipientKeySeal::upsert(key)?)
+}
+
+fn upsert_acl(acl: NftAcl) -> Result<bool> {
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	Ok(NftAclSeal::import(acl)?)
+}
+
+fn remove(nft_id: u32, version: WriteVersion) -> Result<bool> {
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	Ok(NftDbSeal::remove_versioned(nft_id, version)?)
+}
+
+/// Looks the other workers up before the write is applied, such that an accepted write is
+/// always pushed to them.
+pub fn peers(header: &Header) -> Result<Peers> {
+	let config = sharing_config()?;
+	let self_account = self_account()?;
+	let workers = registered_workers(header)?
+		.into_iter()
+		.filter(|w| w.pubkey != self_account)
+		.collect();
+	Ok(Peers { config, workers })
+}
+
+/// Pushes the write to all other registered workers in parallel, and reports those that have not
+/// applied it within `NFT_SECRET_REPLICATION_TIMEOUT`.
+///
+/// Every push runs on its own enclave thread, such that an unreachable worker delays the write by
+/// the timeout at most. A push that times out carries on in the background, the worker misses all
+/// further writes until it is done.
+fn replicate(peers: &Peers, request: MuRaRequest) -> NftSecretReplication {
+	let deadline = Instant::now() + NFT_SECRET_REPLICATION_TIMEOUT;
+	let request = Arc::new(request);
+	let (sender, receiver) = channel();
+	let pushed = peers
+		.workers
+		.iter()
+		.filter(|worker| spawn_push(peers.config, worker, request.clone(), sender.clone()))
+		.count();
+
+	let mut applied = Vec::new();
+	for _ in 0..pushed {
+		let timeout = deadline.saturating_duration_since(Instant::now());
+		match receiver.recv_timeout(timeout) {
+			Ok((worker, true)) => applied.push(worker),
+			Ok((_, false)) => {},
+			Err(_) => {
+				warn!(
+					"Write not replicated to all workers within {:?}",
+					NFT_SECRET_REPLICATION_TIMEOUT
+				);
+				break
+			},
+		}
+	}
+	let missed_by = peers
+		.workers
+		.iter()
+		.map(|worker| worker.pubkey.clone())
+		.filter(|worker| !applied.contains(worker))
+		.collect();
+	NftSecretReplication { missed_by }
+}
+
+/// Spawns the push of the write to the worker, unless an earlier push to it is still running.
+/// The push reports whether the worker applied the write to `sender`. Returns whether the push
+/// has been spawned.
+fn spawn_push(
+	config: NftSecretSharingConfig,
+	worker: &Enclave,
+	request: Arc<MuRaRequest>,
+	sender: Sender<(AccountId, bool)>,
+) -> bool {
+	match PUSHING_TO.lock() {
+		Ok(mut pushing) if !pushing.contains(&worker.pubkey) => pushing.push(worker.pubkey.clone()),
+		Ok(_) => {
+			warn!("Write not replicated to {:?}: busy with an earlier write", worker.pubkey);
+			return false
+		},
+		Err(e) => {
+			error!("Write not replicated to {:?}: {:?}", worker.pubkey, e);
+			return false
+		},
+	}
+	let peer = worker.clone();
+	let spawned = thread::Builder::new().name("nft_secret_replication".into()).spawn(move || {
+		let applied = match send_to_worker(&config, &peer, &request) {
+			Ok(MuRaResponse::Done) => true,
+			Ok(_) => {
+				warn!("Worker {:?} failed to apply replicated write", peer.pubkey);
+				false
+			},
+			Err(e) => {
+				warn!("Write not replicated to {:?}: {:?}", peer.pubkey, e);
+				false
+			},
+		};
+		done_pushing(&peer.pubkey);
+		// Nobody is waiting for the result anymore, if the push timed out.
+		let _ = sender.send((peer.pubkey, applied));
+	});
+	if let Err(e) = spawned {
+		warn!("Write not replicated to {:?}: {:?}", worker.pubkey, e);
+		done_pushing(&worker.pubkey);
+		return false
+	}
+	true
+}
+
+fn done_pushing(worker: &AccountId) {
+	match PUSHING_TO.lock() {
+		Ok(mut pushing) => pushing.retain(|pushing_to| pushing_to != worker),
+		Err(e) => error!("Failed to release the push to {:?}: {:?}", worker, e),
+	}
+}
@@ -0,0 +1,397 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Threshold sharing of NFT secrets across the registered workers.
+//!
+//! If a threshold `k` is configured, a stored secret is split into one Shamir share per
+//! registered worker running this enclave and every worker seals only its own share. The shares
+//! are exchanged over the mutually remote attested TLS channel of `tls_ra`, hence they are only
+//! ever handed out to enclaves with the same MRENCLAVE. Any `k` workers can reconstruct the
+//! secret, fewer learn nothing about it.
+
+use crate::{
+	error::{Error, Result},
+	nft_secret_unlock,
+	ocall::OcallApi,
+	sync::{EnclaveLock, NftDbRwLock},
+	tls_ra::{send_mu_ra_request, MuRaRequest, MuRaResponse},
+	version_anchor,
+};
+use codec::{Decode, Encode};
+use itp_ocall_api::EnclaveAttestationOCallApi;
+use itp_sgx_crypto::Ed25519Seal;
+use itp_sgx_io::SealedIO;
+use itp_storage_verifier::GetStorageVerified;
+use itp_teerex_storage::{TeeRexStorage, TeerexStorageKeys};
+use itp_types::{
+	AccountId, Enclave, Header, NftSecretReplication, NftSecretWriteMode, UnlockCondition,
+};
+use lazy_static::lazy_static;
+use log::*;
+use sgx_rand::{os::SgxRng, Rng};
+use sgx_types::{c_int, sgx_quote_sign_type_t, sgx_status_t};
+use sp_core::Pair;
+use std::{format, str, sync::SgxRwLock, vec::Vec};
+use ternoa_sgx_nft::{
+	error::Error as NftError,
+	metadata::{NftSecretMetadataSeal, ShareWrite},
+	shamir::{self, ThresholdShare},
+	NftDbSeal, ShareIndex,
+};
+
+lazy_static! {
+	static ref NFT_SECRET_SHARING_CONFIG: SgxRwLock<NftSecretSharingConfig> = Default::default();
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NftSecretSharingConfig {
+	/// Number of shares needed to reconstruct a secret. 0 disables threshold sharing.
+	pub threshold: u8,
+	/// The workers have been registered without remote attestation.
+	pub skip_ra: bool,
+}
+
+#[no_mangle]
+pub extern "C" fn init_nft_secret_sharing(threshold: u8, skip_ra: c_int) -> sgx_status_t {
+	let mut config = match NFT_SECRET_SHARING_CONFIG.write() {
+		Ok(config) => config,
+		Err(_) => return Error::MutexAccess.into(),
+	};
+	*config = NftSecretSharingConfig { threshold, skip_ra: skip_ra == 1 };
+
+	if threshold > 0 {
+		info!("NFT secrets are shared among the registered workers with threshold {}", threshold);
+	}
+	sgx_status_t::SGX_SUCCESS
+}
+
+pub fn sharing_config() -> Result<NftSecretSharingConfig> {
+	Ok(*NFT_SECRET_SHARING_CONFIG.read().map_err(|_| Error::MutexAccess)?)
+}
+
+/// Returns the configuration if threshold sharing is enabled.
+pub fn threshold_sharing_config() -> Result<Option<NftSecretSharingConfig>> {
+	Ok(Some(sharing_config()?).filter(|c| c.threshold > 0))
+}
+
+/// Splits the secret written by `writer` into one share per registered worker and hands the
+/// shares out.
+///
+/// Fails if fewer than `threshold` workers, including this one, stored their share, or with
+/// `WriteDenied` if the share has been written in a mode that a write in `mode` may not
+/// overwrite.
+#[allow(clippy::too_many_arguments)]
+pub fn store_secret(
+	config: &NftSecretSharingConfig,
+	header: &Header,
+	nft_id: u32,
+	share_index: ShareIndex,
+	secret: &[u8],
+	writer: &AccountId,
+	unlock: Option<UnlockCondition>,
+	mode: NftSecretWriteMode,
+) -> Result<NftSecretReplication> {
+	let workers = registered_workers(header)?;
+	if workers.len() > u8::MAX as usize {
+		return Err(Error::Other("too many registered workers for threshold sharing".into()))
+	}
+	// Every worker checks the mode again when it stores its share.
+	{
+		let _nft_db_lock = EnclaveLock::read_nft_db()?;
+		NftSecretMetadataSeal::check_write(nft_id, share_index, mode)?;
+	}
+
+	let mut rng = SgxRng::new()?;
+	let mut tag = [0u8; 16];
+	rng.fill_bytes(&mut tag);
+	let shares =
+		shamir::split(secret, config.threshold, workers.len() as u8, |buf| rng.fill_bytes(buf))?;
+
+	let self_account = self_account()?;
+	let mut missed_by = Vec::new();
+	for (worker, share) in workers.iter().zip(shares.into_iter()) {
+		let share =
+			ThresholdShare { block_number: header.number, tag, threshold: config.threshold, share };
+		let result = if worker.pubkey == self_account {
+			store_share(nft_id, share_index, share, writer.clone(), unlock, mode)
+		} else {
+			let request = MuRaRequest::StoreNftSecretShare {
+				nft_id,
+				share_index,
+				share,
+				writer: writer.clone(),
+				unlock,
+				mode,
+			};
+			send_to_worker(config, worker, &request).and_then(|response| match response {
+				MuRaResponse::Done => Ok(()),
+				_ => Err(Error::Other("worker failed to store share".into())),
+			})
+		};
+		if let Err(e) = result {
+			warn!("Share of NFT {} not stored by {:?}: {:?}", nft_id, worker.pubkey, e);
+			missed_by.push(worker.pubkey.clone());
+		}
+	}
+
+	let stored = workers.len() - missed_by.len();
+	if stored < config.threshold as usize {
+		return Err(Error::Other(
+			format!("only {} workers stored a share of NFT {}", stored, nft_id).into(),
+		))
+	}
+	Ok(NftSecretReplication { missed_by })
+}
+
+/// Reconstructs the secret from the shares of the registered workers.
+///
+/// Returns `None` if no worker holds a share of the secret.
+pub fn retrieve_secret(
+	config: &NftSecretSharingConfig,
+	header: &Header,
+	nft_id: u32,
+	share_index: ShareIndex,
+) -> Result<Option<Vec<u8>>> {
+	let mut secrets = retrieve_secrets(config, header, &[(nft_id, share_index)])?;
+	secrets.pop().unwrap_or(Ok(None))
+}
+
+/// Reconstructs the given secrets from the shares of the registered workers, in order. Every
+/// worker is queried once for all secrets.
+///
+/// Yields `None` for a secret that no worker holds a share of.
+pub fn retrieve_secrets(
+	config: &NftSecretSharingConfig,
+	header: &Header,
+	secrets: &[(u32, ShareIndex)],
+) -> Result<Vec<Result<Option<Vec<u8>>>>> {
+	let self_account = self_account()?;
+	let mut shares: Vec<Vec<ThresholdShare>> = secrets.iter().map(|_| Vec::new()).collect();
+
+	// All workers are queried, such that the newest sharing is found even if some workers
+	// hold the share of an outdated one.
+	for worker in registered_workers(header)?.iter() {
+		let worker_shares = if worker.pubkey == self_account {
+			Ok(get_shares(secrets))
+		} else {
+			let request = MuRaRequest::RetrieveNftSecretShares(secrets.to_vec());
+			match send_to_worker(config, worker, &request) {
+				Ok(MuRaResponse::NftSecretShares(s)) if s.len() == secrets.len() => Ok(s),
+				Ok(_) => Err(Error::Other("worker failed to return shares".into())),
+				Err(e) => Err(e),
+			}
+		};
+		match worker_shares {
+			Ok(worker_shares) => shares
+				.iter_mut()
+				.zip(worker_shares.into_iter())
+				.for_each(|(shares, share)| shares.extend(share)),
+			Err(e) => warn!("Shares not retrieved from {:?}: {:?}", worker.pubkey, e),
+		}
+	}
+
+	Ok(shares
+		.into_iter()
+		.map(|shares| {
+			if shares.is_empty() {
+				return Ok(None)
+			}
+			Ok(Some(shamir::combine_newest(&shares)?))
+		})
+		.collect())
+}
+
+/// Removes the shares of all secrets of the NFT from all registered workers. The share of this
+/// worker has to be removed by the caller.
+///
+/// Fails unless at least `n - k + 1` of the `n` registered workers, including this one, removed
+/// their shares. The fewer than `k` shares left behind cannot reconstruct the secret.
+pub fn remove_secrets(
+	config: &NftSecretSharingConfig,
+	header: &Header,
+	nft_id: u32,
+) -> Result<NftSecretReplication> {
+	let self_account = self_account()?;
+	let workers = registered_workers(header)?;
+	let mut missed_by = Vec::new();
+	for worker in workers.iter().filter(|w| w.pubkey != self_account) {
+		let request = MuRaRequest::RemoveNftSecretShares { nft_id, block_number: header.number };
+		match send_to_worker(config, worker, &request) {
+			Ok(MuRaResponse::Done) => continue,
+			Ok(_) => warn!("Worker {:?} failed to remove shares of NFT {}", worker.pubkey, nft_id),
+			Err(e) => warn!("Shares of NFT {} not removed by {:?}: {:?}", nft_id, worker.pubkey, e),
+		}
+		missed_by.push(worker.pubkey.clone());
+	}
+
+	let removed = workers.len() - missed_by.len();
+	let required = (workers.len() + 1).saturating_sub(config.threshold as usize);
+	if removed < required {
+		return Err(Error::Other(
+			format!(
+				"only {} workers removed their shares of NFT {}, {} required",
+				removed, nft_id, required
+			)
+			.into(),
+		))
+	}
+	Ok(NftSecretReplication { missed_by })
+}
+
+/// Serves a request of a fellow worker, that has been received over MU-RA.
+pub fn handle_worker_request(request: MuRaRequest) -> MuRaResponse {
+	let result = match request {
+		MuRaRequest::StoreNftSecretShare { nft_id, share_index, share, writer, unlock, mode } =>
+			store_share(nft_id, share_index, share, writer, unlock, mode)
+				.map(|_| MuRaResponse::Done),
+		MuRaRequest::RetrieveNftSecretShares(_)
+			if !version_anchor::is_latest_anchor_confirmed() =>
+			Err(Error::Other("latest version anchor is not on chain yet".into())),
+		MuRaRequest::RetrieveNftSecretShares(secrets) =>
+			Ok(MuRaResponse::NftSecretShares(get_shares(&secrets))),
+		MuRaRequest::RemoveNftSecretShares { nft_id, block_number } =>
+			remove_shares(nft_id, block_number).map(|_| MuRaResponse::Done),
+		_ => Err(Error::Other("unexpected worker request".into())),
+	};
+	result.unwrap_or_else(|e| {
+		error!("Failed to serve worker request: {:?}", e);
+		MuRaResponse::Failed
+	})
+}
+
+/// Seals the share, unless a share of a newer sharing is already present or the NFT has been
+/// removed since.
+fn store_share(
+	nft_id: u32,
+	share_index: ShareIndex,
+	share: ThresholdShare,
+	writer: AccountId,
+	unlock: Option<UnlockCondition>,
+	mode: NftSecretWriteMode,
+) -> Result<()> {
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	NftSecretMetadataSeal::check_write(nft_id, share_index, mode)?;
+	let removal = NftDbSeal::unseal_versions()?.removal_version(nft_id);
+	if removal.map_or(false, |removal| removal.block_number >= share.block_number) {
+		return Err(Error::Other("NFT has been removed by a newer write".into()))
+	}
+	if let Some(current) = unseal_share(nft_id, share_index)? {
+		if current.block_number > share.block_number {
+			return Err(Error::Other("share of a newer sharing is present".into()))
+		}
+	}
+	// Every share is as long as the secret itself.
+	let write = ShareWrite {
+		share_index,
+		byte_length: share.share.y.len() as u32,
+		block_number: share.block_number,
+		writer,
+		unlock,
+		mode,
+	};
+	// The unlock condition is recorded first, such that the share is never sealed without it.
+	NftSecretMetadataSeal::record_write(nft_id, write)?;
+	NftDbSeal::upsert(nft_id, share_index, share.encode())?;
+	Ok(())
+}
+
+/// Returns the shares of the given secrets, withholding those that are time-locked or fail to
+/// unseal.
+fn get_shares(secrets: &[(u32, ShareIndex)]) -> Vec<Option<ThresholdShare>> {
+	secrets
+		.iter()
+		.map(|(nft_id, share_index)| {
+			get_share(*nft_id, *share_index).unwrap_or_else(|e| {
+				warn!("Share {} of NFT {} withheld: {:?}", share_index, nft_id, e);
+				None
+			})
+		})
+		.collect()
+}
+
+/// Returns the share, unless it is time-locked. A locked share is withheld, such that the secret
+/// cannot be reconstructed before it is released.
+fn get_share(nft_id: u32, share_index: ShareIndex) -> Result<Option<ThresholdShare>> {
+	let _nft_db_lock = EnclaveLock::read_nft_db()?;
+	if let Some(unlock) = nft_secret_unlock::unlock_condition(nft_id, share_index)? {
+		if !nft_secret_unlock::is_met(&unlock)? {
+			return Err(Error::Other(format!("share of NFT {} is time-locked", nft_id).into()))
+		}
+	}
+	unseal_share(nft_id, share_index)
+}
+
+fn remove_shares(nft_id: u32, block_number: u32) -> Result<()> {
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	match NftDbSeal::remove(nft_id, block_number) {
+		Ok(()) | Err(NftError::NftNotFound) => Ok(()),
+		Err(e) => Err(e.into()),
+	}
+}
+
+fn unseal_share(nft_id: u32, share_index: ShareIndex) -> Result<Option<ThresholdShare>> {
+	match NftDbSeal::get(nft_id, share_index) {
+		Ok(encoded) => Ok(Some(ThresholdShare::decode(&mut encoded.as_slice())?)),
+		Err(NftError::NftNotFound) => Ok(None),
+		Err(e) => Err(e.into()),
+	}
+}
+
+/// Registered workers that run this enclave, in the order of the teerex registry.
+pub fn registered_workers(header: &Header) -> Result<Vec<Enclave>> {
+	let ocall_api = OcallApi;
+	let count: u64 = ocall_api
+		.get_storage_verified(TeeRexStorage::enclave_count(), header)?
+		.into_tuple()
+		.1
+		.unwrap_or_default();
+
+	let keys: Vec<Vec<u8>> = (1..=count).map(TeeRexStorage::enclave).collect();
+	let entries = ocall_api.get_multiple_storages_verified::<_, Enclave>(keys.clone(), header)?;
+	if entries.len() != keys.len()
+		|| entries.iter().zip(keys.iter()).any(|(e, k)| e.key() != k.as_slice())
+	{
+		return Err(Error::Other("unexpected enclave registry entries".into()))
+	}
+
+	let mr_enclave = ocall_api.get_mrenclave_of_self()?.m;
+	Ok(entries
+		.into_iter()
+		.filter_map(|entry| entry.into_tuple().1)
+		.filter(|enclave: &Enclave| enclave.mr_enclave == mr_enclave)
+		.collect())
+}
+
+pub fn self_account() -> Result<AccountId> {
+	Ok(AccountId::from(Ed25519Seal::unseal()?.public().0))
+}
+
+pub fn send_to_worker(
+	config: &NftSecretSharingConfig,
+	worker: &Enclave,
+	request: &MuRaRequest,
+) -> Result<MuRaResponse> {
+	let url = str::from_utf8(&worker.url).map_err(|e| Error::Other(e.into()))?;
+	let socket_fd = OcallApi.get_mu_ra_socket(url)?;
+	send_mu_ra_request(
+		socket_fd,
+		sgx_quote_sign_type_t::SGX_LINKABLE_SIGNATURE,
+		OcallApi,
+		config.skip_ra,
+		request,
+	)
+}
@@ -0,0 +1,73 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Time-locked release of NFT secrets.
+//!
+//! A secret share can be stored with an `UnlockCondition`, which is recorded in the sealed
+//! metadata of the share. Until the condition is met, the share is neither returned to a client
+//! nor, under threshold sharing, handed out to a fellow worker. Hence the secret cannot be
+//! reconstructed early from the shares of the workers either.
+//!
+//! The condition is checked against the latest finalized header of the light client and the
+//! timestamp in the state of that block, which is read with a storage proof.
+
+use crate::{
+	error::{Error, Result},
+	ocall::OcallApi,
+	EnclaveValidatorAccessor,
+};
+use itc_parentchain::light_client::{concurrent_access::ValidatorAccess, LightClientState};
+use itp_storage::storage_value_key;
+use itp_storage_verifier::GetStorageVerified;
+use itp_types::{Header, UnlockCondition};
+use ternoa_sgx_nft::{metadata::NftSecretMetadataSeal, ShareIndex};
+
+/// Unlock condition of the share, if it has been stored time-locked.
+///
+/// The NFT db has to be locked by the caller.
+pub fn unlock_condition(nft_id: u32, share_index: ShareIndex) -> Result<Option<UnlockCondition>> {
+	Ok(NftSecretMetadataSeal::get(nft_id)?
+		.and_then(|metadata| metadata.share_write(share_index).and_then(|write| write.unlock)))
+}
+
+/// Whether the condition is met at the latest finalized parentchain block.
+pub fn is_met(condition: &UnlockCondition) -> Result<bool> {
+	is_met_at(condition, &latest_finalized_header()?)
+}
+
+/// Whether the condition is met at the given parentchain block.
+pub fn is_met_at(condition: &UnlockCondition, header: &Header) -> Result<bool> {
+	let timestamp = match condition {
+		UnlockCondition::BlockNumber(_) => 0,
+		UnlockCondition::Timestamp(_) => verified_timestamp(header)?,
+	};
+	Ok(condition.is_met(header.number, timestamp))
+}
+
+fn latest_finalized_header() -> Result<Header> {
+	Ok(EnclaveValidatorAccessor::default()
+		.execute_on_validator(|v| v.latest_finalized_header(v.num_relays()))?)
+}
+
+/// Timestamp of the block in milliseconds, as kept by the `Timestamp` pallet.
+fn verified_timestamp(header: &Header) -> Result<u64> {
+	OcallApi
+		.get_storage_verified(storage_value_key("Timestamp", "Now"), header)?
+		.into_tuple()
+		.1
+		.ok_or_else(|| Error::Other("no timestamp in parentchain storage".into()))
+}
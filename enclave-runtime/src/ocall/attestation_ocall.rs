@@ -63,6 +63,25 @@ impl EnclaveAttestationOCallApi for OcallApi {
 		Ok(ias_sock)
 	}
 
+	fn get_mu_ra_socket(&self, worker_url: &str) -> SgxResult<i32> {
+		let mut rt: sgx_status_t = sgx_status_t::SGX_ERROR_UNEXPECTED;
+		let mut mu_ra_sock: i32 = 0;
+
+		let res = unsafe {
+			ffi::ocall_get_mu_ra_socket(
+				&mut rt as *mut sgx_status_t,
+				worker_url.as_ptr(),
+				worker_url.len() as u32,
+				&mut mu_ra_sock as *mut i32,
+			)
+		};
+
+		ensure!(res == sgx_status_t::SGX_SUCCESS, res);
+		ensure!(rt == sgx_status_t::SGX_SUCCESS, rt);
+
+		Ok(mu_ra_sock)
+	}
+
 	fn get_quote(
 		&self,
 		sig_rl: Vec<u8>,
@@ -29,6 +29,13 @@ extern "C" {
 
 	pub fn ocall_get_ias_socket(ret_val: *mut sgx_status_t, ret_fd: *mut i32) -> sgx_status_t;
 
+	pub fn ocall_get_mu_ra_socket(
+		ret_val: *mut sgx_status_t,
+		worker_url: *const u8,
+		worker_url_size: u32,
+		ret_fd: *mut i32,
+	) -> sgx_status_t;
+
 	pub fn ocall_get_quote(
 		ret_val: *mut sgx_status_t,
 		p_sigrl: *const u8,
@@ -15,22 +15,53 @@
 
 */
 
-use crate::{EnclaveValidatorAccessor, OcallApi};
+use crate::{
+	error::Error as EnclaveError,
+	nft_keys, nft_secret_commitment, nft_secret_replication, nft_secret_sharing, nft_secret_unlock,
+	sync::{EnclaveLock, NftDbRwLock, RequestNonceRwLock},
+	version_anchor, EnclaveValidatorAccessor, OcallApi,
+};
 use codec::{Decode, Encode};
 use core::result::Result;
 use itc_parentchain::light_client::{concurrent_access::ValidatorAccess, LightClientState};
-use itp_nfts_storage::{NFTsStorage, NFTsStorageKeys};
+use itp_nfts_storage::{CapsulesStorage, CapsulesStorageKeys, NFTsStorage, NFTsStorageKeys};
+use itp_ocall_api::EnclaveAttestationOCallApi;
 use itp_primitives_cache::{GetPrimitives, GLOBAL_PRIMITIVES_CACHE};
-use itp_sgx_crypto::Rsa3072Seal;
+use itp_settings::enclave::MAX_NFT_SECRET_BATCH_SIZE;
+use itp_sgx_crypto::{Ed25519Seal, Rsa3072Seal, ShieldingCrypto};
 use itp_sgx_io::SealedIO;
 use itp_storage_verifier::GetStorageVerified;
 use itp_types::{
-	AccountId, DirectRequestStatus, NFTData, RetrieveNftSecretRequest, RpcReturnValue,
-	SignedRequest, StoreNftSecretRequest,
+	AccountId, CapsuleData, DecryptWithNftKeyRequest, DirectRequestStatus, EncryptedNftSecret,
+	GenerateNftKeyRequest, GetNftSecretCommitmentRequest, GrantNftAccessRequest, Header,
+	ListMyNftSecretsRequest, ListNftSecretSharesRequest, NFTData, NFTSeriesData,
+	NftSecretAccessDenial, NftSecretBatchResult, NftSecretInfo, NftSecretPolicy,
+	NftSecretReplication, NftSecretWriteMode, RegisterRecipientKeyRequest, RemoveNftSecretRequest,
+	RequestContext, RequestNonce, RetrieveCapsuleKeyRequest, RetrieveNftSecretHandoverRequest,
+	RetrieveNftSecretRequest, RetrieveNftSecretsRequest, RetrieveNftSeriesSecretRequest,
+	RevokeNftAccessRequest, RpcReturnValue, SignWithNftKeyRequest, SignableRequest, SignedRequest,
+	StoreCapsuleKeyRequest, StoreNftSecretRequest, StoreNftSecretsRequest,
+	StoreNftSeriesSecretRequest, StoreShieldedNftSecretRequest, UnlockCondition,
+	NFT_SECRETS_UNAVAILABLE_ERROR_CODE,
 };
-use jsonrpc_core::{serde_json::json, Error, IoHandler, Params, Value};
+use jsonrpc_core::{serde_json::json, Error, ErrorCode, IoHandler, Params, Value};
+use log::*;
+use sgx_crypto_helper::rsa3072::Rsa3072PubKey;
+use sgx_rand::{os::SgxRng, Rng};
+use sgx_tcrypto::rsgx_rijndael128GCM_encrypt;
+use sp_core::{sr25519::Public, Pair};
 use std::{borrow::ToOwned, format, str, string::String, sync::Arc, vec::Vec};
-use ternoa_sgx_nft::NftDbSeal;
+use ternoa_sgx_nft::{
+	acl::{NftAcl, NftAclSeal},
+	capsule::{CapsuleKey, CapsuleKeySeal},
+	error::Error as NftError,
+	handover::NftOwnerRecordSeal,
+	metadata::NftSecretMetadataSeal,
+	recipient_key::RecipientKey,
+	request_nonce::{RequestNonceDb, RequestNonceDbSeal},
+	series::{NftSeriesSecretSeal, SeriesSecret},
+	NftDbSeal,
+};
 
 fn compute_encoded_return_error(error_msg: &str) -> Vec<u8> {
 	RpcReturnValue::from_error_message(error_msg).encode()
@@ -50,63 +81,548 @@ pub fn public_api_rpc_handler() -> IoHandler {
 	let mut io = IoHandler::new();
 
 	// nft_storeSecret
-	let nft_store_secret_name: &str = "nft_storeSecret";
+	let nft_store_secret_name: &str = StoreNftSecretRequest::METHOD;
 	io.add_sync_method(nft_store_secret_name, |params: Params| {
-		let encoded_params = params.parse::<Vec<u8>>()?;
-		let signed_req =
-			SignedRequest::<StoreNftSecretRequest>::decode(&mut encoded_params.as_slice())
-				.map_err(|_| Error::invalid_params("failed to decode signed_request"))?;
+		let (req, signer, nonce) = verify_signed_request::<StoreNftSecretRequest>(params)?;
 
-		let req = signed_req
-			.get_request()
-			.ok_or(Error::invalid_params("invalid request signature"))?;
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_write_access(req.nft_id, req.share_index, req.mode, &data, &signer)?;
+		nonce.consume()?;
 
-		let owner = get_verified_nft_owner(req.nft_id)?;
+		let replication = store_nft_secret(
+			req.nft_id,
+			req.share_index,
+			req.secret,
+			signer,
+			data.owner.into(),
+			req.unlock,
+			req.mode,
+		)?;
 
-		if owner != signed_req.signer.into() {
-			return Err(Error::invalid_params(format!(
-				"sender does not own the nft with id {}",
-				&req.nft_id
-			)))
-		}
+		Ok(replication.encode().into())
+	});
+
+	// nft_storeSecrets
+	let nft_store_secrets_name: &str = StoreNftSecretsRequest::METHOD;
+	io.add_sync_method(nft_store_secrets_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<StoreNftSecretsRequest>(params)?;
+		check_batch_size(req.secrets.len())?;
 
-		let mut db = NftDbSeal::unseal().map_err(|_| Error::internal_error())?;
+		let nft_ids: Vec<u32> = req.secrets.iter().map(|secret| secret.nft_id).collect();
+		let nft_data = get_verified_nft_data_batch(&nft_ids)?;
+		nonce.consume()?;
 
-		db.upsert_sorted(req.nft_id, req.secret);
+		let results: Vec<NftSecretBatchResult<NftSecretReplication>> = req
+			.secrets
+			.into_iter()
+			.zip(nft_data.into_iter())
+			.map(|(secret, data)| {
+				let data = data.ok_or_else(|| nft_not_found(secret.nft_id))?;
+				check_write_access(secret.nft_id, secret.share_index, secret.mode, &data, &signer)?;
+				store_nft_secret(
+					secret.nft_id,
+					secret.share_index,
+					secret.secret,
+					signer.clone(),
+					data.owner.into(),
+					secret.unlock,
+					secret.mode,
+				)
+			})
+			.map(|result| result.map_err(|e| e.message))
+			.collect();
 
-		NftDbSeal::seal(db).map_err(|_| Error::internal_error())?;
+		Ok(results.encode().into())
+	});
+
+	// nft_storeShieldedSecret
+	let nft_store_shielded_secret_name: &str = StoreShieldedNftSecretRequest::METHOD;
+	io.add_sync_method(nft_store_shielded_secret_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<StoreShieldedNftSecretRequest>(params)?;
+
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_write_access(req.nft_id, req.share_index, req.mode, &data, &signer)?;
+
+		let secret = Rsa3072Seal::unseal()
+			.map_err(|_| Error::internal_error())?
+			.decrypt(&req.encrypted_secret)
+			.map_err(|_| Error::invalid_params("failed to decrypt secret"))?;
+		nonce.consume()?;
+
+		let replication = store_nft_secret(
+			req.nft_id,
+			req.share_index,
+			secret,
+			signer,
+			data.owner.into(),
+			req.unlock,
+			req.mode,
+		)?;
 
-		Ok(Value::Null)
+		Ok(replication.encode().into())
 	});
 
 	// nft_retrieveSecret
-	let nft_retrieve_secret_name: &str = "nft_retrieveSecret";
+	let nft_retrieve_secret_name: &str = RetrieveNftSecretRequest::METHOD;
 	io.add_sync_method(nft_retrieve_secret_name, |params: Params| {
-		let encoded_params = params.parse::<Vec<u8>>()?;
-		let signed_req =
-			SignedRequest::<RetrieveNftSecretRequest>::decode(&mut encoded_params.as_slice())
-				.map_err(|_| Error::invalid_params("failed to decode signed_request"))?;
+		let (req, signer, nonce) = verify_signed_request::<RetrieveNftSecretRequest>(params)?;
+		check_version_anchor_confirmed()?;
 
-		let req = signed_req
-			.get_request()
-			.ok_or(Error::invalid_params("invalid request signature"))?;
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_retrieve_access(&req, &data, &signer)?;
+		nonce.consume()?;
 
-		let owner = get_verified_nft_owner(req.nft_id)?;
+		Ok(retrieve_nft_secret_as(req)?.into())
+	});
+
+	// nft_retrieveSecrets
+	let nft_retrieve_secrets_name: &str = RetrieveNftSecretsRequest::METHOD;
+	io.add_sync_method(nft_retrieve_secrets_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<RetrieveNftSecretsRequest>(params)?;
+		check_version_anchor_confirmed()?;
+		check_batch_size(req.secrets.len())?;
 
-		if owner != signed_req.signer.into() {
+		let nft_ids: Vec<u32> = req.secrets.iter().map(|secret| secret.nft_id).collect();
+		let nft_data = get_verified_nft_data_batch(&nft_ids)?;
+		nonce.consume()?;
+
+		let permitted: Vec<Result<RetrieveNftSecretRequest, Error>> = req
+			.secrets
+			.into_iter()
+			.zip(nft_data.into_iter())
+			.map(|(secret, data)| {
+				let data = data.ok_or_else(|| nft_not_found(secret.nft_id))?;
+				check_retrieve_access(&secret, &data, &signer)?;
+				Ok(secret)
+			})
+			.collect();
+		let results: Vec<NftSecretBatchResult<Vec<u8>>> = retrieve_nft_secrets_as(permitted)?
+			.into_iter()
+			.map(|result| result.map_err(|e| e.message))
+			.collect();
+
+		Ok(results.encode().into())
+	});
+
+	// nft_storeSeriesSecret
+	let nft_store_series_secret_name: &str = StoreNftSeriesSecretRequest::METHOD;
+	io.add_sync_method(nft_store_series_secret_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<StoreNftSeriesSecretRequest>(params)?;
+
+		let series = get_verified_nft_series_data(&req.series_id)?;
+		let creator: AccountId = series.owner.into();
+
+		if creator != signer {
 			return Err(Error::invalid_params(format!(
-				"sender does not own the nft with id {}",
-				&req.nft_id
+				"sender did not create the nft series with id '{}'",
+				&req.series_id
 			)))
 		}
+		nonce.consume()?;
+
+		let replication = store_nft_series_secret(req.series_id, req.secret, signer)?;
+
+		Ok(replication.encode().into())
+	});
+
+	// nft_retrieveSeriesSecret
+	let nft_retrieve_series_secret_name: &str = RetrieveNftSeriesSecretRequest::METHOD;
+	io.add_sync_method(nft_retrieve_series_secret_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<RetrieveNftSeriesSecretRequest>(params)?;
+		check_version_anchor_confirmed()?;
+
+		// The verified data of the NFT proves both its ownership and its series membership.
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_owner(req.nft_id, &data, &signer)?;
+		if data.series_id != req.series_id {
+			return Err(Error::invalid_params(format!(
+				"nft with id {} is not a member of the series with id '{}'",
+				&req.nft_id, &req.series_id
+			)))
+		}
+		nonce.consume()?;
+
+		let secret = {
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+			NftSeriesSecretSeal::get(req.series_id.as_bytes())
+				.map_err(|_| Error::internal_error())?
+		}
+		.ok_or_else(|| {
+			Error::invalid_params(format!(
+				"no secret stored for the series with id '{}'",
+				&req.series_id
+			))
+		})?
+		.into_secret();
+
+		match req.recipient_key {
+			Some(recipient_key) => {
+				let aad = req.series_id.encode();
+				Ok(encrypt_nft_secret(&aad, &secret, &recipient_key)?.encode().into())
+			},
+			None => Ok(secret.into()),
+		}
+	});
+
+	// capsule_storeKey
+	let capsule_store_key_name: &str = StoreCapsuleKeyRequest::METHOD;
+	io.add_sync_method(capsule_store_key_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<StoreCapsuleKeyRequest>(params)?;
+
+		check_capsule_access(req.nft_id, &signer)?;
+		nonce.consume()?;
+
+		let replication = store_capsule_key(req.nft_id, req.key)?;
+
+		Ok(replication.encode().into())
+	});
+
+	// capsule_retrieveKey
+	let capsule_retrieve_key_name: &str = RetrieveCapsuleKeyRequest::METHOD;
+	io.add_sync_method(capsule_retrieve_key_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<RetrieveCapsuleKeyRequest>(params)?;
+		check_version_anchor_confirmed()?;
+
+		check_capsule_access(req.nft_id, &signer)?;
+		nonce.consume()?;
+
+		let key = {
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+			CapsuleKeySeal::get(req.nft_id).map_err(|_| Error::internal_error())?
+		}
+		.ok_or_else(|| {
+			Error::invalid_params(format!(
+				"no key stored for the capsule of NFT with id '{}'",
+				req.nft_id
+			))
+		})?
+		.into_key();
+
+		match req.recipient_key {
+			Some(recipient_key) => {
+				let aad = RetrieveCapsuleKeyRequest::aad(req.nft_id);
+				Ok(encrypt_nft_secret(&aad, &key, &recipient_key)?.encode().into())
+			},
+			None => Ok(key.into()),
+		}
+	});
+
+	// nft_generateKey
+	let nft_generate_key_name: &str = GenerateNftKeyRequest::METHOD;
+	io.add_sync_method(nft_generate_key_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<GenerateNftKeyRequest>(params)?;
+
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_owner(req.nft_id, &data, &signer)?;
+		nonce.consume()?;
+
+		let key_pair =
+			nft_keys::derive_key_pair(req.nft_id).map_err(|_| Error::internal_error())?;
+
+		Ok(key_pair.public_key().encode().into())
+	});
+
+	// nft_sign
+	let nft_sign_name: &str = SignWithNftKeyRequest::METHOD;
+	io.add_sync_method(nft_sign_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<SignWithNftKeyRequest>(params)?;
+
+		check_nft_key_access(req.nft_id, &signer)?;
+		nonce.consume()?;
+
+		let key_pair =
+			nft_keys::derive_key_pair(req.nft_id).map_err(|_| Error::internal_error())?;
+		let signature = key_pair.sign(&req.message).map_err(|_| Error::internal_error())?;
+
+		Ok(signature.encode().into())
+	});
+
+	// nft_decrypt
+	let nft_decrypt_name: &str = DecryptWithNftKeyRequest::METHOD;
+	io.add_sync_method(nft_decrypt_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<DecryptWithNftKeyRequest>(params)?;
+
+		check_nft_key_access(req.nft_id, &signer)?;
+		nonce.consume()?;
+
+		let key_pair =
+			nft_keys::derive_key_pair(req.nft_id).map_err(|_| Error::internal_error())?;
+		let message = key_pair.decrypt(req.nft_id, &req.ciphertext).map_err(|_| {
+			Error::invalid_params(format!(
+				"failed to decrypt the ciphertext with the key of NFT with id '{}'",
+				req.nft_id
+			))
+		})?;
+
+		match req.recipient_key {
+			Some(recipient_key) => {
+				let aad = DecryptWithNftKeyRequest::aad(req.nft_id);
+				Ok(encrypt_nft_secret(&aad, &message, &recipient_key)?.encode().into())
+			},
+			None => Ok(message.into()),
+		}
+	});
+
+	// nft_registerRecipientKey
+	let nft_register_recipient_key_name: &str = RegisterRecipientKeyRequest::METHOD;
+	io.add_sync_method(nft_register_recipient_key_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<RegisterRecipientKeyRequest>(params)?;
+
+		// Secrets are encrypted to the key long after its registration, hence reject it now.
+		serde_json::from_slice::<Rsa3072PubKey>(&req.key)
+			.map_err(|_| Error::invalid_params("failed to decode recipient key"))?;
+		nonce.consume()?;
+
+		let replication = store_recipient_key(signer, req.key)?;
+
+		Ok(replication.encode().into())
+	});
+
+	// nft_retrieveSecretHandover
+	let nft_retrieve_secret_handover_name: &str = RetrieveNftSecretHandoverRequest::METHOD;
+	io.add_sync_method(nft_retrieve_secret_handover_name, |params: Params| {
+		let (req, signer, nonce) =
+			verify_signed_request::<RetrieveNftSecretHandoverRequest>(params)?;
+		check_version_anchor_confirmed()?;
+
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_owner(req.nft_id, &data, &signer)?;
+		nonce.consume()?;
+
+		let handover = {
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+			NftOwnerRecordSeal::get(req.nft_id).map_err(|_| Error::internal_error())?
+		}
+		.and_then(|record| record.into_handover())
+		// The block importer may not have observed the latest transfer yet.
+		.filter(|handover| handover.owner == signer)
+		.ok_or_else(|| {
+			Error::invalid_params(format!("no secret handover for NFT with id '{}'", req.nft_id))
+		})?;
 
-		let mut db = NftDbSeal::unseal().map_err(|_| Error::internal_error())?;
+		Ok(handover.encode().into())
+	});
+
+	// nft_getSecretCommitment
+	let nft_get_secret_commitment_name: &str = GetNftSecretCommitmentRequest::METHOD;
+	io.add_sync_method(nft_get_secret_commitment_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<GetNftSecretCommitmentRequest>(params)?;
 
-		let secret = db.get(req.nft_id).map_err(|_| {
-			Error::invalid_params(format!("no secret stored for NFT with id '{}'", req.nft_id))
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_owner(req.nft_id, &data, &signer)?;
+		{
+			// The salt of the proof lets anyone check guesses of the share, so it is withheld too.
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+			check_unlocked(req.nft_id, req.share_index)?;
+		}
+		nonce.consume()?;
+
+		let secret = retrieve_nft_secret(req.nft_id, req.share_index)?.ok_or_else(|| {
+			Error::invalid_params(format!(
+				"no secret share {} stored for NFT with id '{}'",
+				req.share_index, req.nft_id
+			))
 		})?;
 
-		Ok(secret.into())
+		let proof = nft_secret_commitment::commitment_proof(req.nft_id, req.share_index, &secret)
+			.map_err(|_| Error::internal_error())?;
+
+		Ok(proof.encode().into())
+	});
+
+	// nft_listSecretShares
+	let nft_list_secret_shares_name: &str = ListNftSecretSharesRequest::METHOD;
+	io.add_sync_method(nft_list_secret_shares_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<ListNftSecretSharesRequest>(params)?;
+
+		let owner = get_verified_nft_owner(req.nft_id)?;
+
+		let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+		check_read_access(req.nft_id, &owner, &signer)?;
+		nonce.consume()?;
+
+		let shares = NftDbSeal::shares(req.nft_id).map_err(|_| Error::internal_error())?;
+
+		Ok(shares.into())
+	});
+
+	// nft_listMySecrets
+	let nft_list_my_secrets_name: &str = ListMyNftSecretsRequest::METHOD;
+	io.add_sync_method(nft_list_my_secrets_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<ListMyNftSecretsRequest>(params)?;
+		check_batch_size(req.nft_ids.len())?;
+		nonce.consume()?;
+
+		let mut nft_ids = {
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+			let index = NftDbSeal::unseal_index().map_err(|_| Error::internal_error())?;
+			req.nft_ids
+				.into_iter()
+				.filter(|nft_id| index.contains_nft(*nft_id))
+				.collect::<Vec<u32>>()
+		};
+		nft_ids.sort_unstable();
+		nft_ids.dedup();
+
+		let mut secrets: Vec<(u32, NftSecretInfo)> = Vec::new();
+		let nft_data = get_verified_nft_data_batch(&nft_ids)?;
+		for (nft_id, data) in nft_ids.into_iter().zip(nft_data.into_iter()) {
+			// Secrets of burned NFTs may not have been purged yet.
+			let owner: Option<AccountId> = data.map(|data| data.owner.into());
+			if owner.as_ref() == Some(&signer) {
+				secrets.push((nft_id, get_nft_secret_info(nft_id)?));
+			}
+		}
+
+		Ok(secrets.encode().into())
+	});
+
+	// nft_getSecretInfo
+	let nft_get_secret_info_name: &str = "nft_getSecretInfo";
+	io.add_sync_method(nft_get_secret_info_name, |params: Params| {
+		let encoded_params = params.parse::<Vec<u8>>()?;
+		let nft_id = match u32::decode(&mut encoded_params.as_slice()) {
+			Ok(nft_id) => nft_id,
+			Err(e) => {
+				let error_msg: String = format!("Could not decode nft id due to: {}", e);
+				return Ok(json!(compute_encoded_return_error(error_msg.as_str())))
+			},
+		};
+
+		let info = match get_nft_secret_info(nft_id) {
+			Ok(info) => info,
+			Err(e) => {
+				let error_msg: String = format!("Could not get secret info due to: {}", e.message);
+				return Ok(json!(compute_encoded_return_error(error_msg.as_str())))
+			},
+		};
+
+		let json_value = RpcReturnValue::new(info.encode(), false, DirectRequestStatus::Ok);
+		Ok(json!(json_value.encode()))
+	});
+
+	// nft_removeSecret
+	let nft_remove_secret_name: &str = RemoveNftSecretRequest::METHOD;
+	io.add_sync_method(nft_remove_secret_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<RemoveNftSecretRequest>(params)?;
+
+		let data = get_verified_nft_data(req.nft_id)?;
+		check_owner(req.nft_id, &data, &signer)?;
+
+		{
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+			check_removable_by_owner(req.nft_id)?;
+		}
+		nonce.consume()?;
+
+		let replication = remove_nft_secrets(req.nft_id)?;
+
+		Ok(replication.encode().into())
+	});
+
+	// nft_grantAccess
+	let nft_grant_access_name: &str = GrantNftAccessRequest::METHOD;
+	io.add_sync_method(nft_grant_access_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<GrantNftAccessRequest>(params)?;
+
+		let data = get_verified_nft_data(req.nft_id)?;
+		let owner = check_owner(req.nft_id, &data, &signer)?;
+		let peers = nft_secret_replication::peers(&get_latest_parentchain_header()?)
+			.map_err(|_| Error::internal_error())?;
+
+		let acl = {
+			let _nft_db_lock = EnclaveLock::write_nft_db().map_err(|_| Error::internal_error())?;
+			let index = NftDbSeal::unseal_index().map_err(|_| Error::internal_error())?;
+			if !index.contains_nft(req.nft_id) {
+				return Err(Error::invalid_params(format!(
+					"no secret stored for NFT with id '{}'",
+					req.nft_id
+				)))
+			}
+			nonce.consume()?;
+
+			// Grants of a previous owner are discarded.
+			let mut acl = match NftAclSeal::get(req.nft_id).map_err(|_| Error::internal_error())? {
+				Some(acl) => acl.for_owner(owner),
+				None => NftAcl::new(req.nft_id, owner),
+			};
+			acl.grant(req.grantee);
+			NftAclSeal::seal(acl.clone()).map_err(|_| Error::internal_error())?;
+			acl
+		};
+		let replication = nft_secret_replication::replicate_acl(&peers, acl);
+
+		Ok(replication.encode().into())
+	});
+
+	// nft_revokeAccess
+	let nft_revoke_access_name: &str = RevokeNftAccessRequest::METHOD;
+	io.add_sync_method(nft_revoke_access_name, |params: Params| {
+		let (req, signer, nonce) = verify_signed_request::<RevokeNftAccessRequest>(params)?;
+
+		let data = get_verified_nft_data(req.nft_id)?;
+		let owner = check_owner(req.nft_id, &data, &signer)?;
+		let peers = nft_secret_replication::peers(&get_latest_parentchain_header()?)
+			.map_err(|_| Error::internal_error())?;
+
+		let acl = {
+			let _nft_db_lock = EnclaveLock::write_nft_db().map_err(|_| Error::internal_error())?;
+			let mut acl = match NftAclSeal::get(req.nft_id).map_err(|_| Error::internal_error())? {
+				Some(acl) => acl.for_owner(owner),
+				None => NftAcl::new(req.nft_id, owner),
+			};
+			if !acl.revoke(&req.grantee) {
+				return Err(Error::invalid_params(format!(
+					"account has no read access to the nft with id {}",
+					&req.nft_id
+				)))
+			}
+			nonce.consume()?;
+			NftAclSeal::seal(acl.clone()).map_err(|_| Error::internal_error())?;
+			acl
+		};
+		let replication = nft_secret_replication::replicate_acl(&peers, acl);
+
+		Ok(replication.encode().into())
+	});
+
+	// author_getRequestNonce
+	let request_nonce_name: &str = "author_getRequestNonce";
+	io.add_sync_method(request_nonce_name, |params: Params| {
+		let encoded_params = params.parse::<Vec<u8>>()?;
+		let account = match Public::decode(&mut encoded_params.as_slice()) {
+			Ok(account) => account,
+			Err(e) => {
+				let error_msg: String = format!("Could not decode account due to: {}", e);
+				return Ok(json!(compute_encoded_return_error(error_msg.as_str())))
+			},
+		};
+
+		let nonce = match RequestNonceDbSeal::unseal() {
+			Ok(db) => db.next_nonce(&account),
+			Err(e) => {
+				let error_msg: String = format!("Could not get request nonce due to: {}", e);
+				return Ok(json!(compute_encoded_return_error(error_msg.as_str())))
+			},
+		};
+
+		let json_value = RpcReturnValue::new(nonce.encode(), false, DirectRequestStatus::Ok);
+		Ok(json!(json_value.encode()))
+	});
+
+	// author_getRequestContext
+	let request_context_name: &str = "author_getRequestContext";
+	io.add_sync_method(request_context_name, |_: Params| {
+		let context = match get_request_context() {
+			Ok(context) => context,
+			Err(e) => {
+				let error_msg: String = format!("Could not get request context due to: {}", e);
+				return Ok(json!(compute_encoded_return_error(error_msg.as_str())))
+			},
+		};
+
+		let json_value = RpcReturnValue::new(context.encode(), false, DirectRequestStatus::Ok);
+		Ok(json!(json_value.encode()))
 	});
 
 	// author_getShieldingKey
@@ -219,26 +735,675 @@ pub fn public_api_rpc_handler() -> IoHandler {
 	io
 }
 
-pub fn get_verified_nft_owner(nft_id: u32) -> Result<AccountId, Error> {
-	// Get last header from light client
+/// Decodes a `SignedRequest` from the rpc params and returns the request, its signer and its
+/// pending nonce.
+///
+/// Besides the signature, the expiry is checked against the light client's latest finalized
+/// header, and the nonce against the signer's next request nonce. The nonce is only consumed
+/// once the handler has authorized the request, see `PendingNonce`.
+fn verify_signed_request<T: SignableRequest + Decode>(
+	params: Params,
+) -> Result<(T, AccountId, PendingNonce), Error> {
+	let encoded_params = params.parse::<Vec<u8>>()?;
+	let signed_req = SignedRequest::<T>::decode(&mut encoded_params.as_slice())
+		.map_err(|_| Error::invalid_params("failed to decode signed_request"))?;
+
+	let context = get_request_context()?;
+	let req = signed_req
+		.get_request(&context)
+		.ok_or(Error::invalid_params("invalid request signature"))?;
+
+	let header = get_latest_parentchain_header()?;
+	if signed_req.expiry < header.number {
+		return Err(Error::invalid_params(format!(
+			"request expired at block {}, latest block is {}",
+			signed_req.expiry, header.number
+		)))
+	}
+
+	let nonce = PendingNonce { signer: signed_req.signer, nonce: signed_req.nonce };
+	{
+		let _nonce_lock =
+			EnclaveLock::write_request_nonce_db().map_err(|_| Error::internal_error())?;
+		let nonce_db = RequestNonceDbSeal::unseal().map_err(|_| Error::internal_error())?;
+		nonce.check(&nonce_db)?;
+	}
+
+	Ok((req, signed_req.signer.into(), nonce))
+}
+
+/// Nonce of a verified request that has not been consumed yet.
+///
+/// A handler consumes the nonce once it has authorized the request, right before executing it.
+/// Hence a request that is rejected by the ownership, policy or lock checks can be signed again
+/// with the same nonce, while a request that fails during its execution has consumed it.
+#[must_use = "the nonce has to be consumed before the request is executed"]
+struct PendingNonce {
+	signer: Public,
+	nonce: RequestNonce,
+}
+
+impl PendingNonce {
+	/// Consumes the nonce. Fails if a concurrent request with the same nonce consumed it first.
+	fn consume(self) -> Result<(), Error> {
+		let _nonce_lock =
+			EnclaveLock::write_request_nonce_db().map_err(|_| Error::internal_error())?;
+		let mut nonce_db = RequestNonceDbSeal::unseal().map_err(|_| Error::internal_error())?;
+		self.check(&nonce_db)?;
+		nonce_db
+			.use_nonce(self.signer, self.nonce)
+			.map_err(|_| Error::internal_error())?;
+		RequestNonceDbSeal::seal(nonce_db).map_err(|_| Error::internal_error())
+	}
+
+	fn check(&self, nonce_db: &RequestNonceDb) -> Result<(), Error> {
+		let expected_nonce = nonce_db.next_nonce(&self.signer);
+		if self.nonce != expected_nonce {
+			return Err(Error::invalid_params(format!(
+				"invalid request nonce {}, expected {}",
+				self.nonce, expected_nonce
+			)))
+		}
+		Ok(())
+	}
+}
+
+/// Domain separation context of this enclave that signed requests have to be bound to.
+fn get_request_context() -> Result<RequestContext, Error> {
+	let validator = Arc::new(EnclaveValidatorAccessor::default());
+	let genesis_hash = validator
+		.execute_on_validator(|v| v.genesis_hash(v.num_relays()))
+		.map_err(|e| Error::invalid_params(format!("failed to get genesis hash: {}", e)))?;
+	let mr_enclave = OcallApi.get_mrenclave_of_self().map_err(|_| Error::internal_error())?.m;
+	let worker = Ed25519Seal::unseal().map_err(|_| Error::internal_error())?.public().0;
+	Ok(RequestContext::new(genesis_hash, mr_enclave, worker))
+}
+
+fn get_latest_parentchain_header() -> Result<Header, Error> {
 	let validator = Arc::new(EnclaveValidatorAccessor::default());
-	let header = validator
+	validator
 		.execute_on_validator(|v| v.latest_finalized_header(v.num_relays()))
-		.map_err(|e| Error::invalid_params(format!("failed to get header: {}", e)))?;
+		.map_err(|e| Error::invalid_params(format!("failed to get header: {}", e)))
+}
+
+pub fn get_verified_nft_owner(nft_id: u32) -> Result<AccountId, Error> {
+	Ok(get_verified_nft_data(nft_id)?.owner.into())
+}
+
+/// Only the owner of a NFT may use its key pair, whoever that is at the latest block, and only
+/// while the `NftSecretPolicy` permits access to the secrets of the NFT.
+fn check_nft_key_access(nft_id: u32, signer: &AccountId) -> Result<(), Error> {
+	let data = get_verified_nft_data(nft_id)?;
+	check_owner(nft_id, &data, signer).map(|_| ())
+}
+
+/// Only the owner of a NFT may manage its secrets, and only while the `NftSecretPolicy` permits
+/// access to them. Returns the owner.
+fn check_owner(nft_id: u32, data: &NFTData, signer: &AccountId) -> Result<AccountId, Error> {
+	check_nft_secret_policy(data)?;
+	let owner: AccountId = data.owner.into();
+	if owner != *signer {
+		return Err(Error::invalid_params(format!("sender does not own the nft with id {}", nft_id)))
+	}
+	Ok(owner)
+}
+
+fn nft_not_found(nft_id: u32) -> Error {
+	Error::invalid_params(format!("there is no nft with id {} in parentchain storage", nft_id))
+}
+
+fn get_verified_nft_data(nft_id: u32) -> Result<NFTData, Error> {
+	// Get last header from light client
+	let header = get_latest_parentchain_header()?;
 
-	// Get verified owner
+	// Get verified NFT data
 	let ocall_api = Arc::new(OcallApi);
 	let (_key, data): (Vec<u8>, Option<NFTData>) = ocall_api
 		.get_storage_verified(NFTsStorage::data(nft_id), &header)
 		.map_err(|_| Error::invalid_params("failed to get storage verified NFTData"))?
 		.into_tuple();
-	let owner = data
-		.ok_or(Error::invalid_params(format!(
-			"there is no nft with id {} in parentchain storage",
-			&nft_id
-		)))?
-		.owner;
-	Ok(owner.into())
+	data.ok_or_else(|| nft_not_found(nft_id))
+}
+
+/// Verified data of all NFTs, read with a single ocall. `None` for the NFTs that do not exist.
+fn get_verified_nft_data_batch(nft_ids: &[u32]) -> Result<Vec<Option<NFTData>>, Error> {
+	let header = get_latest_parentchain_header()?;
+
+	let keys: Vec<Vec<u8>> = nft_ids.iter().map(|nft_id| NFTsStorage::data(*nft_id)).collect();
+	let entries = OcallApi
+		.get_multiple_storages_verified::<_, NFTData>(keys.clone(), &header)
+		.map_err(|_| Error::invalid_params("failed to get storage verified NFTData"))?;
+	// The host could omit or reorder entries, hence every entry must match the requested key.
+	if entries.len() != keys.len()
+		|| entries.iter().zip(keys.iter()).any(|(e, k)| e.key() != k.as_slice())
+	{
+		return Err(Error::invalid_params("unexpected NFTData storage entries"))
+	}
+	Ok(entries.into_iter().map(|entry| entry.into_tuple().1).collect())
+}
+
+fn get_verified_nft_series_data(series_id: &str) -> Result<NFTSeriesData, Error> {
+	let header = get_latest_parentchain_header()?;
+
+	let (_key, data): (Vec<u8>, Option<NFTSeriesData>) = OcallApi
+		.get_storage_verified(NFTsStorage::series(series_id.as_bytes()), &header)
+		.map_err(|_| Error::invalid_params("failed to get storage verified NFTSeriesData"))?
+		.into_tuple();
+	data.ok_or_else(|| {
+		Error::invalid_params(format!(
+			"there is no nft series with id '{}' in parentchain storage",
+			series_id
+		))
+	})
+}
+
+fn get_verified_capsule_data(nft_id: u32) -> Result<CapsuleData, Error> {
+	let header = get_latest_parentchain_header()?;
+
+	let (_key, data): (Vec<u8>, Option<CapsuleData>) = OcallApi
+		.get_storage_verified(CapsulesStorage::capsule(nft_id), &header)
+		.map_err(|_| Error::invalid_params("failed to get storage verified CapsuleData"))?
+		.into_tuple();
+	data.ok_or_else(|| {
+		Error::invalid_params(format!(
+			"there is no capsule of the nft with id {} in parentchain storage",
+			nft_id
+		))
+	})
+}
+
+fn check_batch_size(size: usize) -> Result<(), Error> {
+	if size > MAX_NFT_SECRET_BATCH_SIZE {
+		return Err(Error::invalid_params(format!(
+			"batch of {} secrets exceeds the maximum of {}",
+			size, MAX_NFT_SECRET_BATCH_SIZE
+		)))
+	}
+	Ok(())
+}
+
+/// Seals the secret written by `writer` in `mode` while `owner` owns the NFT, and replicates it
+/// to the registered workers, or distributes it among them if threshold sharing is enabled. The
+/// commitment to the secret is published on the parentchain.
+///
+/// A secret with an unlock condition is not released before the condition is met.
+fn store_nft_secret(
+	nft_id: u32,
+	share_index: u8,
+	secret: Vec<u8>,
+	writer: AccountId,
+	owner: AccountId,
+	unlock: Option<UnlockCondition>,
+	mode: NftSecretWriteMode,
+) -> Result<NftSecretReplication, Error> {
+	let header = get_latest_parentchain_header()?;
+	let replication = match nft_secret_sharing::threshold_sharing_config()
+		.map_err(|_| Error::internal_error())?
+	{
+		Some(config) => nft_secret_sharing::store_secret(
+			&config,
+			&header,
+			nft_id,
+			share_index,
+			&secret,
+			&writer,
+			unlock,
+			mode,
+		)
+		.map_err(|e| match e {
+			EnclaveError::NftDb(NftError::WriteDenied) => write_denied(nft_id, share_index),
+			e => {
+				error!("Failed to share secret of NFT {}: {:?}", nft_id, e);
+				Error::internal_error()
+			},
+		})?,
+		None => nft_secret_replication::store_secret(
+			&header,
+			nft_id,
+			share_index,
+			secret.clone(),
+			writer,
+			owner,
+			unlock,
+			mode,
+		)
+		.map_err(|e| match e {
+			EnclaveError::NftDb(NftError::WriteDenied) => write_denied(nft_id, share_index),
+			_ => Error::internal_error(),
+		})?
+		.ok_or_else(|| {
+			Error::invalid_params(format!(
+				"secret share {} of NFT with id '{}' has been written by a newer request",
+				share_index, nft_id
+			))
+		})?,
+	};
+
+	// The secret has been stored, hence a failed commitment must not fail the request.
+	if let Err(e) = nft_secret_commitment::commit_secret(nft_id, share_index, &secret) {
+		error!("Failed to commit to secret of NFT {}: {:?}", nft_id, e);
+	}
+	Ok(replication)
+}
+
+/// Seals the series secret written by `writer` and replicates it to the registered workers.
+fn store_nft_series_secret(
+	series_id: String,
+	secret: Vec<u8>,
+	writer: AccountId,
+) -> Result<NftSecretReplication, Error> {
+	check_no_threshold_sharing("series secrets")?;
+
+	let header = get_latest_parentchain_header()?;
+	let secret = SeriesSecret::new(series_id.clone().into_bytes(), header.number, writer, secret);
+	nft_secret_replication::store_series_secret(&header, secret)
+		.map_err(|_| Error::internal_error())?
+		.ok_or_else(|| {
+			Error::invalid_params(format!(
+				"secret of the series with id '{}' has been written by a newer request",
+				series_id
+			))
+		})
+}
+
+/// Seals the capsule key and replicates it to the registered workers.
+fn store_capsule_key(nft_id: u32, key: Vec<u8>) -> Result<NftSecretReplication, Error> {
+	check_no_threshold_sharing("capsule keys")?;
+
+	let header = get_latest_parentchain_header()?;
+	let key = CapsuleKey::new(nft_id, header.number, key);
+	nft_secret_replication::store_capsule_key(&header, key)
+		.map_err(|_| Error::internal_error())?
+		.ok_or_else(|| {
+			Error::invalid_params(format!(
+				"key of the capsule of NFT with id '{}' has been written by a newer request",
+				nft_id
+			))
+		})
+}
+
+fn store_recipient_key(account: AccountId, key: Vec<u8>) -> Result<NftSecretReplication, Error> {
+	check_no_threshold_sharing("recipient keys")?;
+
+	let header = get_latest_parentchain_header()?;
+	let key = RecipientKey::new(account, header.number, key);
+	nft_secret_replication::store_recipient_key(&header, key)
+		.map_err(|_| Error::internal_error())?
+		.ok_or_else(|| Error::invalid_params("recipient key has been written by a newer request"))
+}
+
+/// Series secrets, capsule keys and recipient keys are replicated as a whole, hence they are not
+/// supported while the NFT secrets are split under threshold sharing.
+fn check_no_threshold_sharing(kind: &str) -> Result<(), Error> {
+	if nft_secret_sharing::threshold_sharing_config()
+		.map_err(|_| Error::internal_error())?
+		.is_some()
+	{
+		return Err(Error::invalid_params(format!(
+			"{} are not supported under threshold sharing",
+			kind
+		)))
+	}
+	Ok(())
+}
+
+/// Returns the secret share, reconstructed from the registered workers if threshold sharing is
+/// enabled. `None` if the share has not been stored.
+fn retrieve_nft_secret(nft_id: u32, share_index: u8) -> Result<Option<Vec<u8>>, Error> {
+	match nft_secret_sharing::threshold_sharing_config().map_err(|_| Error::internal_error())? {
+		Some(config) => {
+			let header = get_latest_parentchain_header()?;
+			nft_secret_sharing::retrieve_secret(&config, &header, nft_id, share_index).map_err(
+				|e| {
+					error!("Failed to reconstruct secret of NFT {}: {:?}", nft_id, e);
+					Error::internal_error()
+				},
+			)
+		},
+		None => {
+			let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+			match NftDbSeal::get(nft_id, share_index) {
+				Ok(secret) => Ok(Some(secret)),
+				Err(NftError::NftNotFound) => Ok(None),
+				Err(_) => Err(Error::internal_error()),
+			}
+		},
+	}
+}
+
+/// Removes all secrets of the NFT from all registered workers, unless the creator locked any
+/// of its shares in.
+fn remove_nft_secrets(nft_id: u32) -> Result<NftSecretReplication, Error> {
+	let sharing_config =
+		nft_secret_sharing::threshold_sharing_config().map_err(|_| Error::internal_error())?;
+	let header = get_latest_parentchain_header()?;
+
+	{
+		let _nft_db_lock = EnclaveLock::write_nft_db().map_err(|_| Error::internal_error())?;
+		// The creator may have locked a share in since the request has been authorized.
+		check_removable_by_owner(nft_id)?;
+		let stored = !NftDbSeal::shares(nft_id).map_err(|_| Error::internal_error())?.is_empty();
+		match sharing_config {
+			Some(_) => match NftDbSeal::remove(nft_id, header.number) {
+				Ok(()) => {},
+				// This worker might have been unavailable while the secret was shared.
+				Err(NftError::NftNotFound) => {},
+				Err(_) => return Err(Error::internal_error()),
+			},
+			None if !stored =>
+				return Err(Error::invalid_params(format!(
+					"no secret stored for NFT with id '{}'",
+					nft_id
+				))),
+			None => {},
+		}
+	}
+
+	match sharing_config {
+		Some(config) => nft_secret_sharing::remove_secrets(&config, &header, nft_id).map_err(|e| {
+			error!("Failed to remove shared secrets of NFT {}: {:?}", nft_id, e);
+			Error::internal_error()
+		}),
+		None => nft_secret_replication::remove_secrets(&header, nft_id)
+			.map_err(|_| Error::internal_error())?
+			.ok_or_else(|| {
+				Error::invalid_params(format!(
+					"secrets of NFT with id '{}' have been removed by a newer request",
+					nft_id
+				))
+			}),
+	}
+}
+
+/// Public metadata of the secrets of the NFT, as far as this worker holds them.
+fn get_nft_secret_info(nft_id: u32) -> Result<NftSecretInfo, Error> {
+	let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+	let shares = NftDbSeal::shares(nft_id).map_err(|_| Error::internal_error())?;
+	let metadata = NftSecretMetadataSeal::get(nft_id).map_err(|_| Error::internal_error())?;
+
+	let latest_write = metadata.as_ref().and_then(|m| m.latest_write(&shares));
+	Ok(NftSecretInfo {
+		exists: !shares.is_empty(),
+		byte_length: metadata.as_ref().map_or(0, |m| m.byte_length(&shares)),
+		share_count: shares.len() as u8,
+		updated_at: latest_write.map(|w| w.block_number),
+		writer: latest_write.map(|w| w.writer.clone()),
+	})
+}
+
+/// Only the owner and the accounts it granted access to may retrieve a secret share of the NFT
+/// with the verified `data`, and only once the share is unlocked.
+fn check_retrieve_access(
+	req: &RetrieveNftSecretRequest,
+	data: &NFTData,
+	signer: &AccountId,
+) -> Result<(), Error> {
+	check_nft_secret_policy(data)?;
+	let owner: AccountId = data.owner.into();
+
+	let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+	check_read_access(req.nft_id, &owner, signer)?;
+	check_unlocked(req.nft_id, req.share_index)
+}
+
+/// Returns the requested secret share in plaintext or encrypted to the recipient key of the
+/// request. The access has to be checked with `check_retrieve_access` beforehand.
+fn retrieve_nft_secret_as(req: RetrieveNftSecretRequest) -> Result<Vec<u8>, Error> {
+	let secret = retrieve_nft_secret(req.nft_id, req.share_index)?;
+	nft_secret_response(req, secret)
+}
+
+/// Like `retrieve_nft_secret_as` for a batch of requests, passing on the requests that have
+/// been denied. Under threshold sharing, every worker is queried once for the whole batch.
+fn retrieve_nft_secrets_as(
+	requests: Vec<Result<RetrieveNftSecretRequest, Error>>,
+) -> Result<Vec<Result<Vec<u8>, Error>>, Error> {
+	let config = match nft_secret_sharing::threshold_sharing_config()
+		.map_err(|_| Error::internal_error())?
+	{
+		Some(config) => config,
+		None => {
+			let results = requests.into_iter().map(|req| req.and_then(retrieve_nft_secret_as));
+			return Ok(results.collect())
+		},
+	};
+
+	let header = get_latest_parentchain_header()?;
+	let secrets: Vec<(u32, u8)> = requests
+		.iter()
+		.filter_map(|req| req.as_ref().ok())
+		.map(|req| (req.nft_id, req.share_index))
+		.collect();
+	let mut retrieved = nft_secret_sharing::retrieve_secrets(&config, &header, &secrets)
+		.map_err(|e| {
+			error!("Failed to reconstruct NFT secrets: {:?}", e);
+			Error::internal_error()
+		})?
+		.into_iter();
+
+	Ok(requests
+		.into_iter()
+		.map(|req| {
+			let req = req?;
+			let secret = retrieved.next().ok_or_else(Error::internal_error)?.map_err(|e| {
+				error!("Failed to reconstruct secret of NFT {}: {:?}", req.nft_id, e);
+				Error::internal_error()
+			})?;
+			nft_secret_response(req, secret)
+		})
+		.collect())
+}
+
+/// The retrieved secret share in plaintext or encrypted to the recipient key of the request.
+fn nft_secret_response(
+	req: RetrieveNftSecretRequest,
+	secret: Option<Vec<u8>>,
+) -> Result<Vec<u8>, Error> {
+	let secret = secret.ok_or_else(|| {
+		Error::invalid_params(format!(
+			"no secret share {} stored for NFT with id '{}'",
+			req.share_index, req.nft_id
+		))
+	})?;
+
+	match req.recipient_key {
+		Some(recipient_key) => {
+			// The share is authenticated, such that it cannot be passed off as another share.
+			let aad = (req.nft_id, req.share_index).encode();
+			Ok(encrypt_nft_secret(&aad, &secret, &recipient_key)?.encode())
+		},
+		None => Ok(secret),
+	}
+}
+
+/// Denies access to a secret share that is time-locked until a later block or timestamp.
+///
+/// The NFT db has to be locked by the caller.
+fn check_unlocked(nft_id: u32, share_index: u8) -> Result<(), Error> {
+	let unlock = match nft_secret_unlock::unlock_condition(nft_id, share_index)
+		.map_err(|_| Error::internal_error())?
+	{
+		Some(unlock) => unlock,
+		None => return Ok(()),
+	};
+	if nft_secret_unlock::is_met(&unlock).map_err(|_| Error::internal_error())? {
+		return Ok(())
+	}
+	let locked_until = match unlock {
+		UnlockCondition::BlockNumber(block_number) => format!("block {}", block_number),
+		UnlockCondition::Timestamp(timestamp) => format!("timestamp {}", timestamp),
+	};
+	Err(Error::invalid_params(format!(
+		"secret share {} of NFT with id '{}' is locked until {}",
+		share_index, nft_id, locked_until
+	)))
+}
+
+/// The owner may write the secrets of a NFT, and so may its creator in creator mode. A share
+/// written in another mode may only be overwritten as far as `NftSecretWriteMode` permits. The
+/// mode is checked again when the share is sealed, since it may have been written meanwhile.
+fn check_write_access(
+	nft_id: u32,
+	share_index: u8,
+	mode: NftSecretWriteMode,
+	data: &NFTData,
+	signer: &AccountId,
+) -> Result<(), Error> {
+	check_nft_secret_policy(data)?;
+	let (writer, role): (AccountId, &str) = match mode {
+		NftSecretWriteMode::Owner => (data.owner.into(), "own"),
+		NftSecretWriteMode::Creator { .. } => (data.creator.into(), "create"),
+	};
+	if writer != *signer {
+		return Err(Error::invalid_params(format!(
+			"sender does not {} the nft with id {}",
+			role, nft_id
+		)))
+	}
+
+	let _nft_db_lock = EnclaveLock::read_nft_db().map_err(|_| Error::internal_error())?;
+	let current = NftSecretMetadataSeal::get(nft_id)
+		.map_err(|_| Error::internal_error())?
+		.and_then(|metadata| metadata.share_write(share_index).map(|write| write.mode));
+	let written_by = match current {
+		Some(current) if !mode.may_overwrite(current) => match current {
+			NftSecretWriteMode::Owner => "an owner",
+			NftSecretWriteMode::Creator { .. } => "the creator and locked in",
+		},
+		_ => return Ok(()),
+	};
+	Err(Error::invalid_params(format!(
+		"secret share {} of NFT with id '{}' has been written by {}",
+		share_index, nft_id, written_by
+	)))
+}
+
+fn write_denied(nft_id: u32, share_index: u8) -> Error {
+	Error::invalid_params(format!(
+		"secret share {} of NFT with id '{}' has been written in a mode that takes precedence",
+		share_index, nft_id
+	))
+}
+
+/// Denies the removal of the secrets of a NFT if the creator locked any of its shares in.
+///
+/// The NFT db has to be locked by the caller.
+fn check_removable_by_owner(nft_id: u32) -> Result<(), Error> {
+	let shares = NftDbSeal::shares(nft_id).map_err(|_| Error::internal_error())?;
+	let metadata = NftSecretMetadataSeal::get(nft_id).map_err(|_| Error::internal_error())?;
+	let locked_in = shares.iter().find(|share_index| {
+		metadata
+			.as_ref()
+			.and_then(|m| m.share_write(**share_index))
+			.map_or(false, |write| !write.mode.is_removable_by_owner())
+	});
+	match locked_in {
+		Some(share_index) => Err(Error::invalid_params(format!(
+			"secret share {} of NFT with id '{}' has been locked in by the creator",
+			share_index, nft_id
+		))),
+		None => Ok(()),
+	}
+}
+
+/// Only the owner may access the key of a capsule, and only while the NFT is converted to it.
+fn check_capsule_access(nft_id: u32, signer: &AccountId) -> Result<(), Error> {
+	let data = get_verified_nft_data(nft_id)?;
+	check_nft_secret_policy(&data)?;
+	if !data.converted_to_capsule {
+		return Err(Error::invalid_params(format!(
+			"nft with id {} is not converted to a capsule",
+			nft_id
+		)))
+	}
+
+	let capsule = get_verified_capsule_data(nft_id)?;
+	let owner: AccountId = data.owner.into();
+	let capsule_owner: AccountId = capsule.owner.into();
+	if owner != *signer || capsule_owner != *signer {
+		return Err(Error::invalid_params(format!(
+			"sender does not own the capsule of the nft with id {}",
+			nft_id
+		)))
+	}
+	Ok(())
+}
+
+/// Only the owner and the accounts it granted access to may read the secrets of a NFT.
+fn check_read_access(nft_id: u32, owner: &AccountId, signer: &AccountId) -> Result<(), Error> {
+	if owner == signer {
+		return Ok(())
+	}
+	let acl = NftAclSeal::get(nft_id).map_err(|_| Error::internal_error())?;
+	if !acl.map_or(false, |acl| acl.has_access(owner, signer)) {
+		return Err(Error::invalid_params(format!(
+			"sender has no read access to the nft with id {}",
+			nft_id
+		)))
+	}
+	Ok(())
+}
+
+/// Encrypts a NFT secret to the JSON encoded RSA-3072 key of the requester, or of the new owner
+/// the secret is handed over to. The `aad` binds the ciphertext to the secret it has been
+/// requested as.
+pub fn encrypt_nft_secret(
+	aad: &[u8],
+	secret: &[u8],
+	recipient_key: &[u8],
+) -> Result<EncryptedNftSecret, Error> {
+	let recipient_key: Rsa3072PubKey = serde_json::from_slice(recipient_key)
+		.map_err(|_| Error::invalid_params("failed to decode recipient key"))?;
+
+	let mut os_rng = SgxRng::new().map_err(|_| Error::internal_error())?;
+	let mut key = [0u8; 16];
+	let mut nonce = [0u8; 12];
+	os_rng.fill_bytes(&mut key);
+	os_rng.fill_bytes(&mut nonce);
+
+	let mut ciphertext = vec![0u8; secret.len()];
+	let mut mac = [0u8; 16];
+	rsgx_rijndael128GCM_encrypt(&key, secret, &nonce, aad, &mut ciphertext, &mut mac)
+		.map_err(|_| Error::internal_error())?;
+
+	let mut encrypted_key = Vec::new();
+	recipient_key
+		.encrypt_buffer(&key, &mut encrypted_key)
+		.map_err(|_| Error::invalid_params("failed to encrypt to recipient key"))?;
+
+	Ok(EncryptedNftSecret { encrypted_key, nonce, ciphertext, mac })
+}
+
+/// Withholds secrets until the latest version anchor has been seen on the parentchain, as the
+/// sealed files could have been rolled back undetected before, see `version_anchor`.
+fn check_version_anchor_confirmed() -> Result<(), Error> {
+	if version_anchor::is_latest_anchor_confirmed() {
+		return Ok(())
+	}
+	Err(Error {
+		code: ErrorCode::ServerError(NFT_SECRETS_UNAVAILABLE_ERROR_CODE),
+		message: "secrets are unavailable until the latest version anchor is on chain".into(),
+		data: None,
+	})
+}
+
+/// Denies access to the secret of a NFT whose lifecycle state forbids it, according to the
+/// `NftSecretPolicy` of this deployment.
+fn check_nft_secret_policy(data: &NFTData) -> Result<(), Error> {
+	let block_listed_for_sale = GLOBAL_PRIMITIVES_CACHE
+		.get_block_listed_nft_secrets()
+		.map_err(|_| Error::internal_error())?;
+	NftSecretPolicy::new(block_listed_for_sale).check(data).map_err(|denial| {
+		let message = match denial {
+			NftSecretAccessDenial::InTransmission => "nft is in transmission",
+			NftSecretAccessDenial::ListedForSale => "nft is listed for sale",
+		};
+		Error {
+			code: ErrorCode::ServerError(denial.error_code()),
+			message: message.into(),
+			data: None,
+		}
+	})
 }
 
 #[cfg(feature = "test")]
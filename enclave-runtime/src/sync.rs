@@ -35,6 +35,8 @@ use std::sync::{SgxRwLock, SgxRwLockReadGuard, SgxRwLockWriteGuard};
 
 lazy_static! {
 	pub static ref SIDECHAIN_DB_LOCK: SgxRwLock<()> = Default::default();
+	pub static ref REQUEST_NONCE_DB_LOCK: SgxRwLock<()> = Default::default();
+	pub static ref NFT_DB_LOCK: SgxRwLock<()> = Default::default();
 }
 
 pub struct EnclaveLock;
@@ -54,6 +56,34 @@ pub trait SidechainRwLock {
 	fn write_sidechain_db() -> EnclaveResult<SgxRwLockWriteGuard<'static, ()>>;
 }
 
+impl RequestNonceRwLock for EnclaveLock {
+	fn write_request_nonce_db() -> EnclaveResult<SgxRwLockWriteGuard<'static, ()>> {
+		REQUEST_NONCE_DB_LOCK.write().map_err(|e| Error::Other(e.into()))
+	}
+}
+
+/// Serializes the unseal-check-seal cycle of the request nonces, such that the same nonce
+/// can not be consumed twice by concurrent requests.
+pub trait RequestNonceRwLock {
+	fn write_request_nonce_db() -> EnclaveResult<SgxRwLockWriteGuard<'static, ()>>;
+}
+
+impl NftDbRwLock for EnclaveLock {
+	fn read_nft_db() -> EnclaveResult<SgxRwLockReadGuard<'static, ()>> {
+		NFT_DB_LOCK.read().map_err(|e| Error::Other(e.into()))
+	}
+
+	fn write_nft_db() -> EnclaveResult<SgxRwLockWriteGuard<'static, ()>> {
+		NFT_DB_LOCK.write().map_err(|e| Error::Other(e.into()))
+	}
+}
+
+/// Guards the sealed NFT secret records and their index against concurrent modification.
+pub trait NftDbRwLock {
+	fn read_nft_db() -> EnclaveResult<SgxRwLockReadGuard<'static, ()>>;
+	fn write_nft_db() -> EnclaveResult<SgxRwLockWriteGuard<'static, ()>>;
+}
+
 // simple type defs to prevent too long names
 type AggregatedReadGuards<'a> = SgxRwLockReadGuard<'a, ()>;
 type AggregatedWriteGuards<'a> = SgxRwLockWriteGuard<'a, ()>;
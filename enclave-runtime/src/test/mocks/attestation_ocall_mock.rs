@@ -47,6 +47,10 @@ impl EnclaveAttestationOCallApi for AttestationOCallMock {
 		unreachable!()
 	}
 
+	fn get_mu_ra_socket(&self, _worker_url: &str) -> SgxResult<i32> {
+		unreachable!()
+	}
+
 	fn get_quote(
 		&self,
 		_sig_rl: Vec<u8>,
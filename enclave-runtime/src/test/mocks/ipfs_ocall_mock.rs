@@ -0,0 +1,69 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+use ipfs_unixfs::file::adder::FileAdder;
+use itp_ocall_api::{EnclaveIpfsOCallApi, IpfsCid};
+use multibase::Base;
+use sgx_types::*;
+use std::{
+	string::String,
+	sync::{Arc, SgxMutex},
+	vec::Vec,
+};
+
+/// In-memory stand-in for IPFS, addressing the files by the CID IPFS would assign to them.
+#[derive(Clone, Default)]
+pub struct IpfsOCallMock {
+	files: Arc<SgxMutex<Vec<(IpfsCid, Vec<u8>)>>>,
+}
+
+impl IpfsOCallMock {
+	pub fn file(&self, cid: &IpfsCid) -> Option<Vec<u8>> {
+		let files = self.files.lock().unwrap();
+		files.iter().find(|(c, _)| c.0 == cid.0).map(|(_, content)| content.clone())
+	}
+}
+
+impl EnclaveIpfsOCallApi for IpfsOCallMock {
+	fn write_ipfs(&self, encoded_state: &[u8]) -> SgxResult<IpfsCid> {
+		let mut cid = IpfsCid([0u8; 46]);
+		match cid_of(encoded_state) {
+			Some(c) if c.len() == cid.0.len() => cid.0.copy_from_slice(c.as_bytes()),
+			_ => return Err(sgx_status_t::SGX_ERROR_UNEXPECTED),
+		}
+		self.files.lock().unwrap().push((IpfsCid(cid.0), encoded_state.to_vec()));
+		Ok(cid)
+	}
+
+	fn read_ipfs(&self, cid: &IpfsCid) -> SgxResult<()> {
+		self.file(cid).map(|_| ()).ok_or(sgx_status_t::SGX_ERROR_UNEXPECTED)
+	}
+}
+
+/// The CID IPFS assigns to the content when it is added as a file.
+fn cid_of(content: &[u8]) -> Option<String> {
+	let mut adder = FileAdder::default();
+	let mut last_cid = None;
+	let mut total: usize = 0;
+	while total < content.len() {
+		let (blocks, consumed) = adder.push(&content[total..]);
+		total += consumed;
+		last_cid = blocks.map(|(cid, _)| cid).last().or(last_cid);
+	}
+	last_cid = adder.finish().map(|(cid, _)| cid).last().or(last_cid);
+	last_cid.map(|cid| Base::Base58Btc.encode(cid.hash().as_bytes()))
+}
@@ -15,7 +15,7 @@
 */
 
 use crate::{
-	attestation,
+	attestation, nft_db_backup, nft_ownership_observer,
 	ocall::OcallApi,
 	rpc,
 	sync::tests::{enclave_rw_lock_works, sidechain_rw_lock_works},
@@ -45,6 +45,12 @@ pub extern "C" fn test_main_entrance() -> size_t {
 		// sync tests
 		sidechain_rw_lock_works,
 		enclave_rw_lock_works,
+		nft_ownership_observer::tests::first_transfer_after_store_hands_the_secret_over,
+		// NFT db backup tests
+		nft_db_backup::tests::backup_restores_the_backed_up_records,
+		nft_db_backup::tests::backup_not_matching_its_cid_is_rejected,
+		nft_db_backup::tests::tampered_backup_is_rejected,
+		nft_db_backup::tests::backup_encrypted_in_other_context_is_rejected,
 		// these unit test (?) need an ipfs node running..
 		// ipfs::test_creates_ipfs_content_struct_works,
 		// ipfs::test_verification_ok_for_correct_content,
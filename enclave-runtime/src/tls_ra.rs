@@ -2,24 +2,94 @@ use crate::{
 	attestation::{create_ra_report_and_signature, DEV_HOSTNAME},
 	cert,
 	error::{Error as EnclaveError, Result as EnclaveResult},
+	nft_secret_replication, nft_secret_sharing,
 	ocall::OcallApi,
+	sync::{EnclaveLock, NftDbRwLock},
 };
+use codec::{Decode, Encode};
 use itp_ocall_api::EnclaveAttestationOCallApi;
-use itp_sgx_crypto::{Aes, AesSeal, Rsa3072Seal};
+use itp_sgx_crypto::{Aes, AesSeal, NftKeySeedSeal, Rsa3072Seal};
 use itp_sgx_io::SealedIO;
+use itp_types::{AccountId, NftSecretWriteMode, UnlockCondition};
 use log::*;
 use rustls::{ClientConfig, ClientSession, ServerConfig, ServerSession, Stream};
 use sgx_crypto_helper::rsa3072::Rsa3072KeyPair;
 use sgx_types::*;
 use std::{
 	backtrace::{self, PrintFormat},
+	format,
 	io::{Read, Write},
 	net::TcpStream,
 	sync::Arc,
 	vec::Vec,
 };
+use ternoa_sgx_nft::{
+	acl::NftAcl,
+	capsule::CapsuleKey,
+	recipient_key::RecipientKey,
+	series::SeriesSecret,
+	shamir::ThresholdShare,
+	snapshot::{NftDbRecord, NftDbSnapshotDigest, NftDbSnapshotHeader, NFT_DB_SNAPSHOT_VERSION},
+	versions::WriteVersion,
+	NftDbSeal, ShareIndex,
+};
 use webpki::DNSName;
 
+/// Upper bound of an encoded MU-RA message, protects against allocating arbitrary amounts of memory.
+const MAX_MU_RA_MESSAGE_SIZE: usize = 1 << 20;
+
+/// Request sent by the client after the MU-RA session has been established.
+#[derive(Debug, Encode, Decode)]
+pub enum MuRaRequest {
+	/// Provision the shielding and state encryption keys and the seed of the NFT key pairs,
+	/// followed by a snapshot of the NFT secret database, to a newly registered worker.
+	ProvisionKeys,
+	/// Seal the given Shamir share of a NFT secret, that has been written by `writer`.
+	StoreNftSecretShare {
+		nft_id: u32,
+		share_index: ShareIndex,
+		share: ThresholdShare,
+		writer: AccountId,
+		unlock: Option<UnlockCondition>,
+		mode: NftSecretWriteMode,
+	},
+	/// Return the sealed Shamir shares of the given NFT secrets, withholding those that are still
+	/// time-locked.
+	RetrieveNftSecretShares(Vec<(u32, ShareIndex)>),
+	/// Remove all sealed Shamir shares of the secrets of a NFT, at the given block.
+	RemoveNftSecretShares { nft_id: u32, block_number: u32 },
+	/// Apply a secret share that has been written by `writer` on another worker, while `owner`
+	/// owned the NFT.
+	ReplicateNftSecret {
+		nft_id: u32,
+		share_index: ShareIndex,
+		secret: Vec<u8>,
+		version: WriteVersion,
+		writer: AccountId,
+		owner: AccountId,
+		unlock: Option<UnlockCondition>,
+		mode: NftSecretWriteMode,
+	},
+	/// Apply a removal of the secrets of a NFT that has been accepted by another worker.
+	ReplicateNftSecretRemoval { nft_id: u32, version: WriteVersion },
+	/// Apply a series secret that has been written on another worker.
+	ReplicateNftSeriesSecret(SeriesSecret),
+	/// Apply a capsule key that has been written on another worker.
+	ReplicateCapsuleKey(CapsuleKey),
+	/// Apply a recipient key that has been registered on another worker.
+	ReplicateRecipientKey(RecipientKey),
+	/// Apply the access grants of a NFT that have been changed on another worker.
+	ReplicateNftAcl(NftAcl),
+}
+
+/// Response to all requests except `MuRaRequest::ProvisionKeys`.
+#[derive(Debug, Encode, Decode)]
+pub enum MuRaResponse {
+	Done,
+	NftSecretShares(Vec<Option<ThresholdShare>>),
+	Failed,
+}
+
 struct ClientAuth<A> {
 	outdated_ok: bool,
 	skip_ra: bool,
@@ -133,16 +203,46 @@ pub unsafe extern "C" fn run_key_provisioning_server(
 	};
 
 	let mut tls = rustls::Stream::new(&mut sess, &mut conn);
-	println!("    [Enclave] (MU-RA-Server) MU-RA successful sending keys");
 
-	let (rsa_pair, aes) = match read_files_to_send() {
-		Ok((r, a)) => (r, a),
+	let request: MuRaRequest = match read_message(&mut tls) {
+		Ok(request) => request,
 		Err(e) => return e.into(),
 	};
 
-	match send_files(&mut tls, &rsa_pair, &aes) {
-		Ok(_) => println!("    [Enclave] (MU-RA-Server) Successfully provisioned keys!\n"),
-		Err(e) => return e.into(),
+	let response = match request {
+		MuRaRequest::ProvisionKeys => {
+			println!("    [Enclave] (MU-RA-Server) MU-RA successful sending keys");
+
+			let (rsa_pair, aes, nft_key_seed) = match read_files_to_send() {
+				Ok((r, a, s)) => (r, a, s),
+				Err(e) => return e.into(),
+			};
+
+			match send_files(&mut tls, &rsa_pair, &aes, &nft_key_seed) {
+				Ok(_) => println!("    [Enclave] (MU-RA-Server) Successfully provisioned keys!\n"),
+				Err(e) => return e.into(),
+			}
+
+			match send_nft_db(&mut tls) {
+				Ok(count) => println!(
+					"    [Enclave] (MU-RA-Server) Successfully provisioned {} NFT records!\n",
+					count
+				),
+				Err(e) => return e.into(),
+			}
+			return sgx_status_t::SGX_SUCCESS
+		},
+		MuRaRequest::ReplicateNftSecret { .. }
+		| MuRaRequest::ReplicateNftSecretRemoval { .. }
+		| MuRaRequest::ReplicateNftSeriesSecret(_)
+		| MuRaRequest::ReplicateCapsuleKey(_)
+		| MuRaRequest::ReplicateRecipientKey(_)
+		| MuRaRequest::ReplicateNftAcl(_) => nft_secret_replication::handle_worker_request(request),
+		_ => nft_secret_sharing::handle_worker_request(request),
+	};
+
+	if let Err(e) = write_message(&mut tls, &response) {
+		return e.into()
 	}
 
 	sgx_status_t::SGX_SUCCESS
@@ -172,30 +272,50 @@ fn tls_server_config<A: EnclaveAttestationOCallApi + 'static>(
 	Ok(cfg)
 }
 
-fn read_files_to_send() -> EnclaveResult<(Vec<u8>, Aes)> {
+fn read_files_to_send() -> EnclaveResult<(Vec<u8>, Aes, [u8; 32])> {
 	let shielding_key = Rsa3072Seal::unseal()?;
 	let aes = AesSeal::unseal()?;
+	let nft_key_seed = NftKeySeedSeal::unseal()?;
 	let rsa_pair = serde_json::to_vec(&shielding_key).map_err(|e| EnclaveError::Other(e.into()))?;
 
 	let rsa_len = rsa_pair.len();
 	info!("    [Enclave] Read Shielding Key: {:?}", rsa_len);
 	info!("    [Enclave] Read AES key {:?}", aes);
 
-	Ok((rsa_pair, aes))
+	Ok((rsa_pair, aes, nft_key_seed))
 }
 
 fn send_files(
 	tls: &mut Stream<ServerSession, TcpStream>,
 	rsa_pair: &[u8],
 	aes: &Aes,
+	nft_key_seed: &[u8; 32],
 ) -> EnclaveResult<()> {
-	tls.write(&rsa_pair.len().to_le_bytes())?;
-	tls.write(rsa_pair)?;
-	tls.write(&aes.key[..])?;
-	tls.write(&aes.init_vec[..])?;
+	tls.write_all(&rsa_pair.len().to_le_bytes())?;
+	tls.write_all(rsa_pair)?;
+	tls.write_all(&aes.key[..])?;
+	tls.write_all(&aes.init_vec[..])?;
+	tls.write_all(&nft_key_seed[..])?;
 	Ok(())
 }
 
+/// Streams a snapshot of the NFT secret database and returns the number of sent records.
+fn send_nft_db(tls: &mut Stream<ServerSession, TcpStream>) -> EnclaveResult<usize> {
+	let records = {
+		let _nft_db_lock = EnclaveLock::read_nft_db()?;
+		NftDbSeal::records()?
+	};
+
+	write_message(tls, &NftDbSnapshotHeader::new(records.len() as u32))?;
+	let mut digest = NftDbSnapshotDigest::default();
+	for record in records.iter() {
+		write_message(tls, record)?;
+		digest.update(record);
+	}
+	write_message(tls, &digest.finalize())?;
+	Ok(records.len())
+}
+
 #[no_mangle]
 pub extern "C" fn request_key_provisioning(
 	socket_fd: c_int,
@@ -216,10 +336,18 @@ pub extern "C" fn request_key_provisioning(
 
 	let mut tls = rustls::Stream::new(&mut sess, &mut conn);
 
+	if let Err(e) = write_message(&mut tls, &MuRaRequest::ProvisionKeys) {
+		return e.into()
+	}
+
 	println!();
 	println!("    [Enclave] (MU-RA-Client) MU-RA successful waiting for keys...");
 
-	match receive_files(&mut tls) {
+	if let Err(e) = receive_files(&mut tls) {
+		return e.into()
+	}
+
+	match receive_nft_db(&mut tls) {
 		Ok(_) => println!("    [Enclave] (MU-RA-Client) Registration procedure successful!\n"),
 		Err(e) => return e.into(),
 	}
@@ -230,10 +358,11 @@ pub extern "C" fn request_key_provisioning(
 fn receive_files(tls: &mut Stream<ClientSession, TcpStream>) -> EnclaveResult<()> {
 	let mut key_len_arr = [0u8; 8];
 
-	let key_len = tls.read(&mut key_len_arr).map(|_| usize::from_le_bytes(key_len_arr))?;
+	let key_len = tls.read_exact(&mut key_len_arr).map(|_| usize::from_le_bytes(key_len_arr))?;
 
 	let mut rsa_pair = vec![0u8; key_len];
-	tls.read(&mut rsa_pair).map(|_| info!("    [Enclave] Received Shielding key"))?;
+	tls.read_exact(&mut rsa_pair)
+		.map(|_| info!("    [Enclave] Received Shielding key"))?;
 
 	let key: Rsa3072KeyPair = serde_json::from_slice(&rsa_pair).map_err(|e| {
 		error!("    [Enclave] Received Invalid RSA key");
@@ -242,20 +371,65 @@ fn receive_files(tls: &mut Stream<ClientSession, TcpStream>) -> EnclaveResult<()
 	Rsa3072Seal::seal(key)?;
 
 	let mut aes_key = [0u8; 16];
-	tls.read(&mut aes_key)
+	tls.read_exact(&mut aes_key)
 		.map(|_| info!("    [Enclave] (MU-RA-Client)Received AES key: {:?}", &aes_key[..]))?;
 
 	let mut aes_iv = [0u8; 16];
-	tls.read(&mut aes_iv)
+	tls.read_exact(&mut aes_iv)
 		.map(|_| info!("    [Enclave] (MU-RA-Client) Received AES IV: {:?}", &aes_iv[..]))?;
 
 	AesSeal::seal(Aes::new(aes_key, aes_iv))?;
 
+	let mut nft_key_seed = [0u8; 32];
+	tls.read_exact(&mut nft_key_seed)
+		.map(|_| info!("    [Enclave] (MU-RA-Client) Received NFT key seed"))?;
+
+	NftKeySeedSeal::seal(nft_key_seed)?;
+
 	println!("    [Enclave] (MU-RA-Client) Successfully received keys.");
 
 	Ok(())
 }
 
+/// Receives a snapshot of the NFT secret database and seals it, once it has been verified
+/// to be complete.
+fn receive_nft_db(tls: &mut Stream<ClientSession, TcpStream>) -> EnclaveResult<()> {
+	let header: NftDbSnapshotHeader = read_message(tls)?;
+	if header.version != NFT_DB_SNAPSHOT_VERSION {
+		return Err(EnclaveError::Other(
+			format!(
+				"unsupported NFT db snapshot version {}, expected {}",
+				header.version, NFT_DB_SNAPSHOT_VERSION
+			)
+			.into(),
+		))
+	}
+
+	let mut records = Vec::new();
+	let mut digest = NftDbSnapshotDigest::default();
+	for _ in 0..header.record_count {
+		let record: NftDbRecord = read_message(tls)?;
+		digest.update(&record);
+		records.push(record);
+	}
+
+	let expected_digest: [u8; 32] = read_message(tls)?;
+	if digest.finalize() != expected_digest {
+		error!("    [Enclave] (MU-RA-Client) Received corrupted NFT db snapshot");
+		return Err(EnclaveError::Other("NFT db snapshot digest mismatch".into()))
+	}
+
+	let _nft_db_lock = EnclaveLock::write_nft_db()?;
+	NftDbSeal::import(records)?;
+
+	println!(
+		"    [Enclave] (MU-RA-Client) Successfully received {} NFT records.",
+		header.record_count
+	);
+
+	Ok(())
+}
+
 fn tls_client_session_stream(
 	socket_fd: i32,
 	cfg: ClientConfig,
@@ -285,3 +459,44 @@ fn tls_client_config<A: EnclaveAttestationOCallApi + 'static>(
 	cfg.versions.push(rustls::ProtocolVersion::TLSv1_2);
 	Ok(cfg)
 }
+
+/// Sends a single request to the MU-RA server of a fellow worker and returns its response.
+pub fn send_mu_ra_request<A: EnclaveAttestationOCallApi + 'static>(
+	socket_fd: c_int,
+	sign_type: sgx_quote_sign_type_t,
+	ocall_api: A,
+	skip_ra: bool,
+	request: &MuRaRequest,
+) -> EnclaveResult<MuRaResponse> {
+	let cfg = tls_client_config(sign_type, ocall_api, skip_ra)?;
+	let (mut sess, mut conn) = tls_client_session_stream(socket_fd, cfg)?;
+	let mut tls = rustls::Stream::new(&mut sess, &mut conn);
+
+	write_message(&mut tls, request)?;
+	read_message(&mut tls)
+}
+
+/// Writes a length prefixed, SCALE encoded message.
+fn write_message<S: Write, M: Encode>(stream: &mut S, message: &M) -> EnclaveResult<()> {
+	let encoded = message.encode();
+	stream.write_all(&(encoded.len() as u32).to_le_bytes())?;
+	stream.write_all(&encoded)?;
+	stream.flush()?;
+	Ok(())
+}
+
+/// Reads a message written by `write_message`.
+fn read_message<S: Read, M: Decode>(stream: &mut S) -> EnclaveResult<M> {
+	let mut len = [0u8; 4];
+	stream.read_exact(&mut len)?;
+	let len = u32::from_le_bytes(len) as usize;
+	if len > MAX_MU_RA_MESSAGE_SIZE {
+		return Err(EnclaveError::Other(
+			format!("MU-RA message of {} bytes is too large", len).into(),
+		))
+	}
+
+	let mut encoded = vec![0u8; len];
+	stream.read_exact(&mut encoded)?;
+	Ok(M::decode(&mut encoded.as_slice())?)
+}
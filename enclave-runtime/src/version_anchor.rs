@@ -0,0 +1,114 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+//! Anchors the versions of the sealed files on the parentchain, see `itp_sgx_io::rollback`.
+//!
+//! After every block import that sealed files, the enclave remarks its root anchor from its
+//! registered account. The light client is sealed alike, hence a rollback of all sealed files
+//! makes the enclave import the blocks carrying the later anchors again. Importing them fails,
+//! and the enclave refuses to unseal any versioned file from then on.
+//!
+//! The anchors are only read from blocks whose header has been validated by the light client and
+//! whose body matches the header. The parentchain has checked the signature of every included
+//! extrinsic, hence only the enclave account can have published them.
+//!
+//! Files sealed since the latest anchor has been published can still be rolled back undetected,
+//! until the anchor has been seen on the parentchain. Until then, secrets are not served.
+
+use codec::{Decode, Encode};
+use itc_parentchain::block_importer::{
+	error::{Error as ImportError, Result as ImportResult},
+	AnchorSealedVersions,
+};
+use itp_settings::node::{REMARK, SYSTEM_MODULE};
+use itp_sgx_io::{
+	check_version_anchor, is_version_anchor_confirmed, publish_version_anchor,
+	rollback::PublishedAnchor,
+};
+use itp_types::{AccountId, Block as ParentchainBlock, OpaqueCall};
+use log::*;
+use sp_core::hashing::blake2_256;
+use sp_runtime::traits::Block as BlockT;
+use std::{format, vec::Vec};
+use substrate_api_client::{GenericAddress, UncheckedExtrinsicV4};
+
+/// Prefix of the parentchain remarks that publish a `PublishedAnchor`.
+const VERSION_ANCHOR_PREFIX: &[u8] = b"ternoa:version-anchor";
+
+/// Call index and remark of a `system.remark` call.
+type RemarkCall = ([u8; 2], Vec<u8>);
+
+pub struct ParentchainVersionAnchor {
+	/// Account of the enclave, that publishes the anchors.
+	account: AccountId,
+}
+
+impl ParentchainVersionAnchor {
+	pub fn new(account: AccountId) -> Self {
+		ParentchainVersionAnchor { account }
+	}
+
+	/// The anchor published by the extrinsic, if it is a remark of the enclave account.
+	fn published_anchor(
+		&self,
+		xt: &<ParentchainBlock as BlockT>::Extrinsic,
+	) -> Option<PublishedAnchor> {
+		let xt = UncheckedExtrinsicV4::<RemarkCall>::decode(&mut xt.encode().as_slice()).ok()?;
+		match &xt.signature {
+			Some((GenericAddress::Id(signer), _, _)) if signer == &self.account => {},
+			_ => return None,
+		}
+		let (call_index, remark) = xt.function;
+		if call_index != [SYSTEM_MODULE, REMARK] {
+			return None
+		}
+		let (prefix, anchor) = <(Vec<u8>, PublishedAnchor)>::decode(&mut remark.as_slice()).ok()?;
+		(prefix == VERSION_ANCHOR_PREFIX).then(|| anchor)
+	}
+}
+
+impl AnchorSealedVersions<ParentchainBlock> for ParentchainVersionAnchor {
+	fn check_version_anchors(&self, block: &ParentchainBlock) -> ImportResult<()> {
+		for anchor in block.extrinsics().iter().filter_map(|xt| self.published_anchor(xt)) {
+			check_version_anchor(&anchor).map_err(to_import_error)?;
+		}
+		Ok(())
+	}
+
+	fn publish_version_anchor(&self) -> ImportResult<Option<OpaqueCall>> {
+		let anchor = match publish_version_anchor(blake2_256).map_err(to_import_error)? {
+			Some(anchor) => anchor,
+			None => return Ok(None),
+		};
+		debug!("Publishing version anchor {}", anchor.sequence);
+		let remark = (VERSION_ANCHOR_PREFIX, anchor).encode();
+		Ok(Some(OpaqueCall::from_tuple(&([SYSTEM_MODULE, REMARK], remark))))
+	}
+}
+
+/// Whether the latest published anchor has been seen on the parentchain, see
+/// `itp_sgx_io::rollback::RootAnchor::confirm`. Secrets must not be served otherwise.
+pub fn is_latest_anchor_confirmed() -> bool {
+	is_version_anchor_confirmed().unwrap_or_else(|e| {
+		error!("Failed to check the confirmation of the version anchor: {:?}", e);
+		false
+	})
+}
+
+fn to_import_error<E: core::fmt::Debug>(e: E) -> ImportError {
+	ImportError::Other(format!("{:?}", e).into())
+}
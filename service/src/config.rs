@@ -24,6 +24,14 @@ pub struct Config {
 	pub mu_ra_external_address: Option<String>,
 	/// Port for mutual-remote attestation requests.
 	pub mu_ra_port: String,
+	/// Deny access to the secrets of NFTs that are listed for sale.
+	pub block_listed_nft_secrets: bool,
+	/// Number of workers needed to reconstruct a NFT secret that is shared across all registered
+	/// workers. 0 stores the secrets in full on every worker.
+	pub nft_secret_threshold: u8,
+	/// Interval in seconds at which the NFT secret database is backed up to IPFS. 0 disables the
+	/// periodic backup.
+	pub nft_db_backup_interval: u64,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -38,6 +46,9 @@ impl Config {
 		untrusted_worker_port: String,
 		mu_ra_external_address: Option<String>,
 		mu_ra_port: String,
+		block_listed_nft_secrets: bool,
+		nft_secret_threshold: u8,
+		nft_db_backup_interval: u64,
 	) -> Self {
 		Self {
 			node_ip,
@@ -49,6 +60,9 @@ impl Config {
 			untrusted_worker_port,
 			mu_ra_external_address,
 			mu_ra_port,
+			block_listed_nft_secrets,
+			nft_secret_threshold,
+			nft_db_backup_interval,
 		}
 	}
 
@@ -109,6 +123,17 @@ impl From<&ArgMatches<'_>> for Config {
 			m.value_of("mu-ra-external-address")
 				.map(|url| add_port_if_necessary(url, mu_ra_port)),
 			mu_ra_port.to_string(),
+			m.is_present("block-listed-nft-secrets"),
+			m.value_of("nft-secret-threshold")
+				.map(|threshold| {
+					threshold.parse().expect("nft-secret-threshold must be a number from 0 to 255")
+				})
+				.unwrap_or_default(),
+			m.value_of("nft-db-backup-interval")
+				.map(|interval| {
+					interval.parse().expect("nft-db-backup-interval must be a number of seconds")
+				})
+				.unwrap_or_default(),
 		)
 	}
 }
@@ -151,6 +176,9 @@ mod test {
 		assert!(config.trusted_external_worker_address.is_none());
 		assert!(config.untrusted_external_worker_address.is_none());
 		assert!(config.mu_ra_external_address.is_none());
+		assert!(!config.block_listed_nft_secrets);
+		assert_eq!(config.nft_secret_threshold, 0);
+		assert_eq!(config.nft_db_backup_interval, 0);
 	}
 
 	#[test]
@@ -164,6 +192,37 @@ mod test {
 		assert_eq!(config.worker_ip, expected_worker_ip);
 	}
 
+	#[test]
+	fn block_listed_nft_secrets_is_set_correctly_for_set_flag() {
+		let mut args = ArgMatches::default();
+		args.args = HashMap::from([("block-listed-nft-secrets", Default::default())]);
+		let config = Config::from(&args);
+
+		assert!(config.block_listed_nft_secrets);
+	}
+
+	#[test]
+	fn nft_secret_threshold_is_set_correctly_for_given_input() {
+		let mut args = ArgMatches::default();
+		args.args = HashMap::from([("nft-secret-threshold", Default::default())]);
+		// Workaround because MatchedArg is private.
+		args.args.get_mut("nft-secret-threshold").unwrap().vals = vec!["2".into()];
+		let config = Config::from(&args);
+
+		assert_eq!(config.nft_secret_threshold, 2);
+	}
+
+	#[test]
+	fn nft_db_backup_interval_is_set_correctly_for_given_input() {
+		let mut args = ArgMatches::default();
+		args.args = HashMap::from([("nft-db-backup-interval", Default::default())]);
+		// Workaround because MatchedArg is private.
+		args.args.get_mut("nft-db-backup-interval").unwrap().vals = vec!["3600".into()];
+		let config = Config::from(&args);
+
+		assert_eq!(config.nft_db_backup_interval, 3600);
+	}
+
 	#[test]
 	fn check_correct_config_assignment_for_given_input() {
 		let node_ip = "ws://12.1.58.1";
@@ -20,6 +20,7 @@ use crate::{
 	node_api_factory::{CreateNodeApi, GlobalUrlNodeApiFactory},
 	ocall_bridge::{
 		bridge_api::Bridge as OCallBridge, component_factory::OCallBridgeComponentFactory,
+		ipfs_ocall::read_from_ipfs,
 	},
 	parentchain_block_syncer::{ParentchainBlockSyncer, SyncParentchainBlocks},
 	utils::{check_files, extract_shard},
@@ -73,6 +74,7 @@ mod config;
 mod enclave;
 mod error;
 mod globals;
+mod nft_db_backup;
 mod node_api_factory;
 mod ocall_bridge;
 mod parentchain_block_syncer;
@@ -131,6 +133,15 @@ fn main() {
 			enclave.as_ref(),
 			smatches.is_present("skip-ra"),
 		);
+	} else if matches.is_present("backup-nft-db") {
+		info!("*** Back up the NFT secret db to IPFS");
+		let cid = nft_db_backup::backup_nft_db(enclave.as_ref()).unwrap();
+		println!("[+] NFT secret db backed up to IPFS: {}", cid);
+	} else if let Some(smatches) = matches.subcommand_matches("restore-nft-db") {
+		let cid = smatches.value_of("cid").unwrap();
+		info!("*** Restore the NFT secret db from IPFS backup {}", cid);
+		nft_db_backup::restore_nft_db(enclave.as_ref(), cid, read_from_ipfs).unwrap();
+		println!("[+] NFT secret db restored successfully");
 	} else if matches.is_present("shielding-key") {
 		info!("*** Get the public key from the TEE\n");
 		let pubkey = enclave.get_rsa_shielding_pubkey().unwrap();
@@ -227,6 +238,21 @@ fn start_worker<E>(
 		)
 	});
 
+	// ------------------------------------------------------------------------
+	// Configure how NFT secrets are distributed among the registered workers.
+	enclave
+		.init_nft_secret_sharing(config.nft_secret_threshold, skip_ra)
+		.expect("Could not initialize NFT secret sharing");
+
+	// ------------------------------------------------------------------------
+	// Periodically back up the NFT secrets to IPFS.
+	if config.nft_db_backup_interval > 0 {
+		nft_db_backup::start_periodic_backup(
+			enclave.clone(),
+			Duration::from_secs(config.nft_db_backup_interval),
+		);
+	}
+
 	// ------------------------------------------------------------------------
 	// Start trusted worker rpc server.
 	let direct_invocation_server_addr = config.trusted_worker_url_internal();
@@ -0,0 +1,62 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+*/
+
+//! Encrypted backups of the NFT secret database to IPFS.
+
+use crate::{
+	error::{Error, ServiceResult as Result},
+	ocall_bridge::bridge_api::Cid,
+};
+use itp_enclave_api::enclave_base::EnclaveBase;
+use itp_settings::worker::IPFS_CID_SIZE;
+use log::*;
+use std::{sync::Arc, thread, time::Duration};
+
+/// Writes an encrypted backup of the NFT secret database to IPFS and returns its CID.
+pub(crate) fn backup_nft_db<E: EnclaveBase>(enclave_api: &E) -> Result<String> {
+	Ok(enclave_api.backup_nft_db()?)
+}
+
+/// Fetches the backup with the given CID from IPFS by `read_ipfs`, and imports it into the NFT
+/// secret database.
+pub(crate) fn restore_nft_db<E, R>(enclave_api: &E, cid: &str, read_ipfs: R) -> Result<()>
+where
+	E: EnclaveBase,
+	R: FnOnce(Cid) -> std::result::Result<Vec<u8>, String>,
+{
+	if cid.len() != IPFS_CID_SIZE {
+		return Err(Error::Custom(format!("invalid CID: {}", cid).into()))
+	}
+	let mut ipfs_cid: Cid = [0; IPFS_CID_SIZE];
+	ipfs_cid.clone_from_slice(cid.as_bytes());
+
+	let backup = read_ipfs(ipfs_cid).map_err(|e| Error::Custom(e.into()))?;
+	Ok(enclave_api.restore_nft_db(cid, &backup)?)
+}
+
+/// Starts a thread that backs up the NFT secret database every `interval`.
+pub(crate) fn start_periodic_backup<E: EnclaveBase>(enclave_api: Arc<E>, interval: Duration) {
+	thread::Builder::new()
+		.name("nft_db_backup".to_owned())
+		.spawn(move || loop {
+			thread::sleep(interval);
+			match backup_nft_db(enclave_api.as_ref()) {
+				Ok(cid) => info!("Backed up NFT secret db to IPFS: {}", cid),
+				Err(e) => error!("Failed to back up NFT secret db: {:?}", e),
+			}
+		})
+		.unwrap();
+}
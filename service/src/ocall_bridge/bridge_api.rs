@@ -105,6 +105,8 @@ pub enum OCallBridgeError {
 	GetUpdateInfo(sgx_status_t),
 	#[error("GetIasSocket Error: {0}")]
 	GetIasSocket(String),
+	#[error("GetMuRaSocket Error: {0}")]
+	GetMuRaSocket(String),
 	#[error("Propose sidechain block failed: {0}")]
 	ProposeSidechainBlock(String),
 	#[error("Sending extrinsics to parentchain failed: {0}")]
@@ -122,6 +124,7 @@ impl From<OCallBridgeError> for sgx_status_t {
 			OCallBridgeError::InitQuote(s) => s,
 			OCallBridgeError::GetUpdateInfo(s) => s,
 			OCallBridgeError::GetIasSocket(_) => sgx_status_t::SGX_ERROR_UNEXPECTED,
+			OCallBridgeError::GetMuRaSocket(_) => sgx_status_t::SGX_ERROR_UNEXPECTED,
 			OCallBridgeError::ProposeSidechainBlock(_) => sgx_status_t::SGX_ERROR_UNEXPECTED,
 			OCallBridgeError::SendExtrinsicsToParentchain(_) => sgx_status_t::SGX_ERROR_UNEXPECTED,
 			OCallBridgeError::IpfsError(_) => sgx_status_t::SGX_ERROR_UNEXPECTED,
@@ -141,6 +144,9 @@ pub trait RemoteAttestationBridge {
 	/// get the intel attestation service socket
 	fn get_ias_socket(&self) -> OCallBridgeResult<i32>;
 
+	/// get a socket connected to the mutual remote attestation server of a registered worker
+	fn get_mu_ra_socket(&self, worker_url: String) -> OCallBridgeResult<i32>;
+
 	/// retrieve the quote from intel
 	fn get_quote(
 		&self,
@@ -0,0 +1,120 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+	Copyright (C) 2017-2019 Baidu, Inc. All Rights Reserved.
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+use crate::ocall_bridge::bridge_api::{Bridge, RemoteAttestationBridge};
+use log::*;
+use sgx_types::{c_int, sgx_status_t};
+use std::{slice, sync::Arc};
+
+/// # Safety
+///
+/// FFI are always unsafe
+#[no_mangle]
+pub unsafe extern "C" fn ocall_get_mu_ra_socket(
+	worker_url: *const u8,
+	worker_url_size: u32,
+	ret_fd: *mut c_int,
+) -> sgx_status_t {
+	get_mu_ra_socket(worker_url, worker_url_size, ret_fd, Bridge::get_ra_api())
+}
+
+fn get_mu_ra_socket(
+	worker_url: *const u8,
+	worker_url_size: u32,
+	ret_fd: *mut c_int,
+	ra_api: Arc<dyn RemoteAttestationBridge>,
+) -> sgx_status_t {
+	debug!("    Entering ocall_get_mu_ra_socket");
+	let worker_url_slice = unsafe { slice::from_raw_parts(worker_url, worker_url_size as usize) };
+	let worker_url = match std::str::from_utf8(worker_url_slice) {
+		Ok(url) => url.to_string(),
+		Err(e) => {
+			error!("[-]  Invalid worker url: {:?}", e);
+			return sgx_status_t::SGX_ERROR_INVALID_PARAMETER
+		},
+	};
+
+	match ra_api.get_mu_ra_socket(worker_url) {
+		Ok(s) => {
+			unsafe {
+				*ret_fd = s;
+			}
+			sgx_status_t::SGX_SUCCESS
+		},
+		Err(e) => {
+			error!("[-]  Failed to get MU-RA socket: {:?}", e);
+			e.into()
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+
+	use super::*;
+	use crate::ocall_bridge::bridge_api::{MockRemoteAttestationBridge, OCallBridgeError};
+	use mockall::predicate::eq;
+	use std::sync::Arc;
+
+	#[test]
+	fn get_socket_sets_pointer_result() {
+		let expected_socket = 4321i32;
+		let worker_url = "wss://127.0.0.1:2000";
+
+		let mut ra_ocall_api_mock = MockRemoteAttestationBridge::new();
+		ra_ocall_api_mock
+			.expect_get_mu_ra_socket()
+			.with(eq(worker_url.to_string()))
+			.times(1)
+			.return_const(Ok(expected_socket));
+
+		let mut mu_ra_sock: i32 = 0;
+
+		let ret_status = get_mu_ra_socket(
+			worker_url.as_ptr(),
+			worker_url.len() as u32,
+			&mut mu_ra_sock as *mut i32,
+			Arc::new(ra_ocall_api_mock),
+		);
+
+		assert_eq!(ret_status, sgx_status_t::SGX_SUCCESS);
+		assert_eq!(mu_ra_sock, expected_socket);
+	}
+
+	#[test]
+	fn given_error_from_ocall_impl_then_return_sgx_error() {
+		let worker_url = "wss://127.0.0.1:2000";
+
+		let mut ra_ocall_api_mock = MockRemoteAttestationBridge::new();
+		ra_ocall_api_mock
+			.expect_get_mu_ra_socket()
+			.times(1)
+			.return_const(Err(OCallBridgeError::GetMuRaSocket("test error".to_string())));
+
+		let mut mu_ra_sock: i32 = 0;
+		let ret_status = get_mu_ra_socket(
+			worker_url.as_ptr(),
+			worker_url.len() as u32,
+			&mut mu_ra_sock as *mut i32,
+			Arc::new(ra_ocall_api_mock),
+		);
+
+		assert_ne!(ret_status, sgx_status_t::SGX_SUCCESS);
+		assert_eq!(mu_ra_sock, 0);
+	}
+}
@@ -24,7 +24,6 @@ use std::{
 	fs::File,
 	io::{Cursor, Write},
 	str,
-	sync::mpsc::channel,
 };
 
 pub struct IpfsOCall;
@@ -32,7 +31,7 @@ pub struct IpfsOCall;
 impl IpfsBridge for IpfsOCall {
 	fn write_to_ipfs(&self, data: &'static [u8]) -> OCallBridgeResult<Cid> {
 		debug!("    Entering ocall_write_ipfs");
-		Ok(write_to_ipfs(data))
+		write_to_ipfs(data).map_err(OCallBridgeError::IpfsError)
 	}
 
 	fn read_from_ipfs(&self, cid: Cid) -> OCallBridgeResult<()> {
@@ -59,7 +58,7 @@ fn create_file(filename: &str, result: &[u8]) -> Result<(), String> {
 }
 
 #[tokio::main]
-async fn write_to_ipfs(data: &'static [u8]) -> Cid {
+async fn write_to_ipfs(data: &'static [u8]) -> Result<Cid, String> {
 	// Creates an `IpfsClient` connected to the endpoint specified in ~/.ipfs/api.
 	// If not found, tries to connect to `localhost:5001`.
 	let client = IpfsClient::default();
@@ -70,18 +69,15 @@ async fn write_to_ipfs(data: &'static [u8]) -> Cid {
 	}
 
 	let datac = Cursor::new(data);
-	let (tx, rx) = channel();
-
-	match client.add(datac).await {
-		Ok(res) => {
-			info!("Result Hash {}", res.hash);
-			tx.send(res.hash.into_bytes()).unwrap();
-		},
-		Err(e) => eprintln!("error adding file: {}", e),
-	}
+	let res = client.add(datac).await.map_err(|e| format!("error adding file: {}", e))?;
+	info!("Result Hash {}", res.hash);
+
 	let mut cid: Cid = [0; 46];
-	cid.clone_from_slice(&rx.recv().unwrap());
-	cid
+	if res.hash.len() != cid.len() {
+		return Err(format!("unexpected CID: {}", res.hash))
+	}
+	cid.clone_from_slice(res.hash.as_bytes());
+	Ok(cid)
 }
 
 #[tokio::main]
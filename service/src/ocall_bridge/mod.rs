@@ -20,6 +20,6 @@ pub mod bridge_api;
 pub mod component_factory;
 
 mod ffi;
-mod ipfs_ocall;
+pub mod ipfs_ocall;
 mod remote_attestation_ocall;
 mod worker_on_chain_ocall;
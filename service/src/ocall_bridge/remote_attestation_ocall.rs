@@ -19,14 +19,18 @@
 use crate::ocall_bridge::bridge_api::{
 	OCallBridgeError, OCallBridgeResult, RemoteAttestationBridge,
 };
+use itc_rpc_client::direct_client::{DirectApi, DirectClient as DirectWorkerApi};
 use itp_enclave_api::remote_attestation::RemoteAttestationCallBacks;
 use sgx_types::*;
 use std::{
 	net::{SocketAddr, TcpStream},
 	os::unix::io::IntoRawFd,
 	sync::Arc,
+	time::Duration,
 };
 
+const MU_RA_SOCKET_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct RemoteAttestationOCall<E> {
 	enclave_api: Arc<E>,
 }
@@ -61,6 +65,22 @@ where
 		Ok(sock.into_raw_fd())
 	}
 
+	fn get_mu_ra_socket(&self, worker_url: String) -> OCallBridgeResult<i32> {
+		let mu_ra_url = DirectWorkerApi::new(worker_url)
+			.get_mu_ra_url()
+			.map_err(|e| OCallBridgeError::GetMuRaSocket(format!("{:?}", e)))?;
+
+		let sock = TcpStream::connect(&mu_ra_url).map_err(|e| {
+			OCallBridgeError::GetMuRaSocket(format!("Connecting to {} failed: {:?}", mu_ra_url, e))
+		})?;
+		// An unresponsive worker must not block the enclave thread forever.
+		sock.set_read_timeout(Some(MU_RA_SOCKET_TIMEOUT))
+			.and_then(|_| sock.set_write_timeout(Some(MU_RA_SOCKET_TIMEOUT)))
+			.map_err(|e| OCallBridgeError::GetMuRaSocket(format!("{:?}", e)))?;
+
+		Ok(sock.into_raw_fd())
+	}
+
 	fn get_quote(
 		&self,
 		revocation_list: Vec<u8>,
@@ -46,5 +46,8 @@ pub fn local_worker_config(
 		untrusted_worker_port,
 		None,
 		mu_ra_port,
+		false,
+		0,
+		0,
 	)
 }
@@ -21,12 +21,25 @@ use sgx_crypto_helper::rsa3072::Rsa3072PubKey;
 use sp_core::ed25519;
 use sp_finality_grandpa::VersionedAuthorityList;
 use sp_runtime::traits::Header;
+use std::sync::RwLock;
+
+/// CID of the NFT secret db backup the mock pretends to have written.
+pub const NFT_DB_BACKUP_CID: &str = "QmSaFjwJ2QtS3rZDKzC98XEzv2bqT4TfpWLCpphPPwyQTr";
 
 /// mock for EnclaveBase - use in tests
-pub struct EnclaveBaseMock;
+#[derive(Default)]
+pub struct EnclaveBaseMock {
+	/// CID and content of the NFT secret db backups restored so far.
+	pub restored_nft_db_backups: RwLock<Vec<(String, Vec<u8>)>>,
+}
 
 impl EnclaveBase for EnclaveBaseMock {
-	fn init(&self, _mu_ra_url: &str, _untrusted_url: &str) -> EnclaveResult<()> {
+	fn init(
+		&self,
+		_mu_ra_url: &str,
+		_untrusted_url: &str,
+		_block_listed_nft_secrets: bool,
+	) -> EnclaveResult<()> {
 		Ok(())
 	}
 
@@ -47,6 +60,10 @@ impl EnclaveBase for EnclaveBaseMock {
 		unimplemented!()
 	}
 
+	fn init_nft_secret_sharing(&self, _: u8, _: bool) -> EnclaveResult<()> {
+		unimplemented!()
+	}
+
 	fn get_rsa_shielding_pubkey(&self) -> EnclaveResult<Rsa3072PubKey> {
 		unreachable!()
 	}
@@ -58,4 +75,16 @@ impl EnclaveBase for EnclaveBaseMock {
 	fn get_mrenclave(&self) -> EnclaveResult<[u8; MR_ENCLAVE_SIZE]> {
 		Ok([1u8; MR_ENCLAVE_SIZE])
 	}
+
+	fn backup_nft_db(&self) -> EnclaveResult<String> {
+		Ok(NFT_DB_BACKUP_CID.to_owned())
+	}
+
+	fn restore_nft_db(&self, cid: &str, backup: &[u8]) -> EnclaveResult<()> {
+		self.restored_nft_db_backups
+			.write()
+			.unwrap()
+			.push((cid.to_owned(), backup.to_vec()));
+		Ok(())
+	}
 }
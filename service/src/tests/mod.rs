@@ -30,6 +30,9 @@ pub mod worker;
 #[cfg(test)]
 pub mod mocks;
 
+#[cfg(test)]
+pub mod nft_db_backup_test;
+
 #[cfg(test)]
 pub mod parentchain_block_syncer_test;
 
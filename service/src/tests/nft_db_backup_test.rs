@@ -0,0 +1,59 @@
+/*
+	Copyright 2021 Integritee AG and Supercomputing Systems AG
+
+	Licensed under the Apache License, Version 2.0 (the "License");
+	you may not use this file except in compliance with the License.
+	You may obtain a copy of the License at
+
+		http://www.apache.org/licenses/LICENSE-2.0
+
+	Unless required by applicable law or agreed to in writing, software
+	distributed under the License is distributed on an "AS IS" BASIS,
+	WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+	See the License for the specific language governing permissions and
+	limitations under the License.
+
+*/
+
+use crate::{
+	nft_db_backup::{backup_nft_db, restore_nft_db},
+	ocall_bridge::bridge_api::Cid,
+	tests::mocks::enclave_api_mock::{EnclaveBaseMock, NFT_DB_BACKUP_CID},
+};
+use std::str;
+
+#[test]
+fn restore_imports_the_backup_read_from_ipfs() {
+	let enclave_api_mock = EnclaveBaseMock::default();
+	let cid = backup_nft_db(&enclave_api_mock).unwrap();
+
+	restore_nft_db(&enclave_api_mock, &cid, |cid: Cid| {
+		assert_eq!(str::from_utf8(&cid).unwrap(), NFT_DB_BACKUP_CID);
+		Ok(b"backup".to_vec())
+	})
+	.unwrap();
+
+	let restored = enclave_api_mock.restored_nft_db_backups.read().unwrap();
+	assert_eq!(*restored, vec![(NFT_DB_BACKUP_CID.to_owned(), b"backup".to_vec())]);
+}
+
+#[test]
+fn restore_rejects_invalid_cid() {
+	let enclave_api_mock = EnclaveBaseMock::default();
+
+	let result = restore_nft_db(&enclave_api_mock, "Qm", |_| unreachable!());
+
+	assert!(result.is_err());
+	assert!(enclave_api_mock.restored_nft_db_backups.read().unwrap().is_empty());
+}
+
+#[test]
+fn restore_fails_if_backup_cannot_be_read_from_ipfs() {
+	let enclave_api_mock = EnclaveBaseMock::default();
+
+	let result =
+		restore_nft_db(&enclave_api_mock, NFT_DB_BACKUP_CID, |_| Err("not found".to_owned()));
+
+	assert!(result.is_err());
+	assert!(enclave_api_mock.restored_nft_db_backups.read().unwrap().is_empty());
+}